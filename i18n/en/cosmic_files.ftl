@@ -9,6 +9,7 @@ notification-in-progress = File operations are in progress.
 trash = Trash
 recents = Recents
 undo = Undo
+redo = Redo
 today = Today
 
 # Desktop view options
@@ -40,21 +41,111 @@ operations-running-finished = {$running} {$running ->
   } running ({$percent}%), {$finished} finished...
 pause = Pause
 resume = Resume
+queued = Waiting for the same drive ({$position} of {$total} queued)
+move-up = Move up
+move-down = Move down
+defer = Move to end of queue
+start-now = Start now
 
 # Dialogs
 
 ## Compress Dialog
 create-archive = Create archive
+compression-level = Compression level
+compression-fast = Fastest
+compression-default = Balanced
+compression-best = Smallest
 
 ## Extract Dialog
 extract-password-required = Password required
 extract-to = Extract To...
 extract-to-title = Extract to folder
+name-encoding = File name encoding
+name-encoding-auto = Detect automatically
+name-encoding-utf8 = Unicode (UTF-8)
+name-encoding-cp437 = Western (CP437)
+name-encoding-shift-jis = Japanese (Shift-JIS)
+name-encoding-cp866 = Cyrillic (CP866)
+archive-volume-missing = Volume "{$name}" of the split archive is missing
+archive-volume-may-be-missing = {$error}. Volume "{$name}" of the split archive may be missing.
+
+## Add to Archive Dialog
+add-to-archive = Add to archive
+add-to-archive-description = Add {$items} {$items ->
+        [one] item
+        *[other] items
+    } to "{$name}"?
+add-to-archive-password = Added entries are encrypted if a password is set.
+add = Add
+
+## Archive Test Dialog
+archive-test-title = Tested "{$name}"
+archive-test-passed = All {$items} {$items ->
+        [one] entry
+        *[other] entries
+    } can be read.
+archive-test-failed = Found {$problems} {$problems ->
+        [one] problem
+        *[other] problems
+    } while reading {$items} {$items ->
+        [one] entry
+        *[other] entries
+    }.
+archive-test-compressed = {$size} compressed
+archive-test-encrypted = Encrypted
+close = Close
+
+## Failed Operation Dialog
+operation-partial-title = Some items failed
+retry-failed = Retry failed
+export-log = Export log
+verify-failed-title = Some copies do not match
+
+## Confirm Plan Dialog
+plan-title = Review operation
+plan-description = {$files} {$files ->
+        [one] file
+        *[other] files
+    } ({$size}) will be transferred.
+plan-conflicts = {$items} existing {$items ->
+        [one] file conflicts
+        *[other] files conflict
+    } with the files being transferred:
+plan-ask = Ask for each file
+plan-replace-all = Replace all
+plan-skip-all = Skip all
+plan-keep-both = Keep both for all
+start = Start
 
 ## Empty Trash Dialog
 empty-trash = Empty trash
 empty-trash-warning = Are you sure you want to permanently delete all the items in Trash?
 
+## Unfinished Operation Dialog
+unfinished-operation = Unfinished operation
+unfinished-operation-description = Resume continues where it stopped. Roll back removes everything it created.
+roll-back = Roll back
+interrupted-compressing = Compressing {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}" was interrupted.
+interrupted-copying = Copying {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}" was interrupted.
+interrupted-duplicating = Duplicating {$items} {$items ->
+        [one] item
+        *[other] items
+    } in "{$from}" was interrupted.
+interrupted-extracting = Extracting {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}" was interrupted.
+interrupted-moving = Moving {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}" was interrupted.
+
 ## Mount Error Dialog
 mount-error = Unable to access drive
 
@@ -95,10 +186,56 @@ permanently-delete-question = Permanently delete
 delete = Delete
 permanently-delete-warning = Are you sure you want to permanently delete {$target}? This cannot be undone.
 
+## Shred Dialog
+shred-question = Shred
+shred = Shred
+shred-warning = Are you sure you want to overwrite {$target} {$passes ->
+        [one] once
+        *[other] {$passes} times
+    } and permanently delete it? This cannot be undone.
+shred-warning-remote = Items on network drives may be kept by the server after they are overwritten.
+shred-warning-copy-on-write = The {$fs_type} file system writes changes to new locations, so the original contents may not be overwritten.
+shred-warning-solid-state = Solid state drives may keep copies of the original contents after they are overwritten.
+
+## Change Permissions Dialog
+change-permissions-title = Change permissions of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+permissions-file-mode = File mode
+permissions-dir-mode = Folder mode
+permissions-recursive = Apply to enclosed files and folders
+permissions-unchanged = Unchanged
+permissions-invalid-mode = "{$mode}" is not a valid octal mode
+permissions-unknown-owner = There is no user named "{$name}"
+permissions-unknown-group = There is no group named "{$name}"
+apply = Apply
+
 ## Rename Dialog
 rename-file = Rename file
 rename-folder = Rename folder
 
+## Batch Rename Dialog
+batch-rename-title = Rename {$items} items
+batch-rename-find = Find
+batch-rename-replace = Replace with
+batch-rename-regex = Use regular expressions
+batch-rename-case-keep = Keep case
+batch-rename-case-lower = lowercase
+batch-rename-case-upper = UPPERCASE
+batch-rename-case-title = Title Case
+batch-rename-template = New name
+batch-rename-tokens = {"{"}name{"}"} name, {"{"}ext{"}"} extension, {"{"}n{"}"} number, {"{"}date{"}"} modified date, {"{"}folder{"}"} parent folder, {"{"}exif{"}"} date taken
+batch-rename-start = Start numbering at
+batch-rename-padding = Number of digits
+batch-rename-preview = Preview
+batch-rename-problems = {$items} {$items ->
+        [one] item cannot
+        *[other] items cannot
+    } be renamed
+batch-rename-duplicate = Another item would have the same name
+batch-rename-invalid-number = "{$value}" is not a valid number
+
 ## Replace Dialog
 replace = Replace
 replace-title = "{$filename}" already exists in this location.
@@ -186,8 +323,23 @@ pending = Pending
 progress = {$percent}%
 progress-cancelled = {$percent}%, cancelled
 progress-paused = {$percent}%, paused
+progress-files = {$done} of {$total} files
+progress-bytes = {$done} of {$total}
+progress-rate = {$rate}/s
+progress-eta = {$eta} left
+duration-hours = {$hours} h {$minutes} min
+duration-minutes = {$minutes} min {$seconds} s
+duration-seconds = {$seconds} s
 failed = Failed
 complete = Complete
+adding-to-archive = Adding {$items} {$items ->
+        [one] item
+        *[other] items
+    } to "{$name}" ({$progress})...
+added-to-archive = Added {$items} {$items ->
+        [one] item
+        *[other] items
+    } to "{$name}"
 compressing = Compressing {$items} {$items ->
         [one] item
         *[other] items
@@ -197,6 +349,7 @@ compressed = Compressed {$items} {$items ->
         *[other] items
     } from "{$from}" to "{$to}"
 copy_noun = Copy
+link_noun = Link
 creating = Creating "{$name}" in "{$parent}"
 created = Created "{$name}" in "{$parent}"
 copying = Copying {$items} {$items ->
@@ -229,6 +382,22 @@ setting-executable-and-launching = Setting "{$name}" as executable and launching
 set-executable-and-launched = Set "{$name}" as executable and launched
 setting-permissions = Setting permissions for "{$name}" to {$mode}
 set-permissions = Set permissions for "{$name}" to {$mode}
+duplicating = Duplicating {$items} {$items ->
+        [one] item
+        *[other] items
+    } in "{$from}" ({$progress})...
+duplicated = Duplicated {$items} {$items ->
+        [one] item
+        *[other] items
+    } in "{$from}"
+linking = Linking {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" in "{$to}" ({$progress})...
+linked = Linked {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" in "{$to}"
 moving = Moving {$items} {$items ->
         [one] item
         *[other] items
@@ -245,6 +414,22 @@ permanently-deleted = Permanently deleted {$items} {$items ->
         [one] item
         *[other] items
     }
+shredding = Shredding {$items} {$items ->
+        [one] item
+        *[other] items
+    } ({$progress})...
+shredded = Shredded {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+testing = Testing {$items} {$items ->
+        [one] archive
+        *[other] archives
+    } ({$progress})...
+tested = Tested {$items} {$items ->
+        [one] archive
+        *[other] archives
+    }
 removing-from-recents = Removing {$items} {$items ->
         [one] item
         *[other] items
@@ -253,8 +438,24 @@ removed-from-recents = Removed {$items} {$items ->
         [one] item
         *[other] items
     } from {recents}
+changing-permissions = Changing permissions of {$items} {$items ->
+        [one] item
+        *[other] items
+    } ({$progress})...
+changed-permissions = Changed permissions of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
 renaming = Renaming "{$from}" to "{$to}"
 renamed = Renamed "{$from}" to "{$to}"
+renaming-items = Renaming {$items} {$items ->
+        [one] item
+        *[other] items
+    } ({$progress})...
+renamed-items = Renamed {$items} {$items ->
+        [one] item
+        *[other] items
+    }
 restoring = Restoring {$items} {$items ->
         [one] item
         *[other] items
@@ -264,6 +465,36 @@ restored = Restored {$items} {$items ->
         *[other] items
     } from {trash}
 unknown-folder = unknown folder
+verify-failed = {$items} copied {$items ->
+        [one] file does
+        *[other] files do
+    } not match the original:
+verify-mismatch = Contents differ from the original
+preserve-failed = Could not preserve {$attribute} of "{$path}": {$error}
+preserve-owner-failed = Could not preserve the owner of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+operation-partial = {$items} {$items ->
+        [one] item
+        *[other] items
+    } could not be processed:
+special-file-skipped = Skipped special file "{$path}"
+special-file-failed = Could not create special file "{$path}": {$error}
+extract-rejected-path = Skipped "{$path}", which is outside of the destination folder
+extract-rejected-link = Skipped link "{$path}", which points outside of the destination folder
+extract-rejected-through-link = Skipped "{$path}", which would be written through a link
+extract-size-exceeded = Extraction stopped after {$size}, which is the size limit for extracted archives
+extract-ratio-exceeded = Extraction stopped because the archive expands more than {$ratio} times its size
+archive-test-problems = Found {$problems} {$problems ->
+        [one] problem
+        *[other] problems
+    } in "{$name}"
+metadata = metadata
+extended-attribute = extended attribute "{$name}"
+extended-attributes = extended attributes
+permissions = permissions
+timestamps = timestamps
 
 ## Open with
 menu-open-with = Open with...
@@ -295,12 +526,45 @@ type-to-search = Type to Search
 type-to-search-recursive = Searches the current folder and all sub-folders
 type-to-search-enter-path = Enters the path to the directory or file
 
+### Operations
+operations = Operations
+continue-on-error = Continue after errors
+continue-on-error-description = Skip files that cannot be copied, moved, deleted or extracted, and list them when the operation finishes
+copy-special-files = Copy special files
+copy-special-files-description = Recreate named pipes, sockets and device nodes instead of skipping them
+extract-ownership = Restore ownership when extracting
+extract-ownership-description = Give extracted files the owner and group stored in the archive, when you are allowed to
+extract-size-limit = Limit extracted size
+extract-size-limit-description = Stop extracting archives that expand beyond this size or far beyond the size of the archive
+extract-size-limit-none = No limit
+extract-size-limit-size = {$size}
+preview-operations = Review large transfers
+preview-operations-description = Show the number of files, their size and any conflicts before copying or moving
+preview-never = Never
+preview-size = {$size} or more
+preserve-metadata = Preserve file attributes
+preserve-metadata-description = Keep timestamps, permissions, ownership and extended attributes when copying
+relative-links = Relative links
+relative-links-description = Point new links at their targets relative to the link, so they keep working when moved together
+shred-passes = Overwrite passes when shredding
+shred-passes-count = {$passes} {$passes ->
+        [one] pass
+        *[other] passes
+    }
+shred-zeros = Shred with zeros
+shred-zeros-description = Overwrite shredded files with zeros instead of random data
+verify-copies = Verify copied files
+verify-copies-description = Read back each copied file and compare its checksum with the original
+
 # Context menu
 add-to-sidebar = Add to sidebar
+browse-archive = Browse archive
 compress = Compress
 delete-permanently = Delete permanently
+shred-menu = Shred...
 eject = Eject
 extract-here = Extract
+test-archive = Test archive
 new-file = New file...
 new-folder = New folder...
 open-in-terminal = Open in terminal
@@ -326,6 +590,7 @@ new-tab = New tab
 new-window = New window
 reload-folder = Reload folder
 rename = Rename...
+change-permissions = Permissions...
 close-tab = Close tab
 quit = Quit
 
@@ -334,6 +599,10 @@ edit = Edit
 cut = Cut
 copy = Copy
 paste = Paste
+paste-symlink = Paste as link
+paste-hard-link = Paste as hard link
+make-link = Make link
+duplicate = Duplicate
 select-all = Select all
 
 ## View
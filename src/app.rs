@@ -47,6 +47,7 @@ use notify_debouncer_full::{
     notify::{self, RecommendedWatcher, Watcher},
     DebouncedEvent, Debouncer, FileIdMap,
 };
+use serde::{Deserialize, Serialize};
 use slotmap::Key as SlotMapKey;
 use std::{
     any::TypeId,
@@ -67,12 +68,14 @@ use trash::TrashItem;
 use wayland_client::{protocol::wl_output::WlOutput, Proxy};
 
 use crate::{
+    archive::{self, NameEncoding},
+    batch_rename::{self, BatchRename, CaseChange, RenamePreview},
     clipboard::{ClipboardCopy, ClipboardKind, ClipboardPaste},
     config::{
-        AppTheme, Config, DesktopConfig, Favorite, IconSizes, TabConfig, TimeConfig, TypeToSearch,
-        TIME_CONFIG_ID,
+        AppTheme, Config, DesktopConfig, Favorite, IconSizes, OperationsConfig, ShredPattern,
+        TabConfig, TimeConfig, TypeToSearch, TIME_CONFIG_ID,
     },
-    dialog::{Dialog, DialogKind, DialogMessage, DialogResult},
+    dialog::{Dialog, DialogChoice, DialogChoiceOption, DialogKind, DialogMessage, DialogResult},
     fl, home_dir,
     key_bind::key_binds,
     localize::LANGUAGE_SORTER,
@@ -81,8 +84,10 @@ use crate::{
     mime_icon,
     mounter::{MounterAuth, MounterItem, MounterItems, MounterKey, MounterMessage, MOUNTERS},
     operation::{
-        Controller, Operation, OperationError, OperationErrorType, OperationSelection,
-        ReplaceResult,
+        scheduler::{operation_devices, Device},
+        shred_warnings, Controller, Journal, LinkKind, Operation, OperationError,
+        OperationErrorType, OperationPlan, OperationSelection, PermissionChanges, PlanResult,
+        ReplaceResult, Scheduler, UnfinishedOperation,
     },
     spawn_detached::spawn_detached,
     tab::{
@@ -112,6 +117,8 @@ pub struct Flags {
 pub enum Action {
     About,
     AddToSidebar,
+    BrowseArchive,
+    ChangePermissions,
     Compress,
     Copy,
     Cut,
@@ -120,6 +127,7 @@ pub enum Action {
     CosmicSettingsWallpaper,
     DesktopViewOptions,
     Delete,
+    Duplicate,
     EditHistory,
     EditLocation,
     Eject,
@@ -136,6 +144,7 @@ pub enum Action {
     ItemRight,
     ItemUp,
     LocationUp,
+    MakeLink,
     NewFile,
     NewFolder,
     Open,
@@ -145,8 +154,11 @@ pub enum Action {
     OpenTerminal,
     OpenWith,
     Paste,
+    PasteHardLink,
+    PasteSymlink,
     PermanentlyDelete,
     Preview,
+    Redo,
     Reload,
     RemoveFromRecents,
     Rename,
@@ -157,15 +169,18 @@ pub enum Action {
     SelectAll,
     SetSort(HeadingOptions, bool),
     Settings,
+    Shred,
     TabClose,
     TabNew,
     TabNext,
     TabPrev,
     TabViewGrid,
     TabViewList,
+    TestArchive,
     ToggleFoldersFirst,
     ToggleShowHidden,
     ToggleSort(HeadingOptions),
+    Undo,
     WindowClose,
     WindowNew,
     ZoomDefault,
@@ -179,6 +194,8 @@ impl Action {
         match self {
             Action::About => Message::ToggleContextPage(ContextPage::About),
             Action::AddToSidebar => Message::AddToSidebar(entity_opt),
+            Action::BrowseArchive => Message::BrowseArchive(entity_opt),
+            Action::ChangePermissions => Message::ChangePermissions(entity_opt),
             Action::Compress => Message::Compress(entity_opt),
             Action::Copy => Message::Copy(entity_opt),
             Action::Cut => Message::Cut(entity_opt),
@@ -187,6 +204,7 @@ impl Action {
             Action::CosmicSettingsWallpaper => Message::CosmicSettings("wallpaper"),
             Action::Delete => Message::Delete(entity_opt),
             Action::DesktopViewOptions => Message::DesktopViewOptions,
+            Action::Duplicate => Message::Duplicate(entity_opt),
             Action::EditHistory => Message::ToggleContextPage(ContextPage::EditHistory),
             Action::EditLocation => {
                 Message::TabMessage(entity_opt, tab::Message::EditLocationEnable)
@@ -207,6 +225,7 @@ impl Action {
             Action::ItemRight => Message::TabMessage(entity_opt, tab::Message::ItemRight),
             Action::ItemUp => Message::TabMessage(entity_opt, tab::Message::ItemUp),
             Action::LocationUp => Message::TabMessage(entity_opt, tab::Message::LocationUp),
+            Action::MakeLink => Message::MakeLink(entity_opt),
             Action::NewFile => Message::NewItem(entity_opt, false),
             Action::NewFolder => Message::NewItem(entity_opt, true),
             Action::Open => Message::TabMessage(entity_opt, tab::Message::Open(None)),
@@ -216,8 +235,11 @@ impl Action {
             Action::OpenTerminal => Message::OpenTerminal(entity_opt),
             Action::OpenWith => Message::OpenWithDialog(entity_opt),
             Action::Paste => Message::Paste(entity_opt),
+            Action::PasteHardLink => Message::PasteLink(entity_opt, LinkKind::Hard),
+            Action::PasteSymlink => Message::PasteLink(entity_opt, LinkKind::Symbolic),
             Action::PermanentlyDelete => Message::PermanentlyDelete(entity_opt),
             Action::Preview => Message::Preview(entity_opt),
+            Action::Redo => Message::Redo,
             Action::Reload => Message::TabMessage(entity_opt, tab::Message::Reload),
             Action::RemoveFromRecents => Message::RemoveFromRecents(entity_opt),
             Action::Rename => Message::Rename(entity_opt),
@@ -230,17 +252,20 @@ impl Action {
                 Message::TabMessage(entity_opt, tab::Message::SetSort(*sort, *dir))
             }
             Action::Settings => Message::ToggleContextPage(ContextPage::Settings),
+            Action::Shred => Message::Shred(entity_opt),
             Action::TabClose => Message::TabClose(entity_opt),
             Action::TabNew => Message::TabNew,
             Action::TabNext => Message::TabNext,
             Action::TabPrev => Message::TabPrev,
             Action::TabViewGrid => Message::TabView(entity_opt, tab::View::Grid),
             Action::TabViewList => Message::TabView(entity_opt, tab::View::List),
+            Action::TestArchive => Message::TestArchive(entity_opt),
             Action::ToggleFoldersFirst => Message::ToggleFoldersFirst,
             Action::ToggleShowHidden => Message::ToggleShowHidden,
             Action::ToggleSort(sort) => {
                 Message::TabMessage(entity_opt, tab::Message::ToggleSort(*sort))
             }
+            Action::Undo => Message::Undo,
             Action::WindowClose => Message::WindowClose,
             Action::WindowNew => Message::WindowNew,
             Action::ZoomDefault => Message::ZoomDefault(entity_opt),
@@ -301,6 +326,9 @@ impl MenuAction for NavMenuAction {
 pub enum Message {
     AddToSidebar(Option<Entity>),
     AppTheme(AppTheme),
+    BatchRenameUpdate(BatchRename),
+    BrowseArchive(Option<Entity>),
+    ChangePermissions(Option<Entity>),
     CloseId(window::Id),
     CloseToast(widget::ToastId),
     Compress(Option<Entity>),
@@ -315,11 +343,13 @@ pub enum Message {
     DialogCancel,
     DialogComplete,
     DragId(window::Id),
+    Duplicate(Option<Entity>),
     Eject,
     FileDialogMessage(DialogMessage),
     DialogPush(DialogPage),
     DialogUpdate(DialogPage),
     DialogUpdateComplete(DialogPage),
+    ExportFailedLog(u64),
     ExtractHere(Option<Entity>),
     ExtractTo(Option<Entity>),
     ExtractToResult(DialogResult),
@@ -327,6 +357,7 @@ pub enum Message {
     Focused(window::Id),
     Key(Modifiers, Key, Option<SmolStr>),
     LaunchUrl(String),
+    MakeLink(Option<Entity>),
     MaybeExit,
     ModifiersChanged(Modifiers),
     MounterItems(MounterKey, MounterItems),
@@ -356,24 +387,33 @@ pub enum Message {
     OpenWithBrowse,
     OpenWithDialog(Option<Entity>),
     OpenWithSelection(usize),
+    OperationsConfig(OperationsConfig),
     #[cfg(all(feature = "wayland", feature = "desktop-applet"))]
     Overlap(OverlapNotifyEvent, window::Id),
     Paste(Option<Entity>),
     PasteContents(PathBuf, ClipboardPaste),
+    PasteLink(Option<Entity>, LinkKind),
+    PasteLinkContents(PathBuf, ClipboardPaste, LinkKind),
     PendingCancel(u64),
     PendingCancelAll,
     PendingComplete(u64, OperationSelection),
+    PendingDefer(u64),
+    PendingDevices(u64, BTreeSet<Device>),
     PendingDismiss,
     PendingError(u64, OperationError),
     PendingPause(u64, bool),
     PendingPauseAll(bool),
+    PendingPromote(u64),
+    PendingReorder(u64, bool),
     PermanentlyDelete(Option<Entity>),
     Preview(Option<Entity>),
+    Redo,
     RescanTrash,
     RemoveFromRecents(Option<Entity>),
     Rename(Option<Entity>),
     ReplaceResult(ReplaceResult),
     RestoreFromTrash(Option<Entity>),
+    RollBackOperation,
     SaveSortNames,
     ScrollTab(i16),
     SearchActivate,
@@ -381,6 +421,7 @@ pub enum Message {
     SearchInput(String),
     SetShowDetails(bool),
     SetTypeToSearch(TypeToSearch),
+    Shred(Option<Entity>),
     SystemThemeModeChange,
     Size(Size),
     TabActivate(Entity),
@@ -398,11 +439,12 @@ pub enum Message {
         Option<Vec<PathBuf>>,
     ),
     TabView(Option<Entity>, tab::View),
+    TestArchive(Option<Entity>),
     TimeConfigChange(TimeConfig),
     ToggleContextPage(ContextPage),
     ToggleFoldersFirst,
     ToggleShowHidden,
-    Undo(usize),
+    Undo,
     UndoTrash(widget::ToastId, Arc<[PathBuf]>),
     UndoTrashStart(Vec<TrashItem>),
     WindowClose,
@@ -439,8 +481,16 @@ pub enum ContextPage {
     Settings,
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum ArchiveType {
+    SevenZ,
+    Tar,
+    #[cfg(feature = "bzip2")]
+    TarBz2,
+    #[cfg(feature = "xz2")]
+    TarXz,
+    #[cfg(feature = "zstd")]
+    TarZst,
     Tgz,
     #[default]
     Zip,
@@ -448,15 +498,80 @@ pub enum ArchiveType {
 
 impl ArchiveType {
     pub fn all() -> &'static [Self] {
-        &[Self::Tgz, Self::Zip]
+        &[
+            Self::SevenZ,
+            Self::Tar,
+            #[cfg(feature = "bzip2")]
+            Self::TarBz2,
+            #[cfg(feature = "xz2")]
+            Self::TarXz,
+            #[cfg(feature = "zstd")]
+            Self::TarZst,
+            Self::Tgz,
+            Self::Zip,
+        ]
     }
 
     pub fn extension(&self) -> &str {
         match self {
+            ArchiveType::SevenZ => ".7z",
+            ArchiveType::Tar => ".tar",
+            #[cfg(feature = "bzip2")]
+            ArchiveType::TarBz2 => ".tar.bz2",
+            #[cfg(feature = "xz2")]
+            ArchiveType::TarXz => ".tar.xz",
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst => ".tar.zst",
             ArchiveType::Tgz => ".tgz",
             ArchiveType::Zip => ".zip",
         }
     }
+
+    /// Format of an existing archive that items can be added to, from its mime type. 7z archives
+    /// are not included, as they cannot be rewritten entry by entry.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_mime(mime_icon::mime_for_path(path, None, false).essence_str())
+    }
+
+    /// Format of an archive with the mime type `essence`. Only tar and zip archives are matched,
+    /// as a single compressed file has the same mime type whether or not it holds a tar archive.
+    pub fn from_mime(essence: &str) -> Option<Self> {
+        match essence {
+            "application/x-compressed-tar" => Some(Self::Tgz),
+            "application/x-tar" => Some(Self::Tar),
+            "application/zip" => Some(Self::Zip),
+            #[cfg(feature = "bzip2")]
+            "application/x-bzip-compressed-tar" | "application/x-bzip2-compressed-tar" => {
+                Some(Self::TarBz2)
+            }
+            #[cfg(feature = "xz2")]
+            "application/x-xz-compressed-tar" => Some(Self::TarXz),
+            #[cfg(feature = "zstd")]
+            "application/x-zstd-compressed-tar" => Some(Self::TarZst),
+            _ => None,
+        }
+    }
+
+    /// Level passed to the compressor of this format, or `None` if it is not compressed
+    pub fn compression_level(&self, level: CompressionLevel) -> Option<u32> {
+        let (fast, default, best) = match self {
+            ArchiveType::Tar => return None,
+            ArchiveType::SevenZ => (1, 6, 9),
+            #[cfg(feature = "bzip2")]
+            ArchiveType::TarBz2 => (1, 6, 9),
+            #[cfg(feature = "xz2")]
+            ArchiveType::TarXz => (1, 6, 9),
+            // Levels above 19 need much more memory to extract
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst => (1, 3, 19),
+            ArchiveType::Tgz | ArchiveType::Zip => (1, 6, 9),
+        };
+        Some(match level {
+            CompressionLevel::Fast => fast,
+            CompressionLevel::Default => default,
+            CompressionLevel::Best => best,
+        })
+    }
 }
 
 impl AsRef<str> for ArchiveType {
@@ -465,15 +580,60 @@ impl AsRef<str> for ArchiveType {
     }
 }
 
+/// Trade-off between speed and size when compressing, mapped to a level for each format by
+/// [`ArchiveType::compression_level`]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum CompressionLevel {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl CompressionLevel {
+    pub fn all() -> &'static [Self] {
+        &[Self::Fast, Self::Default, Self::Best]
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum DialogPage {
+    AddToArchive {
+        archive: PathBuf,
+        paths: Vec<PathBuf>,
+        archive_type: ArchiveType,
+        password: String,
+    },
+    ArchiveTest {
+        path: PathBuf,
+        report: archive::TestReport,
+    },
+    BatchRename {
+        paths: Vec<PathBuf>,
+        rename: BatchRename,
+        preview: Result<Vec<RenamePreview>, String>,
+    },
+    ChangePermissions {
+        paths: Vec<PathBuf>,
+        file_mode: String,
+        dir_mode: String,
+        owner: String,
+        group: String,
+        recursive: bool,
+    },
     Compress {
         paths: Vec<PathBuf>,
         to: PathBuf,
         name: String,
         archive_type: ArchiveType,
+        level: CompressionLevel,
         password: Option<String>,
     },
+    ConfirmPlan {
+        plan: OperationPlan,
+        replace_result: Option<ReplaceResult>,
+        tx: mpsc::Sender<PlanResult>,
+    },
     EmptyTrash,
     FailedOperation(u64),
     ExtractPassword {
@@ -526,10 +686,15 @@ pub enum DialogPage {
     SetExecutableAndLaunch {
         path: PathBuf,
     },
+    Shred {
+        paths: Vec<PathBuf>,
+        warnings: Vec<String>,
+    },
     FavoritePathError {
         path: PathBuf,
         entity: Entity,
     },
+    UnfinishedOperation(UnfinishedOperation),
 }
 
 pub struct DialogPages {
@@ -631,6 +796,25 @@ impl PartialEq for WatcherWrapper {
     }
 }
 
+// Choices for the size archives may expand to before extraction is stopped
+const EXTRACT_SIZE_LIMITS: [Option<u64>; 4] = [
+    None,
+    Some(10_000_000_000),
+    Some(100_000_000_000),
+    Some(1_000_000_000_000),
+];
+
+// Choices for the size of copies and moves that are previewed before they start
+const PREVIEW_SIZES: [Option<u64>; 4] = [
+    None,
+    Some(100_000_000),
+    Some(1_000_000_000),
+    Some(10_000_000_000),
+];
+
+// Choices for the number of times shredded files are overwritten
+const SHRED_PASSES: [u8; 4] = [1, 3, 7, 35];
+
 // The [`App`] stores application-specific state.
 pub struct App {
     core: Core,
@@ -643,10 +827,17 @@ pub struct App {
     state: State,
     mode: Mode,
     app_themes: Vec<String>,
+    compression_levels: Vec<String>,
+    extract_size_limits: Vec<String>,
+    preview_sizes: Vec<String>,
+    shred_passes: Vec<String>,
     compio_tx: mpsc::Sender<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    // Copied or cut items include folders
+    clipboard_dirs: bool,
     context_page: ContextPage,
     dialog_pages: DialogPages,
     dialog_text_input: widget::Id,
+    journal: Journal,
     key_binds: HashMap<KeyBind, Action>,
     margin: HashMap<window::Id, (f32, f32, f32, f32)>,
     mime_app_cache: MimeAppCache,
@@ -661,8 +852,9 @@ pub struct App {
     pending_operation_id: u64,
     pending_operations: BTreeMap<u64, (Operation, Controller)>,
     progress_operations: BTreeSet<u64>,
-    complete_operations: BTreeMap<u64, Operation>,
-    failed_operations: BTreeMap<u64, (Operation, Controller, String)>,
+    complete_operations: BTreeMap<u64, (Operation, Vec<String>)>,
+    failed_operations: BTreeMap<u64, (Operation, Controller, OperationError)>,
+    scheduler: Scheduler,
     search_id: widget::Id,
     size: Option<Size>,
     #[cfg(all(feature = "wayland", feature = "desktop-applet"))]
@@ -1001,6 +1193,7 @@ impl App {
             self.config.tab,
             Some(&self.state.sort_names),
         );
+        tab.clipboard_dirs = self.clipboard_dirs;
         tab.mode = match self.mode {
             Mode::App => tab::Mode::App,
             Mode::Desktop => {
@@ -1079,16 +1272,68 @@ impl App {
 
     #[must_use]
     fn operation(&mut self, operation: Operation) -> Task<Message> {
+        // Record the operation so it can be undone
+        self.journal
+            .prepare(self.pending_operation_id, &operation, None);
+        self.spawn_operation(operation)
+    }
+
+    // Perform the steps of an undo one after another, as the next step is returned when the
+    // previous one completes
+    #[must_use]
+    fn undo_operations(&mut self, mut operations: Vec<Operation>) -> Task<Message> {
+        if operations.is_empty() {
+            return Task::none();
+        }
+        let operation = operations.remove(0);
+        self.journal
+            .prepare_undo(self.pending_operation_id, operations);
+        self.spawn_operation(operation)
+    }
+
+    #[must_use]
+    fn spawn_operation(&mut self, operation: Operation) -> Task<Message> {
         let id = self.pending_operation_id;
-        let controller = Controller::default();
-        let compio_tx = self.compio_tx.clone();
 
         self.pending_operation_id += 1;
         if operation.show_progress_notification() {
             self.progress_operations.insert(id);
         }
         self.pending_operations
-            .insert(id, (operation.clone(), controller.clone()));
+            .insert(id, (operation.clone(), Controller::default()));
+
+        // Finding devices reads the filesystem, which may be slow, so the operation holds its
+        // place in the queue meanwhile
+        self.scheduler.reserve(id);
+        Task::perform(
+            async move {
+                let devices = tokio::task::spawn_blocking(move || operation_devices(&operation))
+                    .await
+                    .unwrap_or_default();
+                cosmic::action::app(Message::PendingDevices(id, devices))
+            },
+            |x| x,
+        )
+    }
+
+    // Remove an operation from the scheduler, starting any operations that were waiting on it
+    #[must_use]
+    fn finish_operation(&mut self, id: u64) -> Task<Message> {
+        let started = self.scheduler.finish(id);
+        Task::batch(started.into_iter().map(|id| self.start_operation(id)))
+    }
+
+    #[must_use]
+    fn start_operation(&mut self, id: u64) -> Task<Message> {
+        let Some((operation, controller)) = self.pending_operations.get(&id) else {
+            return Task::none();
+        };
+        // Capture what undo needs now, as earlier operations may have changed the same paths
+        self.journal.start(id);
+        let operation = operation.clone();
+        let controller = controller.clone();
+        let compio_tx = self.compio_tx.clone();
+        let config = self.config.operations;
 
         // Use a task to send operations to the compio runtime thread.
         cosmic::Task::stream(cosmic::iced_futures::stream::channel(
@@ -1102,7 +1347,7 @@ impl App {
 
                 _ = compio_tx
                     .send(Box::pin(async move {
-                        let msg = match operation.perform(&msg_tx_clone, controller).await {
+                        let msg = match operation.perform(&msg_tx_clone, controller, config).await {
                             Ok(result_paths) => Message::PendingComplete(id, result_paths),
                             Err(err) => Message::PendingError(id, err),
                         };
@@ -1213,6 +1458,17 @@ impl App {
         )
     }
 
+    // Remember if the clipboard holds folders, which cannot be pasted as hard links
+    fn set_clipboard_paths(&mut self, paths: &[PathBuf]) {
+        self.clipboard_dirs = paths.iter().any(|path| path.is_dir());
+        let entities: Vec<_> = self.tab_model.iter().collect();
+        for entity in entities {
+            if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
+                tab.clipboard_dirs = self.clipboard_dirs;
+            }
+        }
+    }
+
     fn rescan_trash(&mut self) -> Task<Message> {
         let mut needs_reload = Vec::new();
         for entity in self.tab_model.iter() {
@@ -1321,6 +1577,30 @@ impl App {
         paths
     }
 
+    /// Paths of the selected items for copying, including items inside archives
+    fn selected_copy_paths(&self, entity_opt: Option<Entity>) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+        if let Some(tab) = self.tab_model.data::<Tab>(entity) {
+            for location in tab.selected_locations() {
+                if let Some(path) = location.copy_path_opt() {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+
+    fn labeled_input<'a>(
+        label: String,
+        input: impl Into<Element<'a, Message>>,
+    ) -> Element<'a, Message> {
+        let cosmic_theme::Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+        widget::column::with_children(vec![widget::text::body(label).into(), input.into()])
+            .spacing(space_xxs)
+            .into()
+    }
+
     fn set_cut(&mut self, entity_opt: Option<Entity>) {
         let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
         if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
@@ -1732,47 +2012,77 @@ impl App {
             let mut section = widget::settings::section().title(fl!("pending"));
             for (id, (op, controller)) in self.pending_operations.iter().rev() {
                 let progress = controller.progress();
-                section = section.add(widget::column::with_children(vec![
-                    widget::row::with_children(vec![
-                        widget::progress_bar(0.0..=1.0, progress)
-                            .height(progress_bar_height)
-                            .into(),
-                        if controller.is_paused() {
-                            widget::tooltip(
-                                widget::button::icon(widget::icon::from_name(
-                                    "media-playback-start-symbolic",
-                                ))
-                                .on_press(Message::PendingPause(*id, false))
-                                .padding(8),
-                                widget::text::body(fl!("resume")),
-                                widget::tooltip::Position::Top,
-                            )
-                            .into()
-                        } else {
-                            widget::tooltip(
-                                widget::button::icon(widget::icon::from_name(
-                                    "media-playback-pause-symbolic",
-                                ))
-                                .on_press(Message::PendingPause(*id, true))
-                                .padding(8),
-                                widget::text::body(fl!("pause")),
-                                widget::tooltip::Position::Top,
-                            )
-                            .into()
-                        },
-                        widget::tooltip(
-                            widget::button::icon(widget::icon::from_name("window-close-symbolic"))
-                                .on_press(Message::PendingCancel(*id))
-                                .padding(8),
-                            widget::text::body(fl!("cancel")),
-                            widget::tooltip::Position::Top,
-                        )
-                        .into(),
-                    ])
-                    .align_y(Alignment::Center)
-                    .into(),
-                    widget::text::body(op.pending_text(progress, controller.state())).into(),
-                ]));
+                let icon_button = |name, message, tooltip| -> Element<Message> {
+                    widget::tooltip(
+                        widget::button::icon(widget::icon::from_name(name))
+                            .on_press(message)
+                            .padding(8),
+                        widget::text::body(tooltip),
+                        widget::tooltip::Position::Top,
+                    )
+                    .into()
+                };
+                let mut row = widget::row::with_capacity(6)
+                    .push(widget::progress_bar(0.0..=1.0, progress).height(progress_bar_height));
+                let queue_position = self.scheduler.queue_position(*id);
+                if let Some((position, total)) = queue_position {
+                    if position > 0 {
+                        row = row.push(icon_button(
+                            "go-up-symbolic",
+                            Message::PendingReorder(*id, true),
+                            fl!("move-up"),
+                        ));
+                    }
+                    if position + 1 < total {
+                        row = row.push(icon_button(
+                            "go-down-symbolic",
+                            Message::PendingReorder(*id, false),
+                            fl!("move-down"),
+                        ));
+                        row = row.push(icon_button(
+                            "go-bottom-symbolic",
+                            Message::PendingDefer(*id),
+                            fl!("defer"),
+                        ));
+                    }
+                    row = row.push(icon_button(
+                        "media-playback-start-symbolic",
+                        Message::PendingPromote(*id),
+                        fl!("start-now"),
+                    ));
+                } else if controller.is_paused() {
+                    row = row.push(icon_button(
+                        "media-playback-start-symbolic",
+                        Message::PendingPause(*id, false),
+                        fl!("resume"),
+                    ));
+                } else {
+                    row = row.push(icon_button(
+                        "media-playback-pause-symbolic",
+                        Message::PendingPause(*id, true),
+                        fl!("pause"),
+                    ));
+                }
+                row = row.push(icon_button(
+                    "window-close-symbolic",
+                    Message::PendingCancel(*id),
+                    fl!("cancel"),
+                ));
+                let mut column = widget::column::with_children(vec![
+                    row.align_y(Alignment::Center).into(),
+                    widget::text::body(op.pending_text(controller)).into(),
+                ]);
+                if let Some((position, total)) = queue_position {
+                    column = column.push(widget::text::caption(fl!(
+                        "queued",
+                        position = position + 1,
+                        total = total
+                    )));
+                }
+                if let Some(current_file) = controller.details().current_file {
+                    column = column.push(widget::text::caption(current_file.display().to_string()));
+                }
+                section = section.add(column);
             }
             children.push(section.into());
         }
@@ -1780,10 +2090,9 @@ impl App {
         if !self.failed_operations.is_empty() {
             let mut section = widget::settings::section().title(fl!("failed"));
             for (_id, (op, controller, error)) in self.failed_operations.iter().rev() {
-                let progress = controller.progress();
                 section = section.add(widget::column::with_children(vec![
-                    widget::text::body(op.pending_text(progress, controller.state())).into(),
-                    widget::text::body(error).into(),
+                    widget::text::body(op.pending_text(controller)).into(),
+                    widget::text::body(error.to_string()).into(),
                 ]));
             }
             children.push(section.into());
@@ -1791,8 +2100,13 @@ impl App {
 
         if !self.complete_operations.is_empty() {
             let mut section = widget::settings::section().title(fl!("complete"));
-            for (_id, op) in self.complete_operations.iter().rev() {
-                section = section.add(widget::text::body(op.completed_text()));
+            for (_id, (op, warnings)) in self.complete_operations.iter().rev() {
+                let mut column = widget::column::with_capacity(1 + warnings.len())
+                    .push(widget::text::body(op.completed_text()));
+                for warning in warnings.iter() {
+                    column = column.push(widget::text::caption(warning));
+                }
+                section = section.add(column);
             }
             children.push(section.into());
         }
@@ -1872,6 +2186,7 @@ impl App {
 
     fn settings(&self) -> Element<Message> {
         let tab_config = self.config.tab;
+        let operations_config = self.config.operations;
 
         // TODO: Should dialog be updated here too?
         widget::settings::view_column(vec![
@@ -1925,6 +2240,144 @@ impl App {
                     )
                 })
                 .into(),
+            widget::settings::section()
+                .title(fl!("operations"))
+                .add({
+                    widget::settings::item::builder(fl!("continue-on-error"))
+                        .description(fl!("continue-on-error-description"))
+                        .toggler(
+                            operations_config.continue_on_error,
+                            move |continue_on_error| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    continue_on_error,
+                                    ..operations_config
+                                })
+                            },
+                        )
+                })
+                .add({
+                    widget::settings::item::builder(fl!("copy-special-files"))
+                        .description(fl!("copy-special-files-description"))
+                        .toggler(
+                            operations_config.copy_special_files,
+                            move |copy_special_files| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    copy_special_files,
+                                    ..operations_config
+                                })
+                            },
+                        )
+                })
+                .add({
+                    widget::settings::item::builder(fl!("extract-ownership"))
+                        .description(fl!("extract-ownership-description"))
+                        .toggler(
+                            operations_config.extract_ownership,
+                            move |extract_ownership| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    extract_ownership,
+                                    ..operations_config
+                                })
+                            },
+                        )
+                })
+                .add({
+                    widget::settings::item::builder(fl!("extract-size-limit"))
+                        .description(fl!("extract-size-limit-description"))
+                        .control(widget::dropdown(
+                            &self.extract_size_limits,
+                            EXTRACT_SIZE_LIMITS.iter().position(|size_opt| {
+                                *size_opt == operations_config.extract_size_limit
+                            }),
+                            move |index| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    extract_size_limit: EXTRACT_SIZE_LIMITS[index],
+                                    ..operations_config
+                                })
+                            },
+                        ))
+                })
+                .add({
+                    widget::settings::item::builder(fl!("preview-operations"))
+                        .description(fl!("preview-operations-description"))
+                        .control(widget::dropdown(
+                            &self.preview_sizes,
+                            PREVIEW_SIZES
+                                .iter()
+                                .position(|size_opt| *size_opt == operations_config.preview_size),
+                            move |index| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    preview_size: PREVIEW_SIZES[index],
+                                    ..operations_config
+                                })
+                            },
+                        ))
+                })
+                .add({
+                    widget::settings::item::builder(fl!("preserve-metadata"))
+                        .description(fl!("preserve-metadata-description"))
+                        .toggler(
+                            operations_config.preserve_metadata,
+                            move |preserve_metadata| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    preserve_metadata,
+                                    ..operations_config
+                                })
+                            },
+                        )
+                })
+                .add({
+                    widget::settings::item::builder(fl!("relative-links"))
+                        .description(fl!("relative-links-description"))
+                        .toggler(operations_config.relative_links, move |relative_links| {
+                            Message::OperationsConfig(OperationsConfig {
+                                relative_links,
+                                ..operations_config
+                            })
+                        })
+                })
+                .add({
+                    widget::settings::item::builder(fl!("shred-passes")).control(widget::dropdown(
+                        &self.shred_passes,
+                        SHRED_PASSES
+                            .iter()
+                            .position(|passes| *passes == operations_config.shred_passes),
+                        move |index| {
+                            Message::OperationsConfig(OperationsConfig {
+                                shred_passes: SHRED_PASSES[index],
+                                ..operations_config
+                            })
+                        },
+                    ))
+                })
+                .add({
+                    widget::settings::item::builder(fl!("shred-zeros"))
+                        .description(fl!("shred-zeros-description"))
+                        .toggler(
+                            operations_config.shred_pattern == ShredPattern::Zero,
+                            move |zeros| {
+                                Message::OperationsConfig(OperationsConfig {
+                                    shred_pattern: if zeros {
+                                        ShredPattern::Zero
+                                    } else {
+                                        ShredPattern::Random
+                                    },
+                                    ..operations_config
+                                })
+                            },
+                        )
+                })
+                .add({
+                    widget::settings::item::builder(fl!("verify-copies"))
+                        .description(fl!("verify-copies-description"))
+                        .toggler(operations_config.verify_copies, move |verify_copies| {
+                            Message::OperationsConfig(OperationsConfig {
+                                verify_copies,
+                                ..operations_config
+                            })
+                        })
+                })
+                .into(),
         ])
         .into()
     }
@@ -2059,6 +2512,32 @@ impl Application for App {
         }
 
         let app_themes = vec![fl!("match-desktop"), fl!("dark"), fl!("light")];
+        let compression_levels = CompressionLevel::all()
+            .iter()
+            .map(|level| match level {
+                CompressionLevel::Fast => fl!("compression-fast"),
+                CompressionLevel::Default => fl!("compression-default"),
+                CompressionLevel::Best => fl!("compression-best"),
+            })
+            .collect();
+        let extract_size_limits = EXTRACT_SIZE_LIMITS
+            .iter()
+            .map(|size_opt| match size_opt {
+                Some(size) => fl!("extract-size-limit-size", size = tab::format_size(*size)),
+                None => fl!("extract-size-limit-none"),
+            })
+            .collect();
+        let preview_sizes = PREVIEW_SIZES
+            .iter()
+            .map(|size_opt| match size_opt {
+                Some(size) => fl!("preview-size", size = tab::format_size(*size)),
+                None => fl!("preview-never"),
+            })
+            .collect();
+        let shred_passes = SHRED_PASSES
+            .iter()
+            .map(|passes| fl!("shred-passes-count", passes = passes))
+            .collect();
 
         let key_binds = key_binds(&match flags.mode {
             Mode::App => tab::Mode::App,
@@ -2094,10 +2573,16 @@ impl Application for App {
             state: flags.state,
             mode: flags.mode,
             app_themes,
+            compression_levels,
+            extract_size_limits,
+            preview_sizes,
+            shred_passes,
             compio_tx,
+            clipboard_dirs: false,
             context_page: ContextPage::Preview(None, PreviewKind::Selected),
             dialog_pages: DialogPages::new(),
             dialog_text_input: widget::Id::unique(),
+            journal: Journal::default(),
             key_binds,
             margin: HashMap::new(),
             mime_app_cache: MimeAppCache::new(),
@@ -2114,6 +2599,7 @@ impl Application for App {
             progress_operations: BTreeSet::new(),
             complete_operations: BTreeMap::new(),
             failed_operations: BTreeMap::new(),
+            scheduler: Scheduler::default(),
             search_id: widget::Id::unique(),
             size: None,
             #[cfg(all(feature = "wayland", feature = "desktop-applet"))]
@@ -2161,6 +2647,16 @@ impl Application for App {
             }
         }
 
+        if matches!(app.mode, Mode::App) {
+            // Offer to resume or roll back operations interrupted by a previous exit
+            for unfinished in UnfinishedOperation::load_all() {
+                commands.push(
+                    app.dialog_pages
+                        .push_back(DialogPage::UnfinishedOperation(unfinished)),
+                );
+            }
+        }
+
         if app.tab_model.iter().next().is_none() {
             if let Ok(current_dir) = env::current_dir() {
                 commands.push(app.open_tab(Location::Path(current_dir), true, None));
@@ -2517,6 +3013,59 @@ impl Application for App {
                 config_set!(app_theme, app_theme);
                 return self.update_config();
             }
+            Message::BatchRenameUpdate(rename) => {
+                // The preview reads metadata, so it is only built when the rules change
+                if let Some(DialogPage::BatchRename { paths, .. }) = self.dialog_pages.front() {
+                    let paths = paths.clone();
+                    let preview = rename.preview(&paths);
+                    self.dialog_pages.update_front(DialogPage::BatchRename {
+                        paths,
+                        rename,
+                        preview,
+                    });
+                }
+            }
+            Message::BrowseArchive(entity_opt) => {
+                let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+                if let Some(path) = self.selected_paths(entity_opt).into_iter().next() {
+                    if archive::is_archive(&path) {
+                        return self.update(Message::TabMessage(
+                            Some(entity),
+                            tab::Message::Location(Location::Archive(path, PathBuf::new())),
+                        ));
+                    }
+                }
+            }
+            Message::ChangePermissions(entity_opt) => {
+                let paths = self.selected_paths(entity_opt);
+                if !paths.is_empty() {
+                    // Start from the modes of the first selected file and folder
+                    let mut file_mode = String::new();
+                    let mut dir_mode = String::new();
+                    #[cfg(unix)]
+                    for path in paths.iter() {
+                        use std::os::unix::fs::PermissionsExt;
+                        let Ok(metadata) = path.symlink_metadata() else {
+                            continue;
+                        };
+                        let mode = format!("{:03o}", metadata.permissions().mode() & 0o7777);
+                        if metadata.is_dir() && dir_mode.is_empty() {
+                            dir_mode = mode;
+                        } else if metadata.is_file() && file_mode.is_empty() {
+                            file_mode = mode;
+                        }
+                    }
+                    let recursive = paths.iter().any(|path| path.is_dir());
+                    return self.dialog_pages.push_back(DialogPage::ChangePermissions {
+                        paths,
+                        file_mode,
+                        dir_mode,
+                        owner: String::new(),
+                        group: String::new(),
+                        recursive,
+                    });
+                }
+            }
             Message::Compress(entity_opt) => {
                 let paths = self.selected_paths(entity_opt);
                 if let Some(current_path) = paths.first() {
@@ -2530,6 +3079,7 @@ impl Application for App {
                                 to,
                                 name,
                                 archive_type,
+                                level: CompressionLevel::default(),
                                 password: None,
                             }),
                             widget::text_input::focus(self.dialog_text_input.clone()),
@@ -2553,13 +3103,15 @@ impl Application for App {
                         tab.refresh_cut(&[]);
                     }
                 }
-                let paths = self.selected_paths(entity_opt);
+                let paths = self.selected_copy_paths(entity_opt);
+                self.set_clipboard_paths(&paths);
                 let contents = ClipboardCopy::new(ClipboardKind::Copy, &paths);
                 return clipboard::write_data(contents);
             }
             Message::Cut(entity_opt) => {
                 self.set_cut(entity_opt);
                 let paths = self.selected_paths(entity_opt);
+                self.set_clipboard_paths(&paths);
                 let contents = ClipboardCopy::new(ClipboardKind::Cut { is_dnd: false }, &paths);
                 return clipboard::write_data(contents);
             }
@@ -2676,6 +3228,12 @@ impl Application for App {
                     }
                 }
             }
+            Message::Duplicate(entity_opt) => {
+                let paths = self.selected_paths(entity_opt);
+                if !paths.is_empty() {
+                    return self.operation(Operation::Duplicate { paths });
+                }
+            }
             Message::DialogCancel => {
                 if let Some((_page, task)) = self.dialog_pages.pop_front() {
                     return task;
@@ -2685,11 +3243,57 @@ impl Application for App {
                 if let Some((dialog_page, task)) = self.dialog_pages.pop_front() {
                     let mut tasks = vec![task];
                     match dialog_page {
+                        DialogPage::AddToArchive {
+                            archive,
+                            paths,
+                            password,
+                            ..
+                        } => {
+                            return self.operation(Operation::AddToArchive {
+                                archive,
+                                paths,
+                                password: (!password.is_empty()).then_some(password),
+                            });
+                        }
+                        DialogPage::ArchiveTest { .. } => {}
+                        DialogPage::BatchRename { preview, .. } => {
+                            let renames: Vec<_> = preview
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter(|preview| preview.from != preview.to)
+                                .map(|preview| (preview.from, preview.to))
+                                .collect();
+                            if !renames.is_empty() {
+                                return self.operation(Operation::BatchRename { renames });
+                            }
+                        }
+                        DialogPage::ChangePermissions {
+                            paths,
+                            file_mode,
+                            dir_mode,
+                            owner,
+                            group,
+                            recursive,
+                        } => {
+                            match PermissionChanges::parse(&file_mode, &dir_mode, &owner, &group) {
+                                Ok(changes) => {
+                                    return self.operation(Operation::ChangePermissions {
+                                        paths,
+                                        changes,
+                                        recursive,
+                                    });
+                                }
+                                Err(err) => {
+                                    log::warn!("invalid permission changes: {}", err);
+                                }
+                            }
+                        }
                         DialogPage::Compress {
                             paths,
                             to,
                             name,
                             archive_type,
+                            level,
                             password,
                         } => {
                             let extension = archive_type.extension();
@@ -2699,22 +3303,65 @@ impl Application for App {
                                 paths,
                                 to,
                                 archive_type,
+                                level,
                                 password,
                             });
                         }
+                        DialogPage::ConfirmPlan {
+                            replace_result, tx, ..
+                        } => {
+                            return Task::perform(
+                                async move {
+                                    let _ = tx.send(PlanResult::Start(replace_result)).await;
+                                    cosmic::action::none()
+                                },
+                                |x| x,
+                            );
+                        }
                         DialogPage::EmptyTrash => {
                             return self.operation(Operation::EmptyTrash);
                         }
                         DialogPage::FailedOperation(id) => {
-                            log::warn!("TODO: retry operation {}", id);
+                            let retries = match self.failed_operations.get(&id) {
+                                Some((operation, _, err)) => match &err.kind {
+                                    OperationErrorType::Partial { failed, .. } => {
+                                        operation.retry_failed(failed)
+                                    }
+                                    OperationErrorType::VerifyFailed {
+                                        mismatched, failed, ..
+                                    } => {
+                                        let mut retry = mismatched.clone();
+                                        retry.extend(failed.iter().cloned());
+                                        operation.retry_failed(&retry)
+                                    }
+                                    _ => {
+                                        log::warn!("TODO: retry operation {}", id);
+                                        Vec::new()
+                                    }
+                                },
+                                None => Vec::new(),
+                            };
+                            for retry in retries {
+                                tasks.push(self.operation(retry));
+                            }
                         }
                         DialogPage::ExtractPassword { id, password } => {
                             let (operation, _, _err) = self.failed_operations.get(&id).unwrap();
                             let new_op = match &operation {
-                                Operation::Extract { to, paths, .. } => Operation::Extract {
+                                Operation::Extract {
+                                    to,
+                                    paths,
+                                    name_encoding,
+                                    ..
+                                } => Operation::Extract {
                                     to: to.clone(),
                                     paths: paths.clone(),
                                     password: Some(password),
+                                    name_encoding: *name_encoding,
+                                },
+                                Operation::TestArchive { paths, .. } => Operation::TestArchive {
+                                    paths: paths.clone(),
+                                    password: Some(password),
                                 },
                                 _ => unreachable!(),
                             };
@@ -2814,6 +3461,13 @@ impl Application for App {
                         DialogPage::SetExecutableAndLaunch { path } => {
                             return self.operation(Operation::SetExecutableAndLaunch { path });
                         }
+                        DialogPage::Shred { paths, .. } => {
+                            return self.operation(Operation::Shred {
+                                paths,
+                                passes: self.config.operations.shred_passes,
+                                pattern: self.config.operations.shred_pattern,
+                            });
+                        }
                         DialogPage::FavoritePathError { entity, .. } => {
                             if let Some(FavoriteIndex(favorite_i)) =
                                 self.nav_model.data::<FavoriteIndex>(entity)
@@ -2824,6 +3478,11 @@ impl Application for App {
                                 return self.update_config();
                             }
                         }
+                        DialogPage::UnfinishedOperation(unfinished) => {
+                            for operation in unfinished.resume() {
+                                tasks.push(self.operation(operation));
+                            }
+                        }
                     }
                     return Task::batch(tasks);
                 }
@@ -2840,6 +3499,31 @@ impl Application for App {
                     self.update(Message::DialogComplete),
                 ]);
             }
+            Message::ExportFailedLog(id) => {
+                if let Some((_, _, err)) = self.failed_operations.get(&id) {
+                    let secs = time::SystemTime::now()
+                        .duration_since(time::UNIX_EPOCH)
+                        .map_or(0, |duration| duration.as_secs());
+                    let result = dirs::state_dir()
+                        .map(|dir| dir.join("cosmic-files").join("logs"))
+                        .ok_or_else(|| io::Error::other("no state directory"))
+                        .and_then(|dir| {
+                            fs::create_dir_all(&dir)?;
+                            let path = dir.join(format!("failed-operation-{}-{}.log", id, secs));
+                            fs::write(&path, format!("{}\n", err))?;
+                            Ok(path)
+                        });
+                    match result {
+                        Ok(path) => {
+                            log::info!("exported failed operation log to {:?}", path);
+                            return self.open_file(&[path]);
+                        }
+                        Err(err) => {
+                            log::warn!("failed to export failed operation log: {}", err);
+                        }
+                    }
+                }
+            }
             Message::ExtractHere(entity_opt) => {
                 let paths = self.selected_paths(entity_opt);
                 if let Some(destination) = paths
@@ -2851,6 +3535,7 @@ impl Application for App {
                         paths,
                         to: destination,
                         password: None,
+                        name_encoding: NameEncoding::Auto,
                     });
                 }
             }
@@ -2870,6 +3555,18 @@ impl Application for App {
                     );
                     let set_title_task = dialog.set_title(fl!("extract-to-title"));
                     dialog.set_accept_label(fl!("extract-here"));
+                    dialog.set_choices([DialogChoice::ComboBox {
+                        id: "name-encoding".to_string(),
+                        label: fl!("name-encoding"),
+                        options: NameEncoding::ALL
+                            .iter()
+                            .map(|encoding| DialogChoiceOption {
+                                id: format!("{:?}", encoding),
+                                label: encoding.label(),
+                            })
+                            .collect(),
+                        selected: Some(0),
+                    }]);
                     self.windows
                         .insert(dialog.window_id(), WindowKind::FileDialog(Some(paths)));
                     self.file_dialog_opt = Some(dialog);
@@ -2881,11 +3578,21 @@ impl Application for App {
                     DialogResult::Cancel => {}
                     DialogResult::Open(selected_paths) => {
                         let mut archive_paths = None;
+                        let mut name_encoding = NameEncoding::Auto;
                         if let Some(file_dialog) = &self.file_dialog_opt {
                             let window = self.windows.remove(&file_dialog.window_id());
                             if let Some(WindowKind::FileDialog(paths)) = window {
                                 archive_paths = paths;
                             }
+                            if let Some(DialogChoice::ComboBox {
+                                selected: Some(selected),
+                                ..
+                            }) = file_dialog.choices().first()
+                            {
+                                if let Some(selected) = NameEncoding::ALL.get(*selected) {
+                                    name_encoding = *selected;
+                                }
+                            }
                         }
                         if let Some(archive_paths) = archive_paths {
                             if !selected_paths.is_empty() {
@@ -2894,6 +3601,7 @@ impl Application for App {
                                     paths: archive_paths,
                                     to: selected_paths[0].clone(),
                                     password: None,
+                                    name_encoding,
                                 });
                             }
                         }
@@ -2947,6 +3655,32 @@ impl Application for App {
                     }
                 }
             }
+            Message::MakeLink(entity_opt) => {
+                // Links are made next to the items they point to
+                let mut by_parent = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+                for path in self.selected_paths(entity_opt) {
+                    if let Some(parent) = path.parent() {
+                        by_parent
+                            .entry(parent.to_path_buf())
+                            .or_default()
+                            .push(path);
+                    }
+                }
+                let relative = self.config.operations.relative_links;
+                return Task::batch(
+                    by_parent
+                        .into_iter()
+                        .map(|(to, paths)| {
+                            self.operation(Operation::Link {
+                                paths,
+                                to,
+                                kind: LinkKind::Symbolic,
+                                relative,
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                );
+            }
             Message::MaybeExit => {
                 if self.window_id_opt.is_none() && self.pending_operations.is_empty() {
                     // Exit if window is closed and there are no pending operations
@@ -3357,8 +4091,27 @@ impl Application for App {
             }
             Message::PasteContents(to, mut contents) => {
                 contents.paths.retain(|p| p != &to);
+                // Items inside archives are extracted, as they cannot be copied or moved
+                let mut archive_entries = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+                contents
+                    .paths
+                    .retain(|path| match archive::split_path(path) {
+                        Some((archive, entry)) => {
+                            archive_entries.entry(archive).or_default().push(entry);
+                            false
+                        }
+                        None => true,
+                    });
+                let mut tasks = Vec::with_capacity(archive_entries.len() + 1);
+                for (archive, paths) in archive_entries {
+                    tasks.push(self.operation(Operation::ExtractEntries {
+                        archive,
+                        paths,
+                        to: to.clone(),
+                    }));
+                }
                 if !contents.paths.is_empty() {
-                    return match contents.kind {
+                    tasks.push(match contents.kind {
                         ClipboardKind::Copy => self.operation(Operation::Copy {
                             paths: contents.paths,
                             to,
@@ -3368,23 +4121,71 @@ impl Application for App {
                             to,
                             cross_device_copy: is_dnd,
                         }),
-                    };
+                    });
+                }
+                return Task::batch(tasks);
+            }
+            Message::PasteLink(entity_opt, kind) => {
+                let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+                if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
+                    if let Some(path) = tab.location.path_opt() {
+                        let to = path.clone();
+                        return clipboard::read_data::<ClipboardPaste>().map(move |contents_opt| {
+                            match contents_opt {
+                                Some(contents) => cosmic::action::app(Message::PasteLinkContents(
+                                    to.clone(),
+                                    contents,
+                                    kind,
+                                )),
+                                None => cosmic::action::none(),
+                            }
+                        });
+                    }
+                }
+            }
+            Message::PasteLinkContents(to, contents, kind) => {
+                self.set_clipboard_paths(&contents.paths);
+                if !contents.paths.is_empty() {
+                    return self.operation(Operation::Link {
+                        paths: contents.paths,
+                        to,
+                        kind,
+                        relative: self.config.operations.relative_links,
+                    });
                 }
             }
             Message::PendingCancel(id) => {
                 if let Some((_, controller)) = self.pending_operations.get(&id) {
                     controller.cancel();
                     self.progress_operations.remove(&id);
+                    // Queued operations were never started, so they are failed here
+                    if self.scheduler.is_queued(id) {
+                        return self.update(Message::PendingError(
+                            id,
+                            OperationError::from_str(fl!("cancelled")),
+                        ));
+                    }
                 }
             }
             Message::PendingCancelAll => {
+                let mut queued = Vec::new();
                 for (id, (_, controller)) in self.pending_operations.iter() {
                     controller.cancel();
                     self.progress_operations.remove(id);
+                    if self.scheduler.is_queued(*id) {
+                        queued.push(*id);
+                    }
                 }
+                return Task::batch(queued.into_iter().map(|id| {
+                    self.update(Message::PendingError(
+                        id,
+                        OperationError::from_str(fl!("cancelled")),
+                    ))
+                }));
             }
             Message::PendingComplete(id, op_sel) => {
-                let mut commands = Vec::with_capacity(4);
+                let mut commands = Vec::with_capacity(5);
+                commands.push(self.finish_operation(id));
                 if let Some((op, _)) = self.pending_operations.remove(&id) {
                     // Show toast for some operations
                     if let Some(description) = op.toast() {
@@ -3433,7 +4234,11 @@ impl Application for App {
                         commands.push(self.rescan_recents());
                     }
 
-                    self.complete_operations.insert(id, op);
+                    let undo_operations = self.journal.complete(id, &op_sel);
+                    commands.push(self.undo_operations(undo_operations));
+
+                    self.complete_operations
+                        .insert(id, (op, op_sel.warnings.clone()));
                 }
                 // Close progress notification if all relevant operations are finished
                 if !self
@@ -3452,16 +4257,39 @@ impl Application for App {
 
                 return Task::batch(commands);
             }
+            Message::PendingDefer(id) => {
+                self.scheduler.defer(id);
+            }
+            Message::PendingDevices(id, devices) => {
+                // Operations sharing a device with running operations wait for them to finish
+                if self.pending_operations.contains_key(&id) && self.scheduler.submit(id, devices) {
+                    return self.start_operation(id);
+                }
+            }
             Message::PendingDismiss => {
                 self.progress_operations.clear();
             }
             Message::PendingError(id, err) => {
-                let mut tasks = Vec::new();
+                let mut tasks = vec![self.finish_operation(id)];
                 if let Some((op, controller)) = self.pending_operations.remove(&id) {
+                    // Items that were processed stay selected, and can still be undone
+                    if let OperationErrorType::VerifyFailed { op_sel, .. }
+                    | OperationErrorType::Partial { op_sel, .. } = &err.kind
+                    {
+                        tasks.push(self.rescan_operation_selection(op_sel.clone()));
+                        let undo_operations = self.journal.complete(id, op_sel);
+                        tasks.push(self.undo_operations(undo_operations));
+                    } else {
+                        self.journal.fail(id);
+                    }
                     // Only show dialog if not cancelled
                     if !controller.is_cancelled() {
                         tasks.push(self.dialog_pages.push_back(match err.kind {
-                            OperationErrorType::Generic(_) => DialogPage::FailedOperation(id),
+                            OperationErrorType::Generic(_)
+                            | OperationErrorType::Partial { .. }
+                            | OperationErrorType::VerifyFailed { .. } => {
+                                DialogPage::FailedOperation(id)
+                            }
                             OperationErrorType::PasswordRequired => DialogPage::ExtractPassword {
                                 id,
                                 password: String::from(""),
@@ -3470,8 +4298,7 @@ impl Application for App {
                     }
                     // Remove from progress
                     self.progress_operations.remove(&id);
-                    self.failed_operations
-                        .insert(id, (op, controller, err.to_string()));
+                    self.failed_operations.insert(id, (op, controller, err));
                 }
                 // Close progress notification if all relevant operations are finished
                 if !self
@@ -3503,6 +4330,14 @@ impl Application for App {
                     }
                 }
             }
+            Message::PendingPromote(id) => {
+                if self.scheduler.promote(id) {
+                    return self.start_operation(id);
+                }
+            }
+            Message::PendingReorder(id, earlier) => {
+                self.scheduler.reorder(id, earlier);
+            }
             Message::PermanentlyDelete(entity_opt) => {
                 let paths = self.selected_paths(entity_opt);
                 if !paths.is_empty() {
@@ -3557,6 +4392,14 @@ impl Application for App {
                 let paths = self.selected_paths(entity_opt);
                 return self.operation(Operation::RemoveFromRecents { paths });
             }
+            Message::Redo => {
+                if let Some(entry) = self.journal.redo() {
+                    let operation = entry.operation.clone();
+                    self.journal
+                        .prepare(self.pending_operation_id, &operation, Some(entry));
+                    return self.spawn_operation(operation);
+                }
+            }
             Message::RescanTrash => {
                 // Update trash icon if empty/full
                 let maybe_entity = self.nav_model.iter().find(|&entity| {
@@ -3574,39 +4417,41 @@ impl Application for App {
             }
             Message::Rename(entity_opt) => {
                 let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
-                if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
-                    if let Some(items) = tab.items_opt() {
-                        let mut selected = Vec::new();
-                        for item in items.iter() {
-                            if item.selected {
-                                if let Some(path) = item.path_opt() {
-                                    selected.push(path.to_path_buf());
-                                }
-                            }
-                        }
-                        if !selected.is_empty() {
-                            //TODO: batch rename
-                            let mut tasks = Vec::new();
-                            for path in selected {
-                                let parent = match path.parent() {
-                                    Some(some) => some.to_path_buf(),
-                                    None => continue,
-                                };
-                                let name = match path.file_name().and_then(|x| x.to_str()) {
-                                    Some(some) => some.to_string(),
-                                    None => continue,
-                                };
-                                let dir = path.is_dir();
-                                tasks.push(self.dialog_pages.push_back(DialogPage::RenameItem {
-                                    from: path,
-                                    parent,
-                                    name,
-                                    dir,
-                                }));
-                            }
-                            tasks.push(widget::text_input::focus(self.dialog_text_input.clone()));
-                            return Task::batch(tasks);
+                if let Some(tab) = self.tab_model.data::<Tab>(entity) {
+                    // Batch renames number items in the order they are displayed
+                    let selected = tab.selected_paths_sorted();
+                    if selected.len() > 1 {
+                        let rename = BatchRename::default();
+                        let preview = rename.preview(&selected);
+                        return Task::batch([
+                            self.dialog_pages.push_back(DialogPage::BatchRename {
+                                paths: selected,
+                                rename,
+                                preview,
+                            }),
+                            widget::text_input::focus(self.dialog_text_input.clone()),
+                        ]);
+                    } else if !selected.is_empty() {
+                        let mut tasks = Vec::new();
+                        for path in selected {
+                            let parent = match path.parent() {
+                                Some(some) => some.to_path_buf(),
+                                None => continue,
+                            };
+                            let name = match path.file_name().and_then(|x| x.to_str()) {
+                                Some(some) => some.to_string(),
+                                None => continue,
+                            };
+                            let dir = path.is_dir();
+                            tasks.push(self.dialog_pages.push_back(DialogPage::RenameItem {
+                                from: path,
+                                parent,
+                                name,
+                                dir,
+                            }));
                         }
+                        tasks.push(widget::text_input::focus(self.dialog_text_input.clone()));
+                        return Task::batch(tasks);
                     }
                 }
             }
@@ -3629,6 +4474,23 @@ impl Application for App {
                     }
                 }
             }
+            Message::RollBackOperation => {
+                if let Some((dialog_page, task)) = self.dialog_pages.pop_front() {
+                    let mut tasks = vec![task];
+                    match dialog_page {
+                        DialogPage::UnfinishedOperation(unfinished) => {
+                            for operation in unfinished.roll_back() {
+                                tasks.push(self.operation(operation));
+                            }
+                        }
+                        other => {
+                            log::warn!("tried to roll back operation from the wrong dialog");
+                            tasks.push(self.dialog_pages.push_front(other));
+                        }
+                    }
+                    return Task::batch(tasks);
+                }
+            }
             Message::RestoreFromTrash(entity_opt) => {
                 let mut trash_items = Vec::new();
                 let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
@@ -3680,6 +4542,15 @@ impl Application for App {
                 config_set!(type_to_search, type_to_search);
                 return self.update_config();
             }
+            Message::Shred(entity_opt) => {
+                let paths = self.selected_paths(entity_opt);
+                if !paths.is_empty() {
+                    let warnings = shred_warnings(&paths);
+                    return self
+                        .dialog_pages
+                        .push_back(DialogPage::Shred { paths, warnings });
+                }
+            }
             Message::SystemThemeModeChange => {
                 return self.update_config();
             }
@@ -3762,6 +4633,12 @@ impl Application for App {
                     return self.update_config();
                 }
             }
+            Message::OperationsConfig(config) => {
+                if config != self.config.operations {
+                    config_set!(operations, config);
+                    return self.update_config();
+                }
+            }
             Message::ToggleFoldersFirst => {
                 let mut config = self.config.tab;
                 config.folders_first = !config.folders_first;
@@ -3793,6 +4670,18 @@ impl Application for App {
                         tab::Command::Action(action) => {
                             commands.push(self.update(action.message(Some(entity))));
                         }
+                        tab::Command::AddToArchive(archive, paths) => {
+                            if let Some(archive_type) = ArchiveType::from_path(&archive) {
+                                commands.push(self.dialog_pages.push_back(
+                                    DialogPage::AddToArchive {
+                                        archive,
+                                        paths,
+                                        archive_type,
+                                        password: String::new(),
+                                    },
+                                ));
+                            }
+                        }
                         tab::Command::AddNetworkDrive => {
                             self.context_page = ContextPage::NetworkDrive;
                             self.set_show_context(true);
@@ -3829,6 +4718,13 @@ impl Application for App {
                         tab::Command::DropFiles(to, from) => {
                             commands.push(self.update(Message::PasteContents(to, from)));
                         }
+                        tab::Command::DropLinks(to, from) => {
+                            commands.push(self.update(Message::PasteLinkContents(
+                                to,
+                                from,
+                                LinkKind::Symbolic,
+                            )));
+                        }
                         tab::Command::EmptyTrash => {
                             return self.dialog_pages.push_back(DialogPage::EmptyTrash);
                         }
@@ -3982,6 +4878,15 @@ impl Application for App {
                 config.view = view;
                 return self.update(Message::TabConfig(config));
             }
+            Message::TestArchive(entity_opt) => {
+                let paths = self.selected_paths(entity_opt);
+                if !paths.is_empty() {
+                    return self.operation(Operation::TestArchive {
+                        paths,
+                        password: None,
+                    });
+                }
+            }
             Message::CutPaths(paths) => {
                 if let Some(tab) = self.tab_model.active_data_mut::<Tab>() {
                     tab.refresh_cut(&paths);
@@ -4008,8 +4913,10 @@ impl Application for App {
                     )));
                 }
             }
-            Message::Undo(_id) => {
-                // TODO: undo
+            Message::Undo => {
+                if let Some(entry) = self.journal.undo() {
+                    return self.undo_operations(entry.inverse);
+                }
             }
             Message::UndoTrash(id, recently_trashed) => {
                 self.toasts.remove(id);
@@ -4672,11 +5579,390 @@ impl Application for App {
         } = theme::active().cosmic().spacing;
 
         let dialog = match dialog_page {
+            DialogPage::AddToArchive {
+                archive,
+                paths,
+                archive_type,
+                password,
+            } => {
+                let mut dialog = widget::dialog()
+                    .title(fl!("add-to-archive"))
+                    .body(fl!(
+                        "add-to-archive-description",
+                        items = paths.len(),
+                        name = archive
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    ))
+                    .primary_action(
+                        widget::button::suggested(fl!("add")).on_press(Message::DialogComplete),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+                if *archive_type == ArchiveType::Zip {
+                    dialog = dialog.control(widget::column::with_children(vec![
+                        widget::text::body(fl!("password")).into(),
+                        widget::text_input("", password)
+                            .password()
+                            .on_input(move |password| {
+                                Message::DialogUpdate(DialogPage::AddToArchive {
+                                    archive: archive.clone(),
+                                    paths: paths.clone(),
+                                    archive_type: *archive_type,
+                                    password,
+                                })
+                            })
+                            .on_submit(|_| Message::DialogComplete)
+                            .into(),
+                        widget::text::caption(fl!("add-to-archive-password")).into(),
+                    ]));
+                }
+                dialog
+            }
+            DialogPage::ArchiveTest { path, report } => {
+                let mut column = widget::column::with_capacity(report.entries.len());
+                for entry in report.entries.iter() {
+                    let mut details = Vec::with_capacity(4);
+                    if !entry.is_dir {
+                        details.push(tab::format_size(entry.size));
+                        if let Some(compressed_size) = entry.compressed_size {
+                            details.push(fl!(
+                                "archive-test-compressed",
+                                size = tab::format_size(compressed_size)
+                            ));
+                        }
+                    }
+                    details.push(entry.method.clone());
+                    if entry.encrypted {
+                        details.push(fl!("archive-test-encrypted"));
+                    }
+                    let mut entry_column = widget::column::with_capacity(3)
+                        .push(widget::text::body(&entry.name))
+                        .push(widget::text::caption(details.join(" · ")));
+                    if let Some(error) = &entry.error {
+                        entry_column = entry_column.push(widget::text::caption(error));
+                    }
+                    column = column.push(entry_column);
+                }
+                if let Some(error) = &report.error {
+                    column = column.push(widget::text::body(error));
+                }
+
+                let problems = report.problems();
+                widget::dialog()
+                    .title(fl!(
+                        "archive-test-title",
+                        name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    ))
+                    .body(if problems == 0 {
+                        fl!("archive-test-passed", items = report.entries.len())
+                    } else {
+                        fl!(
+                            "archive-test-failed",
+                            items = report.entries.len(),
+                            problems = problems
+                        )
+                    })
+                    .icon(
+                        widget::icon::from_name(if problems == 0 {
+                            "dialog-information"
+                        } else {
+                            "dialog-error"
+                        })
+                        .size(64),
+                    )
+                    .control(
+                        widget::scrollable(column.spacing(space_xxs)).height(Length::Fixed(240.0)),
+                    )
+                    .primary_action(
+                        widget::button::standard(fl!("close")).on_press(Message::DialogComplete),
+                    )
+            }
+            DialogPage::BatchRename {
+                paths,
+                rename,
+                preview,
+            } => {
+                let mut case_row =
+                    widget::row::with_capacity(CaseChange::ALL.len()).spacing(space_s);
+                for case in CaseChange::ALL {
+                    let label = match case {
+                        CaseChange::Keep => fl!("batch-rename-case-keep"),
+                        CaseChange::Lower => fl!("batch-rename-case-lower"),
+                        CaseChange::Upper => fl!("batch-rename-case-upper"),
+                        CaseChange::Title => fl!("batch-rename-case-title"),
+                    };
+                    case_row = case_row.push(widget::radio(
+                        widget::text::body(label),
+                        case,
+                        Some(rename.case),
+                        move |case| {
+                            Message::BatchRenameUpdate(BatchRename {
+                                case,
+                                ..rename.clone()
+                            })
+                        },
+                    ));
+                }
+
+                let mut dialog = widget::dialog()
+                    .title(fl!("batch-rename-title", items = paths.len()))
+                    .control(
+                        widget::column::with_children(vec![
+                            widget::row::with_children(vec![
+                                Self::labeled_input(
+                                    fl!("batch-rename-find"),
+                                    widget::text_input("", rename.find.as_str())
+                                        .id(self.dialog_text_input.clone())
+                                        .on_input(move |find| {
+                                            Message::BatchRenameUpdate(BatchRename {
+                                                find,
+                                                ..rename.clone()
+                                            })
+                                        }),
+                                ),
+                                Self::labeled_input(
+                                    fl!("batch-rename-replace"),
+                                    widget::text_input("", rename.replace.as_str()).on_input(
+                                        move |replace| {
+                                            Message::BatchRenameUpdate(BatchRename {
+                                                replace,
+                                                ..rename.clone()
+                                            })
+                                        },
+                                    ),
+                                ),
+                            ])
+                            .spacing(space_s)
+                            .into(),
+                            widget::checkbox(fl!("batch-rename-regex"), rename.regex)
+                                .on_toggle(move |regex| {
+                                    Message::BatchRenameUpdate(BatchRename {
+                                        regex,
+                                        ..rename.clone()
+                                    })
+                                })
+                                .into(),
+                            case_row.into(),
+                            Self::labeled_input(
+                                fl!("batch-rename-template"),
+                                widget::text_input("", rename.template.as_str()).on_input(
+                                    move |template| {
+                                        Message::BatchRenameUpdate(BatchRename {
+                                            template,
+                                            ..rename.clone()
+                                        })
+                                    },
+                                ),
+                            ),
+                            widget::text::caption(fl!("batch-rename-tokens")).into(),
+                            widget::row::with_children(vec![
+                                Self::labeled_input(
+                                    fl!("batch-rename-start"),
+                                    widget::text_input("", rename.start.as_str()).on_input(
+                                        move |start| {
+                                            Message::BatchRenameUpdate(BatchRename {
+                                                start,
+                                                ..rename.clone()
+                                            })
+                                        },
+                                    ),
+                                ),
+                                Self::labeled_input(
+                                    fl!("batch-rename-padding"),
+                                    widget::text_input("", rename.padding.as_str()).on_input(
+                                        move |padding| {
+                                            Message::BatchRenameUpdate(BatchRename {
+                                                padding,
+                                                ..rename.clone()
+                                            })
+                                        },
+                                    ),
+                                ),
+                            ])
+                            .spacing(space_s)
+                            .into(),
+                        ])
+                        .spacing(space_s),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+
+                let complete_maybe = match preview {
+                    Ok(previews) => {
+                        let name = |path: &PathBuf| {
+                            path.file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default()
+                        };
+                        let mut column = widget::column::with_capacity(previews.len());
+                        for preview in previews.iter() {
+                            column = column.push(
+                                widget::row::with_children(vec![
+                                    widget::text::body(name(&preview.from))
+                                        .width(Length::Fill)
+                                        .into(),
+                                    widget::icon::from_name("go-next-symbolic").size(16).into(),
+                                    widget::text::body(name(&preview.to))
+                                        .width(Length::Fill)
+                                        .into(),
+                                ])
+                                .align_y(Alignment::Center)
+                                .spacing(space_xxs),
+                            );
+                            if let Some(problem) = &preview.problem {
+                                column = column.push(widget::text::caption(problem));
+                            }
+                        }
+                        dialog = dialog.control(
+                            widget::column::with_children(vec![
+                                widget::text::heading(fl!("batch-rename-preview")).into(),
+                                widget::scrollable(column.spacing(space_xxs))
+                                    .height(Length::Fixed(200.0))
+                                    .into(),
+                            ])
+                            .spacing(space_xxs),
+                        );
+
+                        let problems = previews
+                            .iter()
+                            .filter(|preview| preview.problem.is_some())
+                            .count();
+                        if problems > 0 {
+                            dialog = dialog.tertiary_action(widget::text::body(fl!(
+                                "batch-rename-problems",
+                                items = problems
+                            )));
+                            None
+                        } else if previews.iter().all(|preview| preview.from == preview.to) {
+                            None
+                        } else {
+                            Some(Message::DialogComplete)
+                        }
+                    }
+                    Err(err) => {
+                        dialog = dialog.tertiary_action(widget::text::body(err.as_str()));
+                        None
+                    }
+                };
+
+                dialog.primary_action(
+                    widget::button::suggested(fl!("rename")).on_press_maybe(complete_maybe),
+                )
+            }
+            DialogPage::ChangePermissions {
+                paths,
+                file_mode,
+                dir_mode,
+                owner,
+                group,
+                recursive,
+            } => {
+                let page = |file_mode: &String,
+                            dir_mode: &String,
+                            owner: &String,
+                            group: &String,
+                            recursive: bool| {
+                    DialogPage::ChangePermissions {
+                        paths: paths.clone(),
+                        file_mode: file_mode.clone(),
+                        dir_mode: dir_mode.clone(),
+                        owner: owner.clone(),
+                        group: group.clone(),
+                        recursive,
+                    }
+                };
+                let unchanged = fl!("permissions-unchanged");
+
+                let mut dialog = widget::dialog()
+                    .title(fl!("change-permissions-title", items = paths.len()))
+                    .control(
+                        widget::column::with_children(vec![
+                            widget::row::with_children(vec![
+                                Self::labeled_input(
+                                    fl!("permissions-file-mode"),
+                                    widget::text_input(unchanged.clone(), file_mode.clone())
+                                        .on_input(move |file_mode| {
+                                            Message::DialogUpdate(page(
+                                                &file_mode, dir_mode, owner, group, *recursive,
+                                            ))
+                                        }),
+                                ),
+                                Self::labeled_input(
+                                    fl!("permissions-dir-mode"),
+                                    widget::text_input(unchanged.clone(), dir_mode.clone())
+                                        .on_input(move |dir_mode| {
+                                            Message::DialogUpdate(page(
+                                                file_mode, &dir_mode, owner, group, *recursive,
+                                            ))
+                                        }),
+                                ),
+                            ])
+                            .spacing(space_s)
+                            .into(),
+                            widget::row::with_children(vec![
+                                Self::labeled_input(
+                                    fl!("owner"),
+                                    widget::text_input(unchanged.clone(), owner.clone()).on_input(
+                                        move |owner| {
+                                            Message::DialogUpdate(page(
+                                                file_mode, dir_mode, &owner, group, *recursive,
+                                            ))
+                                        },
+                                    ),
+                                ),
+                                Self::labeled_input(
+                                    fl!("group"),
+                                    widget::text_input(unchanged, group.clone()).on_input(
+                                        move |group| {
+                                            Message::DialogUpdate(page(
+                                                file_mode, dir_mode, owner, &group, *recursive,
+                                            ))
+                                        },
+                                    ),
+                                ),
+                            ])
+                            .spacing(space_s)
+                            .into(),
+                            widget::checkbox(fl!("permissions-recursive"), *recursive)
+                                .on_toggle(move |recursive| {
+                                    Message::DialogUpdate(page(
+                                        file_mode, dir_mode, owner, group, recursive,
+                                    ))
+                                })
+                                .into(),
+                        ])
+                        .spacing(space_s),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+
+                let complete_maybe =
+                    match PermissionChanges::parse(file_mode, dir_mode, owner, group) {
+                        Ok(changes) if !changes.is_empty() => Some(Message::DialogComplete),
+                        Ok(_) => None,
+                        Err(err) => {
+                            dialog = dialog.tertiary_action(widget::text::body(err));
+                            None
+                        }
+                    };
+                dialog.primary_action(
+                    widget::button::suggested(fl!("apply")).on_press_maybe(complete_maybe),
+                )
+            }
             DialogPage::Compress {
                 paths,
                 to,
                 name,
                 archive_type,
+                level,
                 password,
             } => {
                 let mut dialog = widget::dialog().title(fl!("create-archive"));
@@ -4730,6 +6016,7 @@ impl Application for App {
                                             to: to.clone(),
                                             name: name.clone(),
                                             archive_type: *archive_type,
+                                            level: *level,
                                             password: password.clone(),
                                         })
                                     })
@@ -4748,6 +6035,7 @@ impl Application for App {
                                         to: to.clone(),
                                         name: name.clone(),
                                         archive_type: archive_types[index],
+                                        level: *level,
                                         password: password.clone(),
                                     })
                                 })
@@ -4760,7 +6048,33 @@ impl Application for App {
                         .spacing(space_xxs),
                     );
 
-                if *archive_type == ArchiveType::Zip {
+                if archive_type
+                    .compression_level(CompressionLevel::default())
+                    .is_some()
+                {
+                    let levels = CompressionLevel::all();
+                    let selected = levels.iter().position(|x| x == level);
+                    dialog = dialog.control(Self::labeled_input(
+                        fl!("compression-level"),
+                        Element::from(widget::dropdown(
+                            &self.compression_levels,
+                            selected,
+                            move |index| index,
+                        ))
+                        .map(move |index| {
+                            Message::DialogUpdate(DialogPage::Compress {
+                                paths: paths.clone(),
+                                to: to.clone(),
+                                name: name.clone(),
+                                archive_type: *archive_type,
+                                level: levels[index],
+                                password: password.clone(),
+                            })
+                        }),
+                    ));
+                }
+
+                if matches!(archive_type, ArchiveType::SevenZ | ArchiveType::Zip) {
                     let password_unwrapped = password.clone().unwrap_or_else(String::default);
                     dialog = dialog.control(widget::column::with_children(vec![
                         widget::text::body(fl!("password")).into(),
@@ -4772,6 +6086,7 @@ impl Application for App {
                                     to: to.clone(),
                                     name: name.clone(),
                                     archive_type: *archive_type,
+                                    level: *level,
                                     password: Some(password_unwrapped),
                                 })
                             })
@@ -4784,6 +6099,61 @@ impl Application for App {
 
                 dialog
             }
+            DialogPage::ConfirmPlan {
+                plan,
+                replace_result,
+                tx,
+            } => {
+                let mut dialog = widget::dialog()
+                    .title(fl!("plan-title"))
+                    .body(fl!(
+                        "plan-description",
+                        files = plan.files,
+                        size = tab::format_size(plan.bytes)
+                    ))
+                    .icon(widget::icon::from_name("dialog-information").size(64))
+                    .primary_action(
+                        widget::button::suggested(fl!("start")).on_press(Message::DialogComplete),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+                if !plan.conflicts.is_empty() {
+                    let mut conflicts = widget::column::with_capacity(plan.conflicts.len());
+                    for conflict in plan.conflicts.iter() {
+                        conflicts =
+                            conflicts.push(widget::text::body(conflict.display().to_string()));
+                    }
+                    let mut column = widget::column::with_capacity(6)
+                        .push(widget::text::heading(fl!(
+                            "plan-conflicts",
+                            items = plan.conflicts.len()
+                        )))
+                        .push(widget::scrollable(conflicts).height(Length::Fixed(160.0)))
+                        .spacing(space_xxs);
+                    for (label, value) in [
+                        (fl!("plan-ask"), None),
+                        (fl!("plan-replace-all"), Some(ReplaceResult::Replace(true))),
+                        (fl!("plan-skip-all"), Some(ReplaceResult::Skip(true))),
+                        (fl!("plan-keep-both"), Some(ReplaceResult::KeepBoth)),
+                    ] {
+                        column = column.push(widget::radio(
+                            widget::text::body(label),
+                            value,
+                            Some(*replace_result),
+                            move |replace_result| {
+                                Message::DialogUpdate(DialogPage::ConfirmPlan {
+                                    plan: plan.clone(),
+                                    replace_result,
+                                    tx: tx.clone(),
+                                })
+                            },
+                        ));
+                    }
+                    dialog = dialog.control(column);
+                }
+                dialog
+            }
             DialogPage::EmptyTrash => widget::dialog()
                 .title(fl!("empty-trash"))
                 .body(fl!("empty-trash-warning"))
@@ -4797,17 +6167,75 @@ impl Application for App {
                 //TODO: try next dialog page (making sure index is used by Dialog messages)?
                 let (operation, _, err) = self.failed_operations.get(id)?;
 
-                //TODO: nice description of error
-                widget::dialog()
-                    .title("Failed operation")
-                    .body(format!("{:#?}\n{}", operation, err))
-                    .icon(widget::icon::from_name("dialog-error").size(64))
-                    //TODO: retry action
-                    .primary_action(
-                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
-                    )
+                // Copies that do not match are shown by their destination
+                let partial = match &err.kind {
+                    OperationErrorType::Partial { failed, .. } => Some((
+                        fl!("operation-partial-title"),
+                        fl!("operation-partial", items = failed.len()),
+                        failed
+                            .iter()
+                            .map(|failed| (&failed.from, &failed.error))
+                            .collect::<Vec<_>>(),
+                    )),
+                    OperationErrorType::VerifyFailed {
+                        mismatched, failed, ..
+                    } => Some((
+                        fl!("verify-failed-title"),
+                        fl!("verify-failed", items = mismatched.len()),
+                        mismatched
+                            .iter()
+                            .map(|mismatched| {
+                                (
+                                    mismatched.to.as_ref().unwrap_or(&mismatched.from),
+                                    &mismatched.error,
+                                )
+                            })
+                            .chain(failed.iter().map(|failed| (&failed.from, &failed.error)))
+                            .collect(),
+                    )),
+                    _ => None,
+                };
+                if let Some((title, body, failed)) = partial {
+                    let mut column = widget::column::with_capacity(failed.len()).spacing(space_xxs);
+                    for (path, error) in failed {
+                        column = column.push(widget::column::with_children(vec![
+                            widget::text::body(path.display().to_string()).into(),
+                            widget::text::caption(error).into(),
+                        ]));
+                    }
+                    widget::dialog()
+                        .title(title)
+                        .body(body)
+                        .icon(widget::icon::from_name("dialog-error").size(64))
+                        .control(widget::scrollable(column).height(Length::Fixed(240.0)))
+                        .primary_action(
+                            widget::button::suggested(fl!("retry-failed"))
+                                .on_press(Message::DialogComplete),
+                        )
+                        .secondary_action(
+                            widget::button::standard(fl!("skip")).on_press(Message::DialogCancel),
+                        )
+                        .tertiary_action(
+                            widget::button::text(fl!("export-log"))
+                                .on_press(Message::ExportFailedLog(*id)),
+                        )
+                } else {
+                    //TODO: nice description of error
+                    widget::dialog()
+                        .title("Failed operation")
+                        .body(format!("{:#?}\n{}", operation, err))
+                        .icon(widget::icon::from_name("dialog-error").size(64))
+                        //TODO: retry action
+                        .primary_action(
+                            widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                        )
+                }
             }
             DialogPage::ExtractPassword { id, password } => {
+                let label = match self.failed_operations.get(id) {
+                    Some((Operation::TestArchive { .. }, _, _)) => fl!("test-archive"),
+                    _ => fl!("extract-here"),
+                };
                 widget::dialog()
                     .title(fl!("extract-password-required"))
                     .icon(widget::icon::from_name("dialog-error").size(64))
@@ -4817,8 +6245,7 @@ impl Application for App {
                         },
                     ))
                     .primary_action(
-                        widget::button::suggested(fl!("extract-here"))
-                            .on_press(Message::DialogComplete),
+                        widget::button::suggested(label).on_press(Message::DialogComplete),
                     )
                     .secondary_action(
                         widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
@@ -5205,7 +6632,7 @@ impl Application for App {
                     None
                 } else {
                     let path = parent.join(name);
-                    if from != &path && path.exists() {
+                    if from != &path && batch_rename::name_taken(from, &path) {
                         if path.is_dir() {
                             dialog = dialog
                                 .tertiary_action(widget::text::body(fl!("folder-already-exists")));
@@ -5336,6 +6763,41 @@ impl Application for App {
                         name = name
                     )))
             }
+            DialogPage::Shred { paths, warnings } => {
+                let target = if paths.len() == 1 {
+                    format!(
+                        "\"{}\"",
+                        paths[0]
+                            .file_name()
+                            .map(std::ffi::OsStr::to_string_lossy)
+                            .unwrap_or_else(|| paths[0].to_string_lossy())
+                    )
+                } else {
+                    fl!("selected-items", items = paths.len())
+                };
+
+                let mut column = widget::column::with_capacity(1 + warnings.len())
+                    .push(widget::text(fl!(
+                        "shred-warning",
+                        target = target,
+                        passes = self.config.operations.shred_passes
+                    )))
+                    .spacing(space_xxs);
+                for warning in warnings.iter() {
+                    column = column.push(widget::text::caption(warning));
+                }
+
+                widget::dialog()
+                    .title(fl!("shred-question"))
+                    .icon(widget::icon::from_name("dialog-warning").size(64))
+                    .primary_action(
+                        widget::button::destructive(fl!("shred")).on_press(Message::DialogComplete),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    )
+                    .control(column)
+            }
             DialogPage::FavoritePathError { path, .. } => widget::dialog()
                 .title(fl!("favorite-path-error"))
                 .body(fl!(
@@ -5349,6 +6811,28 @@ impl Application for App {
                 .secondary_action(
                     widget::button::standard(fl!("keep")).on_press(Message::DialogCancel),
                 ),
+            DialogPage::UnfinishedOperation(unfinished) => {
+                let mut dialog = widget::dialog()
+                    .title(fl!("unfinished-operation"))
+                    .body(format!(
+                        "{}\n{}",
+                        unfinished.operation.interrupted_text(),
+                        fl!("unfinished-operation-description")
+                    ))
+                    .icon(widget::icon::from_name("dialog-warning").size(64))
+                    .primary_action(
+                        widget::button::suggested(fl!("resume")).on_press(Message::DialogComplete),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+                if unfinished.can_roll_back() {
+                    dialog = dialog.tertiary_action(
+                        widget::button::text(fl!("roll-back")).on_press(Message::RollBackOperation),
+                    );
+                }
+                dialog
+            }
         };
         Some(dialog.into())
     }
@@ -5363,6 +6847,7 @@ impl Application for App {
         } = theme::active().cosmic().spacing;
 
         let mut title = String::new();
+        let mut current_file_opt = None;
         let mut total_progress = 0.0;
         let mut count = 0;
         let mut all_paused = true;
@@ -5373,7 +6858,8 @@ impl Application for App {
             if op.show_progress_notification() {
                 let progress = controller.progress();
                 if title.is_empty() {
-                    title = op.pending_text(progress, controller.state());
+                    title = op.pending_text(controller);
+                    current_file_opt = controller.details().current_file;
                 }
                 total_progress += progress;
                 count += 1;
@@ -5390,6 +6876,7 @@ impl Application for App {
         let finished = count - running;
         total_progress /= count as f32;
         if running > 1 {
+            current_file_opt = None;
             if finished > 0 {
                 title = fl!(
                     "operations-running-finished",
@@ -5449,6 +6936,12 @@ impl Application for App {
             .align_y(Alignment::Center)
             .into(),
             widget::text::body(title).into(),
+            match current_file_opt {
+                Some(current_file) => {
+                    widget::text::caption(current_file.display().to_string()).into()
+                }
+                None => widget::Space::with_height(0).into(),
+            },
             widget::Space::with_height(space_s).into(),
             widget::row::with_children(vec![
                 widget::button::link(fl!("details"))
@@ -5475,6 +6968,7 @@ impl Application for App {
             &self.core,
             self.tab_model.active_data::<Tab>(),
             &self.config,
+            &self.journal,
             &self.modifiers,
             &self.key_binds,
         )]
@@ -0,0 +1,1706 @@
+use chrono::{Local, NaiveDate};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    ffi::OsString,
+    fs,
+    io::{self, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use sevenz_rust::{SevenZArchiveEntry, SevenZMethod, SevenZReader};
+use zip::result::{ZipError, ZipResult};
+
+use crate::{
+    fl,
+    mime_icon::mime_for_path,
+    operation::{reader::OpReader, Controller},
+    tab,
+    volumes::{self, VolumeReader},
+};
+
+/// Mime types of archives that can be browsed and extracted
+pub const MIME_TYPES: &[&str] = &[
+    "application/gzip",
+    "application/x-compressed-tar",
+    "application/x-tar",
+    "application/zip",
+    "application/x-7z-compressed",
+    #[cfg(feature = "bzip2")]
+    "application/x-bzip",
+    #[cfg(feature = "bzip2")]
+    "application/x-bzip-compressed-tar",
+    #[cfg(feature = "bzip2")]
+    "application/x-bzip2",
+    #[cfg(feature = "bzip2")]
+    "application/x-bzip2-compressed-tar",
+    #[cfg(feature = "xz2")]
+    "application/x-xz",
+    #[cfg(feature = "xz2")]
+    "application/x-xz-compressed-tar",
+    #[cfg(feature = "zstd")]
+    "application/zstd",
+    #[cfg(feature = "zstd")]
+    "application/x-zstd-compressed-tar",
+];
+
+/// File or directory inside an archive
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArchiveEntry {
+    /// Path relative to the root of the archive
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub mtime: Option<SystemTime>,
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && MIME_TYPES.contains(&mime_for_path(path, None, false).essence_str())
+}
+
+/// Split a path below an archive file into the archive and the path inside it. Items copied out
+/// of archives use these paths, which do not exist on disk.
+pub fn split_path(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    if path.symlink_metadata().is_ok() {
+        return None;
+    }
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.symlink_metadata().is_ok())?;
+    if !is_archive(archive) {
+        return None;
+    }
+    let inner = path.strip_prefix(archive).ok()?.to_path_buf();
+    Some((archive.to_path_buf(), inner))
+}
+
+/// Paths inside archives may only descend from the root, anything else is skipped
+pub fn enclosed(path: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => enclosed.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if enclosed.as_os_str().is_empty() {
+        None
+    } else {
+        Some(enclosed)
+    }
+}
+
+/// Encoding of the names of zip entries that are not marked as UTF-8. Older tools write names in
+/// the code page of the system that made the archive, which is not recorded in it.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum NameEncoding {
+    /// Guess the encoding from the names
+    #[default]
+    Auto,
+    Utf8,
+    /// The code page of DOS in western languages, which zip archives use by default
+    Cp437,
+    ShiftJis,
+    Cp866,
+}
+
+impl NameEncoding {
+    pub const ALL: [Self; 5] = [
+        Self::Auto,
+        Self::Utf8,
+        Self::Cp437,
+        Self::ShiftJis,
+        Self::Cp866,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            Self::Auto => fl!("name-encoding-auto"),
+            Self::Utf8 => fl!("name-encoding-utf8"),
+            Self::Cp437 => fl!("name-encoding-cp437"),
+            Self::ShiftJis => fl!("name-encoding-shift-jis"),
+            Self::Cp866 => fl!("name-encoding-cp866"),
+        }
+    }
+
+    /// The encoding of names in a zip archive, guessed from names not marked as UTF-8 if it is
+    /// automatic
+    pub fn resolve<R: Read + io::Seek>(self, zip: &mut zip::ZipArchive<R>) -> ZipResult<Self> {
+        if self != Self::Auto {
+            return Ok(self);
+        }
+        let mut detector = chardetng::EncodingDetector::new();
+        for i in 0..zip.len() {
+            let file = zip.by_index_raw(i)?;
+            if is_legacy_name(&file) {
+                // One name per line, so that characters do not span names
+                detector.feed(file.name_raw(), false);
+                detector.feed(b"\n", false);
+            }
+        }
+        detector.feed(b"", true);
+        let guess = detector.guess(None, true);
+        Ok(if guess == encoding_rs::UTF_8 {
+            Self::Utf8
+        } else if guess == encoding_rs::SHIFT_JIS {
+            Self::ShiftJis
+        } else if guess == encoding_rs::IBM866 {
+            Self::Cp866
+        } else {
+            Self::Cp437
+        })
+    }
+
+    /// Name of a zip entry, decoded with this encoding unless it is marked as UTF-8
+    pub fn decode<'a>(self, file: &'a zip::read::ZipFile<'_>) -> Cow<'a, str> {
+        if !is_legacy_name(file) {
+            return Cow::Borrowed(file.name());
+        }
+        let raw = file.name_raw();
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(raw),
+            Self::ShiftJis => encoding_rs::SHIFT_JIS.decode_without_bom_handling(raw).0,
+            Self::Cp866 => encoding_rs::IBM866.decode_without_bom_handling(raw).0,
+            Self::Auto | Self::Cp437 => Cow::Borrowed(file.name()),
+        }
+    }
+
+    /// Path of a zip entry decoded with this encoding, or `None` if it leaves the archive
+    pub fn enclosed_name(self, file: &zip::read::ZipFile<'_>) -> Option<PathBuf> {
+        enclosed(Path::new(&*self.decode(file)))
+    }
+}
+
+// The zip crate reads names that are not marked as UTF-8 as CP437, so names that differ from
+// their bytes hold characters of some legacy encoding
+fn is_legacy_name(file: &zip::read::ZipFile<'_>) -> bool {
+    file.name().as_bytes() != file.name_raw()
+}
+
+// Zip archives store times without a time zone, which are the local time by convention
+fn zip_mtime(datetime: zip::DateTime) -> Option<SystemTime> {
+    let local = NaiveDate::from_ymd_opt(
+        datetime.year().into(),
+        datetime.month().into(),
+        datetime.day().into(),
+    )?
+    .and_hms_opt(
+        datetime.hour().into(),
+        datetime.minute().into(),
+        datetime.second().into(),
+    )?
+    .and_local_timezone(Local)
+    .earliest()?;
+    Some(local.into())
+}
+
+/// Modification time of a zip entry, preferring the extended timestamp field, which is exact and
+/// in UTC
+pub fn zip_entry_mtime(file: &zip::read::ZipFile<'_>) -> Option<SystemTime> {
+    file.extra_data_fields()
+        .find_map(|field| match field {
+            zip::ExtraField::ExtendedTimestamp(timestamp) => timestamp
+                .mod_time()
+                .map(|mtime| SystemTime::UNIX_EPOCH + Duration::from_secs(mtime.into())),
+            _ => None,
+        })
+        .or_else(|| file.last_modified().and_then(zip_mtime))
+}
+
+/// Owner and group of a zip entry, from the Info-ZIP Unix extra field
+pub fn zip_entry_owner(file: &zip::read::ZipFile<'_>) -> Option<(u32, u32)> {
+    // Ids are stored little endian with their size in front
+    fn id(data: &[u8]) -> Option<(u32, &[u8])> {
+        let (&size, data) = data.split_first()?;
+        let bytes = data.get(..usize::from(size))?;
+        let id = bytes.iter().rev().try_fold(0u32, |id, byte| {
+            id.checked_mul(256)?.checked_add(u32::from(*byte))
+        })?;
+        Some((id, &data[bytes.len()..]))
+    }
+
+    let mut extra = file.extra_data()?;
+    while extra.len() >= 4 {
+        let kind = u16::from_le_bytes([extra[0], extra[1]]);
+        let len = usize::from(u16::from_le_bytes([extra[2], extra[3]]));
+        let data = extra.get(4..4 + len)?;
+        if kind == 0x7875 {
+            // Only version 1 of the field exists
+            let (&1, data) = data.split_first()? else {
+                return None;
+            };
+            let (uid, data) = id(data)?;
+            let (gid, _) = id(data)?;
+            return Some((uid, gid));
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+fn tar_mtime(header: &tar::Header) -> Option<SystemTime> {
+    header
+        .mtime()
+        .ok()
+        .map(|mtime| SystemTime::UNIX_EPOCH + Duration::from_secs(mtime))
+}
+
+/// Decompress the tar stream of an archive read from `reader`, or `None` if it is a zip archive.
+/// The name of the compression method is returned with the stream.
+pub fn tar_decoder<R: io::BufRead + 'static>(
+    path: &Path,
+    reader: R,
+) -> io::Result<Option<(Box<dyn Read>, &'static str)>> {
+    let mime = mime_for_path(path, None, false);
+    let decoder: (Box<dyn Read>, &'static str) = match mime.essence_str() {
+        "application/gzip" | "application/x-compressed-tar" => {
+            (Box::new(flate2::read::GzDecoder::new(reader)), "Gzip")
+        }
+        "application/x-tar" => (Box::new(reader), "Stored"),
+        "application/zip" => return Ok(None),
+        #[cfg(feature = "bzip2")]
+        "application/x-bzip"
+        | "application/x-bzip-compressed-tar"
+        | "application/x-bzip2"
+        | "application/x-bzip2-compressed-tar" => {
+            (Box::new(bzip2::read::BzDecoder::new(reader)), "Bzip2")
+        }
+        #[cfg(feature = "xz2")]
+        "application/x-xz" | "application/x-xz-compressed-tar" => {
+            (Box::new(xz2::read::XzDecoder::new(reader)), "Xz")
+        }
+        #[cfg(feature = "zstd")]
+        "application/zstd" | "application/x-zstd-compressed-tar" => {
+            (Box::new(zstd::Decoder::with_buffer(reader)?), "Zstd")
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported mime type {:?}", mime),
+            ))
+        }
+    };
+    Ok(Some(decoder))
+}
+
+/// Open the decompressed tar stream of an archive, or `None` if it is a zip archive
+fn open_tar(path: &Path) -> io::Result<Option<tar::Archive<Box<dyn Read>>>> {
+    let file = io::BufReader::new(volumes::open(path)?);
+    Ok(tar_decoder(&volumes::archive_path(path), file)?
+        .map(|(decoder, _)| tar::Archive::new(decoder)))
+}
+
+fn open_zip(path: &Path) -> io::Result<zip::ZipArchive<io::BufReader<VolumeReader>>> {
+    Ok(zip::ZipArchive::new(io::BufReader::new(volumes::open(
+        path,
+    )?))?)
+}
+
+fn is_7z(path: &Path) -> bool {
+    mime_for_path(volumes::archive_path(path), None, false).essence_str()
+        == "application/x-7z-compressed"
+}
+
+fn open_7z<R: Read + Seek>(
+    mut reader: R,
+    password: Option<&str>,
+) -> Result<SevenZReader<R>, sevenz_rust::Error> {
+    let len = reader.seek(io::SeekFrom::End(0))?;
+    let password = password.map_or_else(sevenz_rust::Password::empty, sevenz_rust::Password::from);
+    SevenZReader::new(reader, len, password)
+}
+
+/// Errors of 7z archives as zip errors, so that both ask for passwords the same way
+fn sevenz_error(err: sevenz_rust::Error) -> ZipError {
+    match err {
+        sevenz_rust::Error::PasswordRequired => {
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)
+        }
+        sevenz_rust::Error::MaybeBadPassword(_) => ZipError::InvalidPassword,
+        sevenz_rust::Error::Io(err, context) if context.is_empty() => ZipError::Io(err),
+        sevenz_rust::Error::Io(err, context) => {
+            ZipError::Io(io::Error::new(err.kind(), format!("{}: {}", context, err)))
+        }
+        err => ZipError::Io(io::Error::new(io::ErrorKind::Other, err.to_string())),
+    }
+}
+
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+/// Unix mode of a 7z entry, which is stored in the upper bits of its attributes
+fn sevenz_mode(file: &SevenZArchiveEntry) -> Option<u32> {
+    (file.has_windows_attributes && file.windows_attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0)
+        .then(|| file.windows_attributes >> 16)
+}
+
+/// Attributes of a 7z entry for `path`, holding its Unix mode the way p7zip stores it
+#[cfg(unix)]
+pub fn sevenz_attributes(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).ok()?;
+    let dir = if metadata.is_dir() {
+        FILE_ATTRIBUTE_DIRECTORY
+    } else {
+        0
+    };
+    Some((metadata.mode() << 16) | FILE_ATTRIBUTE_UNIX_EXTENSION | dir)
+}
+
+#[cfg(not(unix))]
+pub fn sevenz_attributes(_path: &Path) -> Option<u32> {
+    //TODO: store attributes on other platforms?
+    None
+}
+
+fn sevenz_mtime(file: &SevenZArchiveEntry) -> Option<SystemTime> {
+    file.has_last_modified_date
+        .then(|| SystemTime::from(file.last_modified_date()))
+}
+
+/// Reader that remembers if reading failed. Errors while decoding 7z entries may come from a
+/// wrong password, unlike errors while writing them.
+struct DecodeReader<'a> {
+    reader: &'a mut dyn Read,
+    failed: bool,
+}
+
+impl Read for DecodeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf).inspect_err(|_| self.failed = true)
+    }
+}
+
+/// List every entry of an archive
+pub fn list(archive: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    if is_7z(archive) {
+        // Archives with encrypted names cannot be listed without their password
+        let sevenz =
+            open_7z(io::BufReader::new(volumes::open(archive)?), None).map_err(sevenz_error)?;
+        for file in sevenz.archive().files.iter() {
+            let Some(path) = enclosed(Path::new(file.name())) else {
+                continue;
+            };
+            if !file.is_anti_item() {
+                entries.push(ArchiveEntry {
+                    path,
+                    is_dir: file.is_directory(),
+                    size: file.size(),
+                    mtime: sevenz_mtime(file),
+                });
+            }
+        }
+        return Ok(entries);
+    }
+    match open_tar(archive)? {
+        Some(mut tar) => {
+            for entry in tar.entries()? {
+                let entry = entry?;
+                let Some(path) = enclosed(&entry.path()?) else {
+                    continue;
+                };
+                let header = entry.header();
+                entries.push(ArchiveEntry {
+                    path,
+                    is_dir: header.entry_type().is_dir(),
+                    size: header.size()?,
+                    mtime: tar_mtime(header),
+                });
+            }
+        }
+        None => {
+            let mut zip = open_zip(archive)?;
+            let encoding = NameEncoding::Auto.resolve(&mut zip)?;
+            for i in 0..zip.len() {
+                // Raw entries can be listed without the password of encrypted archives
+                let file = zip.by_index_raw(i)?;
+                let Some(path) = encoding.enclosed_name(&file) else {
+                    continue;
+                };
+                entries.push(ArchiveEntry {
+                    path,
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    mtime: zip_entry_mtime(&file),
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// Number of archive listings kept while browsing
+const LISTING_CACHE_SIZE: usize = 8;
+
+// Listing of an archive along with the size and modification time it had when listed
+struct Listing {
+    archive: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+    entries: Arc<[ArchiveEntry]>,
+}
+
+// Recently browsed archives, most recent last
+static LISTING_CACHE: Lazy<Mutex<VecDeque<Listing>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// List every entry of an archive, reusing the previous listing if the archive has not changed
+/// since. Browsing lists the archive again each time another directory inside it is opened.
+pub fn list_cached(archive: &Path) -> io::Result<Arc<[ArchiveEntry]>> {
+    let metadata = fs::metadata(archive)?;
+    let (len, modified) = (metadata.len(), metadata.modified().ok());
+    {
+        let mut cache = LISTING_CACHE.lock().unwrap();
+        if let Some(i) = cache.iter().position(|listing| listing.archive == archive) {
+            let listing = cache.remove(i).unwrap();
+            if listing.len == len && listing.modified == modified {
+                let entries = listing.entries.clone();
+                cache.push_back(listing);
+                return Ok(entries);
+            }
+        }
+    }
+
+    let entries: Arc<[ArchiveEntry]> = list(archive)?.into();
+    let mut cache = LISTING_CACHE.lock().unwrap();
+    if cache.len() >= LISTING_CACHE_SIZE {
+        cache.pop_front();
+    }
+    cache.push_back(Listing {
+        archive: archive.to_path_buf(),
+        len,
+        modified,
+        entries: entries.clone(),
+    });
+    Ok(entries)
+}
+
+/// Entries directly inside `dir` and the number of items inside each of them. Archives may
+/// leave out the entries of parent directories, so those are added too.
+pub fn read_dir(entries: &[ArchiveEntry], dir: &Path) -> Vec<(ArchiveEntry, usize)> {
+    let mut children = BTreeMap::<PathBuf, (Option<&ArchiveEntry>, BTreeSet<OsString>)>::new();
+    for entry in entries.iter() {
+        let Ok(relative) = entry.path.strip_prefix(dir) else {
+            continue;
+        };
+        let mut components = relative.components();
+        let Some(name) = components.next() else {
+            continue;
+        };
+        let child = children.entry(dir.join(name)).or_default();
+        match components.next() {
+            Some(grandchild) => {
+                child.1.insert(grandchild.as_os_str().to_os_string());
+            }
+            None => child.0 = Some(entry),
+        }
+    }
+    children
+        .into_iter()
+        .map(|(path, (entry_opt, grandchildren))| {
+            let entry = match entry_opt {
+                Some(entry) => ArchiveEntry {
+                    // Directories with contents may be listed without the directory flag
+                    is_dir: entry.is_dir || !grandchildren.is_empty(),
+                    ..entry.clone()
+                },
+                None => ArchiveEntry {
+                    path,
+                    is_dir: true,
+                    size: 0,
+                    mtime: None,
+                },
+            };
+            (entry, grandchildren.len())
+        })
+        .collect()
+}
+
+/// Read up to `limit` bytes of a file inside an archive
+pub fn read(archive: &Path, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+    let mut data_opt = None;
+    read_each(archive, &[(path.to_path_buf(), limit)], |_path, data| {
+        data_opt = Some(data);
+        false
+    })?;
+    data_opt.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{:?} not found in {:?}", path, archive),
+        )
+    })
+}
+
+/// Read up to a limit of bytes of each of several files inside an archive, in a single pass
+/// over compressed streams. Each file is passed to `f` once it is read, which returns false to
+/// stop reading. Files that are not found are skipped.
+pub fn read_each(
+    archive: &Path,
+    files: &[(PathBuf, u64)],
+    mut f: impl FnMut(&Path, Vec<u8>) -> bool,
+) -> io::Result<()> {
+    let mut remaining: BTreeMap<&Path, u64> = files
+        .iter()
+        .map(|(path, limit)| (path.as_path(), *limit))
+        .collect();
+    let mut read_file = |path: &Path, limit: u64, reader: &mut dyn Read| -> io::Result<bool> {
+        let mut data = Vec::new();
+        reader.take(limit).read_to_end(&mut data)?;
+        Ok(f(path, data))
+    };
+
+    if is_7z(archive) {
+        let mut sevenz =
+            open_7z(io::BufReader::new(volumes::open(archive)?), None).map_err(sevenz_error)?;
+        sevenz
+            .for_each_entries(|file, reader| {
+                if let Some(path) = enclosed(Path::new(file.name())) {
+                    if let Some(limit) = remaining.remove(path.as_path()) {
+                        if !read_file(&path, limit, &mut *reader)? || remaining.is_empty() {
+                            return Ok(false);
+                        }
+                    }
+                }
+                // Entries of solid archives follow each other in one stream
+                io::copy(reader, &mut io::sink())?;
+                Ok(true)
+            })
+            .map_err(sevenz_error)?;
+        return Ok(());
+    }
+    match open_tar(archive)? {
+        Some(mut tar) => {
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                let Some(path) = enclosed(&entry.path()?) else {
+                    continue;
+                };
+                if let Some(limit) = remaining.remove(path.as_path()) {
+                    if !read_file(&path, limit, &mut entry)? || remaining.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+        None => {
+            let mut zip = open_zip(archive)?;
+            let encoding = NameEncoding::Auto.resolve(&mut zip)?;
+            for i in 0..zip.len() {
+                let Some(path) = encoding.enclosed_name(&zip.by_index_raw(i)?) else {
+                    continue;
+                };
+                if let Some(limit) = remaining.remove(path.as_path()) {
+                    if !read_file(&path, limit, &mut zip.by_index(i)?)? || remaining.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Archives expanding more than this many times their size are treated as decompression bombs
+const MAX_RATIO: u64 = 1000;
+// Small archives can have a large ratio without doing harm, so it is only checked past this size
+const RATIO_MIN_SIZE: u64 = 100_000_000;
+// Longest chain of links followed when checking where a link points
+const MAX_LINK_FOLLOWS: usize = 40;
+
+/// Protection for extracting archives, which rejects entries that would be written outside of
+/// the destination and stops archives that expand far beyond their size
+pub struct Guard {
+    root: PathBuf,
+    archive_size: u64,
+    size_limit: Option<u64>,
+    written: u64,
+    links: Vec<(PathBuf, PathBuf)>,
+    warnings: Vec<String>,
+}
+
+impl Guard {
+    /// Guard extraction to `root` of an archive of `archive_size` bytes. Without a size limit,
+    /// the ratio of extracted to archive bytes is not checked either.
+    pub fn new(root: &Path, archive_size: u64, size_limit: Option<u64>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            archive_size,
+            size_limit,
+            written: 0,
+            links: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn reject(&mut self, warning: String) {
+        log::warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    /// Path of an entry relative to the root, or `None` if its name leaves the root with `..`
+    /// or an absolute path
+    pub fn entry_path(&mut self, name: &Path) -> Option<PathBuf> {
+        let path = enclosed(name);
+        // Entries for the root itself are skipped without a warning
+        if path.is_none() && name.components().any(|c| c != Component::CurDir) {
+            self.reject(fl!(
+                "extract-rejected-path",
+                path = name.display().to_string()
+            ));
+        }
+        path
+    }
+
+    /// Check that writing to `path` does not go through a link, which could lead anywhere
+    pub fn check_path(&mut self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            self.reject(fl!(
+                "extract-rejected-path",
+                path = path.display().to_string()
+            ));
+            return false;
+        };
+        let mut ancestor = self.root.clone();
+        for component in relative.components() {
+            ancestor.push(component);
+            if ancestor.is_symlink() {
+                self.reject(fl!(
+                    "extract-rejected-through-link",
+                    path = relative.display().to_string()
+                ));
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Check that a link created at `path` points inside the root, following links that were
+    /// already extracted
+    pub fn check_link(&mut self, path: &Path, target: &Path) -> bool {
+        if self.link_inside(path, target) {
+            self.links.push((path.to_path_buf(), target.to_path_buf()));
+            true
+        } else {
+            self.reject(fl!(
+                "extract-rejected-link",
+                path = path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            ));
+            false
+        }
+    }
+
+    // Resolve a link target the way the file system would, without leaving the root
+    fn link_inside(&self, path: &Path, target: &Path) -> bool {
+        let Some(parent) = path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(&self.root).ok())
+        else {
+            return false;
+        };
+        let mut resolved = parent.to_path_buf();
+        let mut pending: VecDeque<PathBuf> = target
+            .components()
+            .map(|component| PathBuf::from(component.as_os_str()))
+            .collect();
+        let mut follows = 0;
+        while let Some(next) = pending.pop_front() {
+            match next.components().next() {
+                Some(Component::Normal(name)) => {
+                    resolved.push(name);
+                    if let Ok(link_target) = fs::read_link(self.root.join(&resolved)) {
+                        // Long chains may be loops
+                        follows += 1;
+                        if follows > MAX_LINK_FOLLOWS {
+                            return false;
+                        }
+                        resolved.pop();
+                        for component in link_target.components().rev() {
+                            pending.push_front(PathBuf::from(component.as_os_str()));
+                        }
+                    }
+                }
+                Some(Component::ParentDir) => {
+                    if !resolved.pop() {
+                        return false;
+                    }
+                }
+                Some(Component::CurDir) | None => {}
+                Some(Component::RootDir | Component::Prefix(_)) => return false,
+            }
+        }
+        true
+    }
+
+    /// Count bytes written, failing if the archive expands beyond the size limit or as far as
+    /// a decompression bomb would
+    pub fn add_bytes(&mut self, count: u64) -> io::Result<()> {
+        self.written += count;
+        let Some(size_limit) = self.size_limit else {
+            return Ok(());
+        };
+        if self.written > size_limit {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                fl!("extract-size-exceeded", size = tab::format_size(size_limit)),
+            ));
+        }
+        if self.written > RATIO_MIN_SIZE && self.written / self.archive_size.max(1) > MAX_RATIO {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                fl!("extract-ratio-exceeded", ratio = MAX_RATIO),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Remove links that point outside of the root once everything is extracted, as later
+    /// links can change where earlier ones lead, and return warnings about rejected entries
+    pub fn finish(mut self) -> Vec<String> {
+        for (path, target) in std::mem::take(&mut self.links) {
+            if !self.link_inside(&path, &target) {
+                if let Err(err) = fs::remove_file(&path) {
+                    log::warn!("failed to remove link {:?}: {}", path, err);
+                }
+                self.reject(fl!(
+                    "extract-rejected-link",
+                    path = path
+                        .strip_prefix(&self.root)
+                        .unwrap_or(&path)
+                        .display()
+                        .to_string()
+                ));
+            }
+        }
+        self.warnings
+    }
+}
+
+/// Unpack every entry of a tar archive to `base`, which is the root of `guard` unless the
+/// archive holds a single folder that is the root
+pub fn unpack_tar<R: Read>(
+    mut tar: tar::Archive<R>,
+    base: &Path,
+    guard: &mut Guard,
+    controller: &Controller,
+    restore_owner: bool,
+) -> io::Result<()> {
+    fs::create_dir_all(base)?;
+    // Directories are unpacked last, deepest first, so their permissions and times are not
+    // changed by their contents
+    let mut dirs = Vec::new();
+    // The tar crate sets the mode of the entries it unpacks and the times of files, but not the
+    // times of directories
+    let mut metadata = Vec::new();
+    let mut buffer = vec![0; 4 * 1024 * 1024];
+    for entry in tar.entries()? {
+        check(controller)?;
+        let mut entry = entry?;
+        let Some(path) = guard.entry_path(&entry.path()?) else {
+            continue;
+        };
+        let to = base.join(&path);
+        if !guard.check_path(&to) {
+            continue;
+        }
+        controller.set_current_file(&to);
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            metadata.push(tar_entry_metadata(to, entry.header()));
+            dirs.push(entry);
+            continue;
+        } else if entry_type.is_file() || entry_type.is_gnu_sparse() {
+            // Files are written here so their data is counted as it is produced, as sparse
+            // entries expand beyond their size in the archive
+            write_entry(&mut entry, &to, guard, &mut buffer)?;
+            metadata.push(EntryMetadata {
+                mode: entry.header().mode().ok().map(|mode| mode & 0o7777),
+                ..tar_entry_metadata(to, entry.header())
+            });
+            continue;
+        } else if entry_type.is_symlink() {
+            match entry.link_name()? {
+                Some(target) if guard.check_link(&to, &target) => {}
+                _ => continue,
+            }
+        } else if entry_type.is_hard_link() {
+            // Hard links may only point at entries that were already unpacked
+            let Some(target) = entry
+                .link_name()?
+                .and_then(|target| guard.entry_path(&target))
+            else {
+                continue;
+            };
+            if !guard.check_path(&base.join(target)) {
+                continue;
+            }
+        }
+        if entry.unpack_in(base)? && !entry_type.is_hard_link() {
+            metadata.push(tar_entry_metadata(to, entry.header()));
+        }
+    }
+    dirs.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut dir in dirs {
+        dir.unpack_in(base)?;
+    }
+    restore_metadata(metadata, restore_owner);
+    Ok(())
+}
+
+/// Unpack every entry of a 7z archive read from `reader` to `base`, decrypting it with
+/// `password` if it is encrypted. 7z archives store no owners, so only permissions and times are
+/// restored.
+pub fn unpack_7z<R: Read + Seek>(
+    reader: R,
+    password: Option<&str>,
+    base: &Path,
+    guard: &mut Guard,
+    controller: &Controller,
+) -> ZipResult<()> {
+    fs::create_dir_all(base)?;
+    let mut sevenz = open_7z(reader, password).map_err(sevenz_error)?;
+    let mut buffer = vec![0; 4 * 1024 * 1024];
+    let mut metadata = Vec::new();
+    sevenz
+        .for_each_entries(|file, reader| {
+            let mut reader = DecodeReader {
+                reader,
+                failed: false,
+            };
+            match unpack_7z_entry(file, &mut reader, base, guard, controller, &mut buffer) {
+                Ok(entry_metadata) => {
+                    metadata.extend(entry_metadata);
+                    Ok(true)
+                }
+                Err(err) if reader.failed && !controller.is_cancelled() => {
+                    Err(sevenz_rust::Error::io(err))
+                }
+                Err(err) => Err(sevenz_rust::Error::io_msg(err, file.name().to_string())),
+            }
+        })
+        .map_err(sevenz_error)?;
+    restore_metadata(metadata, false);
+    Ok(())
+}
+
+fn unpack_7z_entry(
+    file: &SevenZArchiveEntry,
+    reader: &mut impl Read,
+    base: &Path,
+    guard: &mut Guard,
+    controller: &Controller,
+    buffer: &mut [u8],
+) -> io::Result<Option<EntryMetadata>> {
+    let to = guard
+        .entry_path(Path::new(file.name()))
+        .map(|path| base.join(path))
+        .filter(|to| !file.is_anti_item() && guard.check_path(to));
+    let Some(to) = to else {
+        // Entries of solid archives follow each other in one stream
+        io::copy(reader, &mut io::sink())?;
+        return Ok(None);
+    };
+    controller.set_current_file(&to);
+    let mode = sevenz_mode(file);
+    if file.is_directory() {
+        fs::create_dir_all(&to)?;
+    } else if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+        let mut target = String::new();
+        reader.read_to_string(&mut target)?;
+        if !guard.check_link(&to, Path::new(&target)) {
+            return Ok(None);
+        }
+        create_symlink(Path::new(&target), &to)?;
+    } else {
+        // Progress is counted while reading the archive
+        write_entry(reader, &to, guard, buffer)?;
+    }
+    Ok(Some(EntryMetadata {
+        path: to,
+        mode: mode.map(|mode| mode & 0o7777),
+        mtime: sevenz_mtime(file),
+        owner: None,
+    }))
+}
+
+fn tar_entry_metadata(path: PathBuf, header: &tar::Header) -> EntryMetadata {
+    let id = |id: io::Result<u64>| id.ok().and_then(|id| u32::try_from(id).ok());
+    EntryMetadata {
+        path,
+        mode: None,
+        mtime: tar_mtime(header),
+        owner: id(header.uid()).zip(id(header.gid())),
+    }
+}
+
+/// Times, permissions and ownership stored in an archive for an extracted entry
+pub struct EntryMetadata {
+    pub path: PathBuf,
+    pub mode: Option<u32>,
+    pub mtime: Option<SystemTime>,
+    pub owner: Option<(u32, u32)>,
+}
+
+/// Apply the metadata of entries once all of them are extracted. Entries are changed deepest
+/// first, so directories are not made read-only before their contents. The stored owner is only
+/// applied if `restore_owner` is set, and so are set-user-ID, set-group-ID and sticky bits, which
+/// also need the process to be privileged. Failures are logged without stopping extraction.
+pub fn restore_metadata(mut entries: Vec<EntryMetadata>, restore_owner: bool) {
+    #[cfg(unix)]
+    let mode_mask = if restore_owner && uzers::get_effective_uid() == 0 {
+        0o7777
+    } else {
+        0o777
+    };
+    entries.sort_by(|a, b| b.path.cmp(&a.path));
+    for entry in entries.iter() {
+        // Changing the owner clears the set-user-ID bits, so it is done before the mode
+        if restore_owner {
+            if let Some((uid, gid)) = entry.owner {
+                set_owner(&entry.path, uid, gid);
+            }
+        }
+        #[cfg(unix)]
+        if let Some(mode) = entry.mode {
+            use std::os::unix::fs::PermissionsExt;
+
+            // Links have no mode of their own and setting it would change their targets
+            if !entry.path.is_symlink() {
+                let permissions = fs::Permissions::from_mode(mode & mode_mask);
+                if let Err(err) = fs::set_permissions(&entry.path, permissions) {
+                    log::warn!("failed to set permissions for {:?}: {}", entry.path, err);
+                }
+            }
+        }
+        if let Some(mtime) = entry.mtime {
+            let mtime = filetime::FileTime::from_system_time(mtime);
+            if let Err(err) = filetime::set_symlink_file_times(&entry.path, mtime, mtime) {
+                log::warn!("failed to set times for {:?}: {}", entry.path, err);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_owner(path: &Path, uid: u32, gid: u32) {
+    // Only root may give items away, other users may only change the group of their own items
+    let euid = uzers::get_effective_uid();
+    if euid != 0 && euid != uid {
+        return;
+    }
+    if let Err(err) = std::os::unix::fs::lchown(path, Some(uid), Some(gid)) {
+        log::warn!(
+            "failed to change owner of {:?} to {}:{}: {}",
+            path,
+            uid,
+            gid,
+            err
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn set_owner(_path: &Path, _uid: u32, _gid: u32) {
+    //TODO: what to do on non-Unix systems?
+}
+
+/// Destination of an entry of the archive if it is being extracted, where `entries` maps paths
+/// inside the archive to their destinations
+fn destination(entries: &[(PathBuf, PathBuf)], path: &Path) -> Option<PathBuf> {
+    entries.iter().find_map(|(from, to)| {
+        let relative = path.strip_prefix(from).ok()?;
+        if relative.as_os_str().is_empty() {
+            Some(to.clone())
+        } else {
+            Some(to.join(relative))
+        }
+    })
+}
+
+// Write the contents of an entry to a new file, counting bytes for `guard` as they are written.
+// Progress is counted by the caller.
+fn write_entry(
+    reader: &mut impl Read,
+    to: &Path,
+    guard: &mut Guard,
+    buffer: &mut [u8],
+) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Existing items are replaced rather than written through, in case they are links
+    if to
+        .symlink_metadata()
+        .is_ok_and(|metadata| !metadata.is_dir())
+    {
+        fs::remove_file(to)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    loop {
+        let count = reader.read(buffer)?;
+        if count == 0 {
+            break;
+        }
+        guard.add_bytes(count as u64)?;
+        file.write_all(&buffer[..count])?;
+    }
+    Ok(())
+}
+
+fn check(controller: &Controller) -> io::Result<()> {
+    futures::executor::block_on(controller.check())
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
+
+fn copy_contents(
+    reader: &mut impl Read,
+    to: &Path,
+    guard: &mut Guard,
+    controller: &Controller,
+    buffer: &mut [u8],
+) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::File::create(to)?;
+    loop {
+        check(controller)?;
+        let count = reader.read(buffer)?;
+        if count == 0 {
+            break;
+        }
+        guard.add_bytes(count as u64)?;
+        file.write_all(&buffer[..count])?;
+        controller.add_bytes(count as u64);
+    }
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    std::os::unix::fs::symlink(target, to)
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, to: &Path) -> io::Result<()> {
+    //TODO: support symbolic links on other platforms?
+    log::warn!("skipping symbolic link {:?}", to);
+    Ok(())
+}
+
+/// Extract some entries of an archive, including everything inside them, where `entries` maps
+/// paths inside the archive to their destinations inside the root of `guard`
+pub fn extract(
+    archive: &Path,
+    entries: &[(PathBuf, PathBuf)],
+    guard: &mut Guard,
+    controller: &Controller,
+) -> io::Result<()> {
+    let (files, bytes) = list(archive)?
+        .iter()
+        .filter(|entry| !entry.is_dir && destination(entries, &entry.path).is_some())
+        .fold((0, 0), |(files, bytes), entry| {
+            (files + 1, bytes + entry.size)
+        });
+    controller.set_totals(files, bytes);
+
+    let mut buffer = vec![0; 4 * 1024 * 1024];
+    if is_7z(archive) {
+        let mut sevenz =
+            open_7z(io::BufReader::new(volumes::open(archive)?), None).map_err(sevenz_error)?;
+        sevenz
+            .for_each_entries(|file, mut reader| {
+                check(controller)?;
+                let to = enclosed(Path::new(file.name()))
+                    .and_then(|path| destination(entries, &path))
+                    .filter(|to| !file.is_anti_item() && guard.check_path(to));
+                let Some(to) = to else {
+                    // Entries of solid archives follow each other in one stream
+                    io::copy(reader, &mut io::sink())?;
+                    return Ok(true);
+                };
+                controller.set_current_file(&to);
+                if file.is_directory() {
+                    fs::create_dir_all(&to)?;
+                    return Ok(true);
+                } else if sevenz_mode(file).is_some_and(|mode| mode & 0o170000 == 0o120000) {
+                    let mut target = String::new();
+                    reader.read_to_string(&mut target)?;
+                    if guard.check_link(&to, Path::new(&target)) {
+                        create_symlink(Path::new(&target), &to)?;
+                    }
+                } else {
+                    copy_contents(&mut reader, &to, guard, controller, &mut buffer)?;
+                }
+                controller.file_done();
+                Ok(true)
+            })
+            .map_err(sevenz_error)?;
+        return Ok(());
+    }
+    match open_tar(archive)? {
+        Some(mut tar) => {
+            for entry in tar.entries()? {
+                check(controller)?;
+                let mut entry = entry?;
+                let Some(to) =
+                    enclosed(&entry.path()?).and_then(|path| destination(entries, &path))
+                else {
+                    continue;
+                };
+                if !guard.check_path(&to) {
+                    continue;
+                }
+                controller.set_current_file(&to);
+                let entry_type = entry.header().entry_type();
+                if entry_type.is_dir() {
+                    fs::create_dir_all(&to)?;
+                    continue;
+                } else if entry_type.is_symlink() {
+                    if let Some(target) = entry.link_name()? {
+                        if guard.check_link(&to, &target) {
+                            create_symlink(&target, &to)?;
+                        }
+                    }
+                } else if entry_type.is_file() {
+                    copy_contents(&mut entry, &to, guard, controller, &mut buffer)?;
+                } else {
+                    log::warn!("skipping {:?} entry {:?}", entry_type, to);
+                }
+                controller.file_done();
+            }
+        }
+        None => {
+            let mut zip = open_zip(archive)?;
+            let encoding = NameEncoding::Auto.resolve(&mut zip)?;
+            for i in 0..zip.len() {
+                check(controller)?;
+                let mut file = zip.by_index(i)?;
+                let Some(to) = encoding
+                    .enclosed_name(&file)
+                    .and_then(|path| destination(entries, &path))
+                else {
+                    continue;
+                };
+                if !guard.check_path(&to) {
+                    continue;
+                }
+                controller.set_current_file(&to);
+                if file.is_dir() {
+                    fs::create_dir_all(&to)?;
+                    continue;
+                } else if file.is_symlink() {
+                    let mut target = String::new();
+                    file.read_to_string(&mut target)?;
+                    if guard.check_link(&to, Path::new(&target)) {
+                        create_symlink(Path::new(&target), &to)?;
+                    }
+                } else {
+                    copy_contents(&mut file, &to, guard, controller, &mut buffer)?;
+                }
+                controller.file_done();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Entry of an archive that was read through by [`test`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestedEntry {
+    /// Name of the entry as stored in the archive
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Size of the stored data, for archives that compress entries separately
+    pub compressed_size: Option<u64>,
+    pub method: String,
+    pub encrypted: bool,
+    /// Why the entry could not be decompressed or did not match its checksum
+    pub error: Option<String>,
+}
+
+/// Entries of a tested archive, and the error that stopped reading it early if there was one
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TestReport {
+    pub entries: Vec<TestedEntry>,
+    pub error: Option<String>,
+}
+
+impl TestReport {
+    /// Number of corrupt entries, counting an error that stopped reading the archive as one
+    pub fn problems(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.error.is_some())
+            .count()
+            + usize::from(self.error.is_some())
+    }
+}
+
+/// Read through every entry of an archive without writing anything, checking that entries
+/// decompress and match their checksums. Corrupt entries are reported instead of failing, while
+/// encrypted zip and 7z entries fail without the right `password`.
+pub fn test(
+    archive: &Path,
+    password: Option<&str>,
+    controller: &Controller,
+) -> ZipResult<TestReport> {
+    let mut report = TestReport::default();
+    let reader = io::BufReader::new(OpReader::archive(archive, controller.clone())?);
+    if is_7z(archive) {
+        test_7z(reader, password, controller, &mut report)?;
+        return Ok(report);
+    }
+    match tar_decoder(&volumes::archive_path(archive), reader)? {
+        Some((decoder, method)) => {
+            if let Err(err) = test_tar(decoder, method, controller, &mut report) {
+                check(controller)?;
+                report.error = Some(err.to_string());
+            }
+        }
+        None => test_zip(archive, password, controller, &mut report)?,
+    }
+    Ok(report)
+}
+
+fn test_tar(
+    decoder: Box<dyn Read>,
+    method: &str,
+    controller: &Controller,
+    report: &mut TestReport,
+) -> io::Result<()> {
+    let mut tar = tar::Archive::new(decoder);
+    for entry in tar.entries()? {
+        check(controller)?;
+        let mut entry = entry?;
+        let header = entry.header();
+        let mut tested = TestedEntry {
+            name: String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+            is_dir: header.entry_type().is_dir(),
+            size: header.size()?,
+            compressed_size: None,
+            method: method.to_string(),
+            encrypted: false,
+            error: None,
+        };
+        let result = io::copy(&mut entry, &mut io::sink());
+        if let Err(err) = result {
+            check(controller)?;
+            tested.error = Some(err.to_string());
+            report.entries.push(tested);
+            // Entries after a corrupt part of the stream cannot be found
+            return Ok(());
+        }
+        report.entries.push(tested);
+    }
+    // Compressed streams are checked at their end, which is after the end of the tar archive
+    io::copy(&mut tar.into_inner(), &mut io::sink())?;
+    Ok(())
+}
+
+fn test_zip(
+    archive: &Path,
+    password: Option<&str>,
+    controller: &Controller,
+    report: &mut TestReport,
+) -> ZipResult<()> {
+    let mut zip = open_zip(archive)?;
+    let encoding = NameEncoding::Auto.resolve(&mut zip)?;
+    for i in 0..zip.len() {
+        check(controller)?;
+        let mut tested = {
+            let file = zip.by_index_raw(i)?;
+            TestedEntry {
+                name: encoding.decode(&file).into_owned(),
+                is_dir: file.is_dir(),
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                method: format!("{:?}", file.compression()),
+                encrypted: file.encrypted(),
+                error: None,
+            }
+        };
+        // Reading to the end checks the checksum of the entry
+        let result = match password {
+            Some(password) => zip.by_index_decrypt(i, password.as_bytes()),
+            None => zip.by_index(i),
+        }
+        .and_then(|mut file| Ok(io::copy(&mut file, &mut io::sink())?));
+        match result {
+            Ok(_) => {}
+            Err(
+                err @ (ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)
+                | ZipError::InvalidPassword),
+            ) => return Err(err),
+            Err(err) => {
+                check(controller)?;
+                tested.error = Some(err.to_string());
+            }
+        }
+        controller.add_bytes(tested.compressed_size.unwrap_or(0));
+        report.entries.push(tested);
+    }
+    Ok(())
+}
+
+fn test_7z<R: Read + Seek>(
+    reader: R,
+    password: Option<&str>,
+    controller: &Controller,
+    report: &mut TestReport,
+) -> ZipResult<()> {
+    let mut sevenz = open_7z(reader, password).map_err(sevenz_error)?;
+
+    // Entries with contents are decoded folder by folder
+    let archive = sevenz.archive();
+    let mut details = Vec::with_capacity(archive.files.len());
+    for folder in archive.folders.iter() {
+        let methods: Vec<_> = folder
+            .coders
+            .iter()
+            .filter_map(|coder| SevenZMethod::by_id(coder.decompression_method_id()))
+            .collect();
+        let encrypted = methods.contains(&SevenZMethod::AES256SHA256);
+        let method = methods
+            .iter()
+            .filter(|method| **method != SevenZMethod::AES256SHA256)
+            .map(|method| method.name())
+            .collect::<Vec<_>>()
+            .join("+");
+        for _ in 0..folder.num_unpack_sub_streams {
+            details.push((method.clone(), encrypted));
+        }
+    }
+    let mut details = details.into_iter();
+
+    sevenz
+        .for_each_entries(|file, reader| {
+            // Errors without context are taken for wrong passwords
+            check(controller)
+                .map_err(|err| sevenz_rust::Error::io_msg(err, file.name().to_string()))?;
+            let (method, encrypted) = file
+                .has_stream()
+                .then(|| details.next())
+                .flatten()
+                .unwrap_or_else(|| (SevenZMethod::COPY.name().to_string(), false));
+            let mut tested = TestedEntry {
+                name: file.name().to_string(),
+                is_dir: file.is_directory(),
+                size: file.size(),
+                compressed_size: None,
+                method,
+                encrypted,
+                error: None,
+            };
+            // Reading to the end checks the checksum of the entry
+            if let Err(err) = io::copy(reader, &mut io::sink()) {
+                if encrypted && password.is_some() && !controller.is_cancelled() {
+                    return Err(sevenz_rust::Error::MaybeBadPassword(err));
+                }
+                check(controller)
+                    .map_err(|err| sevenz_rust::Error::io_msg(err, file.name().to_string()))?;
+                tested.error = Some(err.to_string());
+                report.entries.push(tested);
+                // Entries after a corrupt part of the stream cannot be found
+                return Ok(false);
+            }
+            report.entries.push(tested);
+            Ok(true)
+        })
+        .map_err(sevenz_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::PathBuf};
+
+    use super::{extract, list, read, read_dir, read_each, split_path, test, unpack_tar, Guard};
+    use crate::{app::test_utils::empty_fs, operation::Controller};
+
+    // Entries are written directly, as the tar builder refuses names that leave the archive
+    fn append_raw(
+        builder: &mut tar::Builder<fs::File>,
+        name: &str,
+        link_opt: Option<&str>,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        let gnu = header.as_gnu_mut().expect("Header is a GNU header");
+        gnu.name[..name.len()].copy_from_slice(name.as_bytes());
+        if let Some(link) = link_opt {
+            gnu.linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(tar::EntryType::Symlink);
+        }
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, data)
+    }
+
+    #[test]
+    fn browse_and_extract_zip() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.zip");
+        {
+            use std::io::Write;
+
+            let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path)?);
+            let options = zip::write::SimpleFileOptions::default();
+            zip.start_file("top.txt", options)?;
+            zip.write_all(b"top")?;
+            // The parent directories of this file have no entries of their own
+            zip.start_file("dir/nested/file.txt", options)?;
+            zip.write_all(b"nested")?;
+            zip.add_directory("empty", options)?;
+            zip.finish()?;
+        }
+
+        let entries = list(&archive_path)?;
+        assert_eq!(entries.len(), 3);
+
+        let root: Vec<_> = read_dir(&entries, &PathBuf::new())
+            .into_iter()
+            .map(|(entry, children)| (entry.path, entry.is_dir, children))
+            .collect();
+        assert_eq!(
+            root,
+            [
+                (PathBuf::from("dir"), true, 1),
+                (PathBuf::from("empty"), true, 0),
+                (PathBuf::from("top.txt"), false, 0),
+            ]
+        );
+        assert_eq!(
+            read(&archive_path, &PathBuf::from("dir/nested/file.txt"), 3)?,
+            b"nes"
+        );
+
+        // Items copied out of the archive use paths below it
+        assert_eq!(
+            split_path(&archive_path.join("dir/nested")),
+            Some((archive_path.clone(), PathBuf::from("dir/nested")))
+        );
+        assert_eq!(split_path(&archive_path), None);
+
+        let to = fs.path().join("nested");
+        let mut guard = Guard::new(fs.path(), 0, None);
+        extract(
+            &archive_path,
+            &[(PathBuf::from("dir/nested"), to.clone())],
+            &mut guard,
+            &Controller::default(),
+        )?;
+        assert!(guard.finish().is_empty());
+        assert_eq!(fs::read_to_string(to.join("file.txt"))?, "nested");
+        assert!(!fs.path().join("top.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_each_in_one_pass() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.tar");
+        {
+            let mut builder = tar::Builder::new(fs::File::create(&archive_path)?);
+            append_raw(&mut builder, "a.txt", None, b"aaa")?;
+            append_raw(&mut builder, "b.txt", None, b"bbbb")?;
+            append_raw(&mut builder, "c.txt", None, b"c")?;
+            builder.finish()?;
+        }
+
+        // Files are read in the order of the archive, up to their limits, and missing files
+        // are skipped
+        let mut files = Vec::new();
+        read_each(
+            &archive_path,
+            &[
+                (PathBuf::from("c.txt"), 10),
+                (PathBuf::from("a.txt"), 2),
+                (PathBuf::from("missing.txt"), 10),
+            ],
+            |path, data| {
+                files.push((path.to_path_buf(), data));
+                true
+            },
+        )?;
+        assert_eq!(
+            files,
+            vec![
+                (PathBuf::from("a.txt"), b"aa".to_vec()),
+                (PathBuf::from("c.txt"), b"c".to_vec()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn extract_rejects_escapes() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.tar");
+        {
+            let mut builder = tar::Builder::new(fs::File::create(&archive_path)?);
+            append_raw(&mut builder, "../escape.txt", None, b"escape")?;
+            append_raw(&mut builder, "/absolute.txt", None, b"absolute")?;
+            append_raw(&mut builder, "outside", Some("../.."), b"")?;
+            append_raw(&mut builder, "sub/file.txt", None, b"inside")?;
+            append_raw(&mut builder, "dirlink", Some("sub"), b"")?;
+            append_raw(&mut builder, "dirlink/file.txt", None, b"through")?;
+            // This link points inside until the next one makes it lead outside
+            append_raw(&mut builder, "a", Some("b/.."), b"")?;
+            append_raw(&mut builder, "b", Some("."), b"")?;
+            builder.finish()?;
+        }
+        let open = || -> io::Result<tar::Archive<fs::File>> {
+            Ok(tar::Archive::new(fs::File::open(&archive_path)?))
+        };
+        let archive_size = fs::metadata(&archive_path)?.len();
+
+        let root = fs.path().join("out");
+        let mut guard = Guard::new(&root, archive_size, None);
+        unpack_tar(open()?, &root, &mut guard, &Controller::default(), false)?;
+        let warnings = guard.finish();
+        assert_eq!(warnings.len(), 5, "{:?}", warnings);
+        assert!(!fs.path().join("escape.txt").exists());
+        assert!(!root.join("outside").is_symlink());
+        assert!(!root.join("a").is_symlink());
+        assert!(root.join("b").is_symlink());
+        assert_eq!(fs::read_to_string(root.join("sub/file.txt"))?, "inside");
+
+        // Archives expanding beyond the size limit are stopped
+        let limited = fs.path().join("limited");
+        let mut guard = Guard::new(&limited, archive_size, Some(4));
+        assert!(unpack_tar(open()?, &limited, &mut guard, &Controller::default(), false).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_drops_special_mode_bits() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.tar");
+        {
+            let mut builder = tar::Builder::new(fs::File::create(&archive_path)?);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_mode(0o4755);
+            builder.append_data(&mut header, "setuid", io::empty())?;
+            builder.finish()?;
+        }
+
+        // Set-user-ID bits are only kept when restoring owners as root
+        let root = fs.path().join("out");
+        let mut guard = Guard::new(&root, 0, None);
+        let archive = tar::Archive::new(fs::File::open(&archive_path)?);
+        unpack_tar(archive, &root, &mut guard, &Controller::default(), false)?;
+        assert_eq!(
+            fs::metadata(root.join("setuid"))?.permissions().mode() & 0o7777,
+            0o755
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_entries_count_expanded_size() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.tar");
+        {
+            // Four bytes of data at the end of a file of 1024 bytes
+            let octal = |value: u64| format!("{value:011o}\0").into_bytes();
+            let mut header = tar::Header::new_gnu();
+            let gnu = header.as_gnu_mut().expect("Header is a GNU header");
+            gnu.name[..10].copy_from_slice(b"sparse.bin");
+            gnu.sparse[0].offset.copy_from_slice(&octal(1020));
+            gnu.sparse[0].numbytes.copy_from_slice(&octal(4));
+            gnu.realsize.copy_from_slice(&octal(1024));
+            header.set_entry_type(tar::EntryType::GNUSparse);
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            let mut builder = tar::Builder::new(fs::File::create(&archive_path)?);
+            builder.append(&header, &b"data"[..])?;
+            builder.finish()?;
+        }
+        let open = || -> io::Result<tar::Archive<fs::File>> {
+            Ok(tar::Archive::new(fs::File::open(&archive_path)?))
+        };
+        let archive_size = fs::metadata(&archive_path)?.len();
+
+        let root = fs.path().join("out");
+        let mut guard = Guard::new(&root, archive_size, None);
+        unpack_tar(open()?, &root, &mut guard, &Controller::default(), false)?;
+        let data = fs::read(root.join("sparse.bin"))?;
+        assert_eq!(data.len(), 1024);
+        assert_eq!(&data[1020..], b"data");
+
+        // The holes count towards the size limit
+        let limited = fs.path().join("limited");
+        let mut guard = Guard::new(&limited, archive_size, Some(100));
+        assert!(unpack_tar(open()?, &limited, &mut guard, &Controller::default(), false).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finds_corrupt_entries() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.zip");
+        {
+            use std::io::Write;
+
+            let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path)?);
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+            zip.start_file("good.txt", options)?;
+            zip.write_all(b"good contents")?;
+            zip.start_file("bad.txt", options)?;
+            zip.write_all(b"bad contents")?;
+            zip.finish()?;
+        }
+
+        let report = test(&archive_path, None, &Controller::default())?;
+        assert_eq!(report.problems(), 0);
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[1].name, "bad.txt");
+        assert_eq!(report.entries[1].method, "Stored");
+        assert_eq!(report.entries[1].compressed_size, Some(12));
+
+        // Stored contents can be changed without breaking the structure of the archive
+        let mut data = fs::read(&archive_path)?;
+        let pos = data
+            .windows(12)
+            .position(|window| window == b"bad contents")
+            .expect("Contents should be stored as is");
+        data[pos] ^= 0xff;
+        fs::write(&archive_path, data)?;
+
+        let report = test(&archive_path, None, &Controller::default())?;
+        assert_eq!(report.problems(), 1);
+        assert!(report.entries[0].error.is_none());
+        assert!(report.entries[1].error.is_some());
+
+        Ok(())
+    }
+}
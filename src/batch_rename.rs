@@ -0,0 +1,409 @@
+use chrono::{DateTime, Local};
+use regex::{NoExpand, Regex};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::fl;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CaseChange {
+    #[default]
+    Keep,
+    Lower,
+    Upper,
+    Title,
+}
+
+impl CaseChange {
+    pub const ALL: [Self; 4] = [Self::Keep, Self::Lower, Self::Upper, Self::Title];
+
+    fn apply(self, name: &str) -> String {
+        match self {
+            Self::Keep => name.to_string(),
+            Self::Lower => name.to_lowercase(),
+            Self::Upper => name.to_uppercase(),
+            Self::Title => {
+                let mut title = String::with_capacity(name.len());
+                let mut word_start = true;
+                for c in name.chars() {
+                    if word_start {
+                        title.extend(c.to_uppercase());
+                    } else {
+                        title.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric();
+                }
+                title
+            }
+        }
+    }
+}
+
+/// Rules for renaming many items at once.
+///
+/// New names are built from `template`, where these tokens are replaced:
+/// - `{name}`: the name without its extension, after find and replace and the case change
+/// - `{ext}`: the extension, including the dot
+/// - `{n}`: a sequential number, starting at `start` and padded with zeros to `padding` digits
+/// - `{date}`: the modified date
+/// - `{folder}`: the name of the parent folder
+/// - `{exif}`: the date an image was taken, or the modified date if it has none
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchRename {
+    pub find: String,
+    pub replace: String,
+    pub regex: bool,
+    pub case: CaseChange,
+    pub template: String,
+    pub start: String,
+    pub padding: String,
+}
+
+impl Default for BatchRename {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            regex: false,
+            case: CaseChange::Keep,
+            template: "{name}{ext}".to_string(),
+            start: "1".to_string(),
+            padding: "1".to_string(),
+        }
+    }
+}
+
+/// The new name of one item, and why it cannot be used if there is a problem
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RenamePreview {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub problem: Option<String>,
+}
+
+impl BatchRename {
+    /// Build the new names of `paths`, in order, checking them for collisions
+    pub fn preview(&self, paths: &[PathBuf]) -> Result<Vec<RenamePreview>, String> {
+        let regex_opt = if self.find.is_empty() {
+            None
+        } else if self.regex {
+            Some(Regex::new(&self.find).map_err(|err| err.to_string())?)
+        } else {
+            Some(Regex::new(&regex::escape(&self.find)).map_err(|err| err.to_string())?)
+        };
+        let start = self
+            .start
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| fl!("batch-rename-invalid-number", value = self.start.as_str()))?;
+        let padding = self
+            .padding
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| fl!("batch-rename-invalid-number", value = self.padding.as_str()))?;
+
+        let mut previews = Vec::with_capacity(paths.len());
+        for (i, from) in paths.iter().enumerate() {
+            let name = self.new_name(from, regex_opt.as_ref(), start + i, padding);
+            let to = match from.parent() {
+                Some(parent) => parent.join(&name),
+                None => from.clone(),
+            };
+            let problem = if name.is_empty() || name == "." || name == ".." {
+                Some(fl!("name-invalid", filename = name.as_str()))
+            } else if name.contains('/') {
+                Some(fl!("name-no-slashes"))
+            } else {
+                None
+            };
+            previews.push(RenamePreview {
+                from: from.clone(),
+                to,
+                problem,
+            });
+        }
+
+        // New names must be unique, and may only replace items that are being renamed too
+        let sources: BTreeSet<&Path> = paths.iter().map(|path| path.as_path()).collect();
+        let mut targets = BTreeMap::<PathBuf, usize>::new();
+        for preview in previews.iter() {
+            *targets.entry(preview.to.clone()).or_default() += 1;
+        }
+        for preview in previews.iter_mut() {
+            if preview.problem.is_some() || preview.to == preview.from {
+                continue;
+            }
+            if targets.get(&preview.to).is_some_and(|count| *count > 1) {
+                preview.problem = Some(fl!("batch-rename-duplicate"));
+            } else if !sources.contains(preview.to.as_path())
+                && name_taken(&preview.from, &preview.to)
+            {
+                preview.problem = Some(if preview.to.is_dir() {
+                    fl!("folder-already-exists")
+                } else {
+                    fl!("file-already-exists")
+                });
+            }
+        }
+
+        Ok(previews)
+    }
+
+    fn new_name(
+        &self,
+        path: &Path,
+        regex_opt: Option<&Regex>,
+        number: usize,
+        padding: usize,
+    ) -> String {
+        let (stem, ext) = if path.is_dir() {
+            (file_name(path), String::new())
+        } else {
+            (
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                path.extension()
+                    .map(|ext| format!(".{}", ext.to_string_lossy()))
+                    .unwrap_or_default(),
+            )
+        };
+
+        let stem = match regex_opt {
+            Some(regex) if self.regex => regex.replace_all(&stem, self.replace.as_str()),
+            Some(regex) => regex.replace_all(&stem, NoExpand(&self.replace)),
+            None => stem.as_str().into(),
+        };
+        let stem = self.case.apply(&stem);
+
+        // Tokens are expanded in one pass, so values that contain tokens are kept as they are,
+        // and tokens that need metadata are only looked up when used
+        let mut name = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            name.push_str(&rest[..start]);
+            let end_opt = rest[start..].find('}').map(|end| start + end + 1);
+            let value_opt = match end_opt.map(|end| &rest[start..end]) {
+                Some("{name}") => Some(stem.clone()),
+                Some("{ext}") => Some(ext.clone()),
+                Some("{n}") => Some(format!("{:0padding$}", number, padding = padding)),
+                Some("{date}") => Some(modified_date(path).unwrap_or_default()),
+                Some("{folder}") => Some(path.parent().map(file_name).unwrap_or_default()),
+                Some("{exif}") => Some(
+                    exif_date(path)
+                        .or_else(|| modified_date(path))
+                        .unwrap_or_default(),
+                ),
+                _ => None,
+            };
+            match (value_opt, end_opt) {
+                (Some(value), Some(end)) => {
+                    name.push_str(&value);
+                    rest = &rest[end..];
+                }
+                _ => {
+                    // Other braces are kept
+                    name.push('{');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        name.push_str(rest);
+        name
+    }
+}
+
+/// Check if the new name of an item is taken by another item. On case-insensitive filesystems,
+/// a name that only changes case refers to the item itself, so it is free.
+pub fn name_taken(from: &Path, to: &Path) -> bool {
+    let Ok(to_metadata) = to.symlink_metadata() else {
+        return false;
+    };
+    let case_only = from.parent() == to.parent()
+        && file_name(from).to_lowercase() == file_name(to).to_lowercase();
+    if !case_only {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        !from.symlink_metadata().is_ok_and(|from_metadata| {
+            from_metadata.dev() == to_metadata.dev() && from_metadata.ino() == to_metadata.ino()
+        })
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = to_metadata;
+        fs::canonicalize(from).ok() != fs::canonicalize(to).ok()
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn modified_date(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    Some(
+        DateTime::<Local>::from(modified)
+            .format("%Y-%m-%d")
+            .to_string(),
+    )
+}
+
+fn exif_date(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut io::BufReader::new(file))
+        .ok()?;
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+    match &field.value {
+        exif::Value::Ascii(values) => {
+            let date = exif::DateTime::from_ascii(values.first()?).ok()?;
+            Some(format!(
+                "{:04}-{:02}-{:02}",
+                date.year, date.month, date.day
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::PathBuf};
+
+    use super::{name_taken, BatchRename, CaseChange};
+    use crate::app::test_utils::empty_fs;
+
+    #[test]
+    fn preview_names() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let paths: Vec<PathBuf> = ["IMG_001.JPG", "IMG_002.JPG", "notes.txt"]
+            .iter()
+            .map(|name| fs.path().join(name))
+            .collect();
+        for path in paths.iter() {
+            fs::File::create(path)?;
+        }
+
+        let rename = BatchRename {
+            find: "IMG_(\\d+)".to_string(),
+            replace: "Photo $1".to_string(),
+            regex: true,
+            case: CaseChange::Lower,
+            template: "{n} {name}{ext}".to_string(),
+            start: "9".to_string(),
+            padding: "2".to_string(),
+        };
+        let names: Vec<_> = rename
+            .preview(&paths)
+            .expect("Rules should be valid")
+            .into_iter()
+            .map(|preview| {
+                assert_eq!(preview.problem, None);
+                preview
+                    .to
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            ["09 photo 001.JPG", "10 photo 002.JPG", "11 notes.txt"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn preview_literal_tokens() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let dir = fs.path().join("{ext}");
+        fs::create_dir(&dir)?;
+        let path = dir.join("a{n}{date}.txt");
+        fs::File::create(&path)?;
+
+        // Tokens inside names and other braces are not expanded
+        let rename = BatchRename {
+            template: "{folder} {name}-{n}{ext} {x}".to_string(),
+            ..Default::default()
+        };
+        let previews = rename.preview(&[path]).expect("Rules should be valid");
+        assert_eq!(
+            previews[0].to.file_name().unwrap().to_string_lossy(),
+            "{ext} a{n}{date}-1.txt {x}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn preview_collisions() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt"]
+            .iter()
+            .map(|name| fs.path().join(name))
+            .collect();
+        for path in paths.iter() {
+            fs::File::create(path)?;
+        }
+        fs::File::create(fs.path().join("c.txt"))?;
+
+        // Both items would get the same name
+        let rename = BatchRename {
+            template: "same{ext}".to_string(),
+            ..Default::default()
+        };
+        let previews = rename.preview(&paths).expect("Rules should be valid");
+        assert!(previews.iter().all(|preview| preview.problem.is_some()));
+
+        // Items that are not renamed cannot be replaced
+        let rename = BatchRename {
+            find: "a".to_string(),
+            replace: "c".to_string(),
+            ..Default::default()
+        };
+        let previews = rename.preview(&paths).expect("Rules should be valid");
+        assert!(previews[0].problem.is_some());
+        assert!(previews[1].problem.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn case_only_rename() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path().join("a.txt");
+        fs::File::create(&path)?;
+
+        // Changing only the case is allowed, whether or not the filesystem ignores case
+        let upper = fs.path().join("A.txt");
+        assert!(!name_taken(&path, &upper));
+        assert!(!name_taken(&path, &path));
+
+        // Another item with a name that differs only by case is still taken
+        let other = fs.path().join("B.txt");
+        fs::File::create(fs.path().join("b.txt"))?;
+        if !other.exists() {
+            fs::File::create(&other)?;
+            assert!(name_taken(&fs.path().join("b.txt"), &other));
+        }
+        assert!(name_taken(&path, &fs.path().join("b.txt")));
+
+        Ok(())
+    }
+}
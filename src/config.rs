@@ -167,6 +167,7 @@ pub struct Config {
     pub dialog: DialogConfig,
     pub desktop: DesktopConfig,
     pub favorites: Vec<Favorite>,
+    pub operations: OperationsConfig,
     pub show_details: bool,
     pub tab: TabConfig,
     pub type_to_search: TypeToSearch,
@@ -216,6 +217,7 @@ impl Default for Config {
                 Favorite::Pictures,
                 Favorite::Videos,
             ],
+            operations: OperationsConfig::default(),
             show_details: false,
             tab: TabConfig::default(),
             type_to_search: TypeToSearch::Recursive,
@@ -263,6 +265,55 @@ impl Default for DialogConfig {
     }
 }
 
+/// Data written over file contents when shredding.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ShredPattern {
+    Random,
+    Zero,
+}
+
+/// Options applied to file operations when they are performed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, CosmicConfigEntry, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OperationsConfig {
+    /// Skip paths that fail and report them at the end instead of stopping
+    pub continue_on_error: bool,
+    /// Recreate FIFOs, sockets and device nodes instead of skipping them
+    pub copy_special_files: bool,
+    /// Give extracted items the owner and group stored in the archive, where the user may
+    pub extract_ownership: bool,
+    /// Stop extracting archives that expand to more than this many bytes
+    pub extract_size_limit: Option<u64>,
+    /// Copy timestamps, permissions, ownership and extended attributes along with contents
+    pub preserve_metadata: bool,
+    /// Preview copies and moves of at least this many bytes before they start
+    pub preview_size: Option<u64>,
+    /// Point new symbolic links at their targets relative to the link
+    pub relative_links: bool,
+    /// Number of times file contents are overwritten when shredding
+    pub shred_passes: u8,
+    pub shred_pattern: ShredPattern,
+    /// Compare checksums of copied files against their sources
+    pub verify_copies: bool,
+}
+
+impl Default for OperationsConfig {
+    fn default() -> Self {
+        Self {
+            continue_on_error: false,
+            copy_special_files: true,
+            extract_ownership: false,
+            extract_size_limit: Some(100_000_000_000),
+            preserve_metadata: true,
+            preview_size: Some(1_000_000_000),
+            relative_links: false,
+            shred_passes: 3,
+            shred_pattern: ShredPattern::Random,
+            verify_copies: false,
+        }
+    }
+}
+
 /// Global and local [`crate::tab::Tab`] config.
 ///
 /// [`TabConfig`] contains options that are passed to each instance of [`crate::tab::Tab`].
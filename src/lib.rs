@@ -6,6 +6,8 @@ use std::{env, fs, path::PathBuf, process};
 
 use app::{App, Flags};
 pub mod app;
+mod archive;
+mod batch_rename;
 pub mod clipboard;
 use config::Config;
 pub mod config;
@@ -25,6 +27,7 @@ use crate::config::State;
 pub mod tab;
 mod thumbnail_cacher;
 mod thumbnailer;
+mod volumes;
 
 pub(crate) fn err_str<T: ToString>(err: T) -> String {
     err.to_string()
@@ -22,6 +22,8 @@ use crate::{
     app::{Action, Message},
     config::Config,
     fl,
+    mime_icon::mime_for_path,
+    operation::Journal,
     tab::{self, HeadingOptions, Location, LocationMenuAction, Tab},
 };
 
@@ -108,6 +110,7 @@ pub fn context_menu<'a>(
     let mut selected_trash_only = false;
     let mut selected_desktop_entry = None;
     let mut selected_types: Vec<Mime> = vec![];
+    let mut selected_volume = false;
     let mut selected_mount_point = 0;
     if let Some(items) = tab.items_opt() {
         for item in items.iter() {
@@ -117,6 +120,7 @@ pub fn context_menu<'a>(
                     selected_mount_point += item.is_mount_point as i32;
                     selected_dir += 1;
                 }
+                let mut mime = item.mime.clone();
                 match &item.location_opt {
                     Some(Location::Trash) => selected_trash_only = true,
                     Some(Location::Path(path)) => {
@@ -125,10 +129,16 @@ pub fn context_menu<'a>(
                         {
                             selected_desktop_entry = Some(&**path);
                         }
+                        // Volumes of split archives are extracted as the whole archive
+                        let archive_path = crate::volumes::archive_path(path);
+                        if archive_path != **path {
+                            selected_volume = true;
+                            mime = mime_for_path(&archive_path, None, true);
+                        }
                     }
                     _ => (),
                 }
-                selected_types.push(item.mime.clone());
+                selected_types.push(mime);
             }
         }
     };
@@ -205,35 +215,31 @@ pub fn context_menu<'a>(
                     children.push(menu_item(fl!("cut"), Action::Cut).into());
                 }
                 children.push(menu_item(fl!("copy"), Action::Copy).into());
+                children.push(menu_item(fl!("duplicate"), Action::Duplicate).into());
+                children.push(menu_item(fl!("make-link"), Action::MakeLink).into());
 
                 children.push(divider::horizontal::light().into());
-                let supported_archive_types = [
-                    "application/gzip",
-                    "application/x-compressed-tar",
-                    "application/x-tar",
-                    "application/zip",
-                    #[cfg(feature = "bzip2")]
-                    "application/x-bzip",
-                    #[cfg(feature = "bzip2")]
-                    "application/x-bzip-compressed-tar",
-                    #[cfg(feature = "bzip2")]
-                    "application/x-bzip2",
-                    #[cfg(feature = "bzip2")]
-                    "application/x-bzip2-compressed-tar",
-                    #[cfg(feature = "xz2")]
-                    "application/x-xz",
-                    #[cfg(feature = "xz2")]
-                    "application/x-xz-compressed-tar",
-                ]
-                .iter()
-                .filter_map(|mime_type| mime_type.parse::<Mime>().ok())
-                .collect::<Vec<_>>();
+                let supported_archive_types = crate::archive::MIME_TYPES
+                    .iter()
+                    .filter_map(|mime_type| mime_type.parse::<Mime>().ok())
+                    .collect::<Vec<_>>();
                 selected_types.retain(|t| !supported_archive_types.contains(t));
                 if selected_types.is_empty() {
+                    if selected == 1 && !selected_volume && matches!(tab.mode, tab::Mode::App) {
+                        children
+                            .push(menu_item(fl!("browse-archive"), Action::BrowseArchive).into());
+                    }
                     children.push(menu_item(fl!("extract-here"), Action::ExtractHere).into());
                     children.push(menu_item(fl!("extract-to"), Action::ExtractTo).into());
+                    children.push(menu_item(fl!("test-archive"), Action::TestArchive).into());
                 }
                 children.push(menu_item(fl!("compress"), Action::Compress).into());
+                #[cfg(unix)]
+                if selected_mount_point == 0 {
+                    children.push(
+                        menu_item(fl!("change-permissions"), Action::ChangePermissions).into(),
+                    );
+                }
                 children.push(divider::horizontal::light().into());
 
                 //TODO: Print?
@@ -257,6 +263,7 @@ pub fn context_menu<'a>(
                     } else {
                         children.push(menu_item(fl!("move-to-trash"), Action::Delete).into());
                     }
+                    children.push(menu_item(fl!("shred-menu"), Action::Shred).into());
                 } else if selected == 1 {
                     children.push(menu_item(fl!("eject"), Action::Eject).into());
                 }
@@ -271,6 +278,11 @@ pub fn context_menu<'a>(
                     children.push(menu_item(fl!("select-all"), Action::SelectAll).into());
                 }
                 children.push(menu_item(fl!("paste"), Action::Paste).into());
+                children.push(menu_item(fl!("paste-symlink"), Action::PasteSymlink).into());
+                // Folders cannot have hard links
+                if !tab.clipboard_dirs {
+                    children.push(menu_item(fl!("paste-hard-link"), Action::PasteHardLink).into());
+                }
 
                 //TODO: only show if cosmic-settings is found?
                 if matches!(tab.mode, tab::Mode::Desktop) {
@@ -334,6 +346,28 @@ pub fn context_menu<'a>(
                 children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
             }
         }
+        (_, Location::Archive(..)) => {
+            // Archives are read-only, items can only be opened or copied out
+            if selected > 0 {
+                if selected_dir == 1 && selected == 1 {
+                    children.push(menu_item(fl!("open"), Action::Open).into());
+                    children.push(divider::horizontal::light().into());
+                }
+                children.push(menu_item(fl!("copy"), Action::Copy).into());
+                children.push(divider::horizontal::light().into());
+                children.push(menu_item(fl!("show-details"), Action::Preview).into());
+            } else {
+                if tab.mode.multiple() {
+                    children.push(menu_item(fl!("select-all"), Action::SelectAll).into());
+                }
+                if !children.is_empty() {
+                    children.push(divider::horizontal::light().into());
+                }
+                children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
+                children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
+                children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+            }
+        }
         (_, Location::Network(..)) => {
             if selected > 0 {
                 if selected_dir == 1 && selected == 1 || selected_dir == 0 {
@@ -545,6 +579,7 @@ pub fn menu_bar<'a>(
     core: &Core,
     tab_opt: Option<&Tab>,
     config: &Config,
+    journal: &Journal,
     modifiers: &Modifiers,
     key_binds: &HashMap<KeyBind, Action>,
 ) -> Element<'a, Message> {
@@ -614,6 +649,12 @@ pub fn menu_bar<'a>(
                         ),
                         menu::Item::Divider,
                         menu_button_optional(fl!("rename"), Action::Rename, selected > 0),
+                        #[cfg(unix)]
+                        menu_button_optional(
+                            fl!("change-permissions"),
+                            Action::ChangePermissions,
+                            selected > 0,
+                        ),
                         menu::Item::Divider,
                         menu::Item::Button(fl!("reload-folder"), None, Action::Reload),
                         menu::Item::Divider,
@@ -637,9 +678,20 @@ pub fn menu_bar<'a>(
                 (
                     (fl!("edit")),
                     vec![
+                        menu_button_optional(fl!("undo"), Action::Undo, journal.can_undo()),
+                        menu_button_optional(fl!("redo"), Action::Redo, journal.can_redo()),
+                        menu::Item::Divider,
                         menu_button_optional(fl!("cut"), Action::Cut, selected > 0),
                         menu_button_optional(fl!("copy"), Action::Copy, selected > 0),
                         menu_button_optional(fl!("paste"), Action::Paste, selected > 0),
+                        menu::Item::Button(fl!("paste-symlink"), None, Action::PasteSymlink),
+                        menu_button_optional(
+                            fl!("paste-hard-link"),
+                            Action::PasteHardLink,
+                            !tab_opt.is_some_and(|tab| tab.clipboard_dirs),
+                        ),
+                        menu_button_optional(fl!("duplicate"), Action::Duplicate, selected > 0),
+                        menu_button_optional(fl!("make-link"), Action::MakeLink, selected > 0),
                         menu::Item::Button(fl!("select-all"), None, Action::SelectAll),
                         menu::Item::Divider,
                         menu::Item::Button(fl!("history"), None, Action::EditHistory),
@@ -0,0 +1,654 @@
+use cosmic::iced::futures::channel::mpsc::Sender;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::Mutex as TokioMutex;
+use walkdir::WalkDir;
+use zip::AesMode::Aes256;
+
+use super::{
+    copy_unique_path, handle_replace_items, reader::OpReader, unique_path_with, Controller,
+    OperationError, ReplaceResult,
+};
+use crate::{
+    app::{ArchiveType, CompressionLevel, Message},
+    archive,
+    config::IconSizes,
+    fl, tab,
+};
+
+/// Item to add to an archive and the name it is given inside the archive
+struct NewEntry {
+    path: PathBuf,
+    name: PathBuf,
+}
+
+/// Add items to an existing archive by rewriting it in the same format. Existing entries are
+/// kept as they are, unless they are replaced by an added item after asking with the replace
+/// dialog. Added zip entries are encrypted with `password` if it is set.
+pub fn add_to_archive(
+    archive: &Path,
+    paths: &[PathBuf],
+    password: Option<&str>,
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let archive_type = ArchiveType::from_path(archive).ok_or_else(|| {
+        OperationError::from_str(format!("{:?} is not a supported archive", archive))
+    })?;
+    let Some((added, replaced)) =
+        plan(archive, paths, msg_tx, controller).map_err(OperationError::from_str)?
+    else {
+        return Ok(());
+    };
+
+    controller.set_totals(
+        added.len(),
+        fs::metadata(archive).map_or(0, |metadata| metadata.len())
+            + added
+                .iter()
+                .filter_map(|entry| fs::metadata(&entry.path).ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum::<u64>(),
+    );
+
+    // The archive is written next to itself and only replaced once it is complete
+    let (Some(parent), Some(file_name)) = (archive.parent(), archive.file_name()) else {
+        return Err(OperationError::from_str(format!(
+            "path {:?} has no parent directory",
+            archive
+        )));
+    };
+    let temp = copy_unique_path(
+        &parent.join(format!(".{}", file_name.to_string_lossy())),
+        parent,
+    );
+    let level = archive_type
+        .compression_level(CompressionLevel::default())
+        .unwrap_or_default();
+    let is_replaced = |name: &Path| replaced.iter().any(|replaced| name.starts_with(replaced));
+    let result = fs::File::create(&temp)
+        .map(io::BufWriter::new)
+        .and_then(|w| match archive_type {
+            ArchiveType::SevenZ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "items cannot be added to 7z archives",
+            )),
+            ArchiveType::Tar => rewrite_tar(w, archive, &is_replaced, &added, controller)?.flush(),
+            #[cfg(feature = "bzip2")]
+            ArchiveType::TarBz2 => rewrite_tar(
+                bzip2::write::BzEncoder::new(w, bzip2::Compression::new(level)),
+                archive,
+                &is_replaced,
+                &added,
+                controller,
+            )?
+            .finish()
+            .and_then(|mut w| w.flush()),
+            #[cfg(feature = "xz2")]
+            ArchiveType::TarXz => rewrite_tar(
+                xz2::write::XzEncoder::new(w, level),
+                archive,
+                &is_replaced,
+                &added,
+                controller,
+            )?
+            .finish()
+            .and_then(|mut w| w.flush()),
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst => rewrite_tar(
+                zstd::Encoder::new(w, level as i32)?,
+                archive,
+                &is_replaced,
+                &added,
+                controller,
+            )?
+            .finish()
+            .and_then(|mut w| w.flush()),
+            ArchiveType::Tgz => rewrite_tar(
+                flate2::write::GzEncoder::new(w, flate2::Compression::new(level)),
+                archive,
+                &is_replaced,
+                &added,
+                controller,
+            )?
+            .finish()
+            .and_then(|mut w| w.flush()),
+            ArchiveType::Zip => {
+                rewrite_zip(w, archive, &is_replaced, &added, password, controller)?.flush()
+            }
+        })
+        .and_then(|()| {
+            // The new archive keeps the permissions of the one it replaces
+            let permissions = fs::metadata(archive)?.permissions();
+            fs::set_permissions(&temp, permissions)?;
+            fs::rename(&temp, archive)
+        });
+    if let Err(err) = result {
+        if let Err(err) = fs::remove_file(&temp) {
+            log::warn!("failed to remove {:?}: {}", temp, err);
+        }
+        return Err(OperationError::from_str(format!(
+            "failed to add to {:?}: {}",
+            archive, err
+        )));
+    }
+    Ok(())
+}
+
+fn check(controller: &Controller) -> io::Result<()> {
+    futures::executor::block_on(controller.check())
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
+
+// Names of the added items and of the existing entries they replace, or `None` if cancelled.
+// Folders that already exist are merged, conflicting files are resolved with the replace dialog.
+fn plan(
+    archive: &Path,
+    paths: &[PathBuf],
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+    controller: &Controller,
+) -> io::Result<Option<(Vec<NewEntry>, Vec<PathBuf>)>> {
+    let entries = archive::list(archive)?;
+    // Directories may only be implied by the entries inside them
+    let mut dirs = BTreeSet::new();
+    let mut files = BTreeSet::new();
+    for entry in entries.iter() {
+        dirs.extend(
+            entry
+                .path
+                .ancestors()
+                .skip(1)
+                .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                .map(Path::to_path_buf),
+        );
+        if entry.is_dir {
+            dirs.insert(entry.path.clone());
+        } else {
+            files.insert(entry.path.clone());
+        }
+    }
+    let mut taken: BTreeSet<PathBuf> = dirs.union(&files).cloned().collect();
+
+    let mut added = Vec::new();
+    let mut replaced = Vec::new();
+    let mut replace_result_opt = None;
+    for path in paths.iter() {
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        // Folders that are added under a new name, mapped from their original names
+        let mut renamed = BTreeMap::<PathBuf, PathBuf>::new();
+        let mut walker = WalkDir::new(path).into_iter();
+        while let Some(entry) = walker.next() {
+            check(controller)?;
+            let entry = entry?;
+            let mut name = PathBuf::from(file_name);
+            if let Ok(relative) = entry.path().strip_prefix(path) {
+                if !relative.as_os_str().is_empty() {
+                    name.push(relative);
+                }
+            }
+            let renamed_opt = name.ancestors().skip(1).find_map(|ancestor| {
+                let relative = name.strip_prefix(ancestor).ok()?;
+                Some(renamed.get(ancestor)?.join(relative))
+            });
+            if let Some(renamed_name) = renamed_opt {
+                name = renamed_name;
+            }
+
+            let is_dir = entry.file_type().is_dir();
+            if files.contains(&name) || (!is_dir && dirs.contains(&name)) {
+                let replace_result = match replace_result_opt {
+                    Some(result) => result,
+                    None => ask_replace(
+                        archive,
+                        &entries,
+                        entry.path(),
+                        &name,
+                        paths.len() > 1 || path.is_dir(),
+                        msg_tx,
+                    )?,
+                };
+                match replace_result {
+                    ReplaceResult::Replace(apply_to_all) => {
+                        if apply_to_all {
+                            replace_result_opt = Some(replace_result);
+                        }
+                        replaced.push(name.clone());
+                    }
+                    ReplaceResult::KeepBoth => {
+                        let unique = unique_path_with(
+                            entry.path(),
+                            name.parent().unwrap_or(Path::new("")),
+                            &fl!("copy_noun"),
+                            |candidate| taken.contains(candidate),
+                        );
+                        if is_dir {
+                            renamed.insert(name, unique.clone());
+                        }
+                        name = unique;
+                    }
+                    ReplaceResult::Skip(apply_to_all) => {
+                        if apply_to_all {
+                            replace_result_opt = Some(replace_result);
+                        }
+                        if is_dir {
+                            walker.skip_current_dir();
+                        }
+                        continue;
+                    }
+                    ReplaceResult::Cancel => return Ok(None),
+                }
+            } else if is_dir && dirs.contains(&name) {
+                // Existing folders are merged with the added ones
+                continue;
+            }
+            taken.insert(name.clone());
+            added.push(NewEntry {
+                path: entry.into_path(),
+                name,
+            });
+        }
+    }
+    Ok(Some((added, replaced)))
+}
+
+fn ask_replace(
+    archive: &Path,
+    entries: &[archive::ArchiveEntry],
+    from: &Path,
+    name: &Path,
+    multiple: bool,
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+) -> io::Result<ReplaceResult> {
+    let item_from = tab::item_from_path(from, IconSizes::default())
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    let Some((entry, children)) =
+        archive::read_dir(entries, name.parent().unwrap_or(Path::new("")))
+            .into_iter()
+            .find(|(entry, _)| entry.path == name)
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{:?} not found in {:?}", name, archive),
+        ));
+    };
+    let item_to = tab::item_from_archive_entry(archive, &entry, children, IconSizes::default());
+    Ok(futures::executor::block_on(handle_replace_items(
+        msg_tx.clone(),
+        item_from,
+        item_to,
+        multiple,
+    )))
+}
+
+fn rewrite_tar<W: Write>(
+    writer: W,
+    archive: &Path,
+    is_replaced: &dyn Fn(&Path) -> bool,
+    added: &[NewEntry],
+    controller: &Controller,
+) -> io::Result<W> {
+    let reader = io::BufReader::new(OpReader::new(archive, controller.clone())?);
+    let Some((decoder, _)) = archive::tar_decoder(archive, reader)? else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} is not a tar archive", archive),
+        ));
+    };
+    let mut source = tar::Archive::new(decoder);
+    let mut builder = tar::Builder::new(writer);
+    // Entries are read raw, so PAX extended headers and GNU long names are copied through as
+    // they are. Those that describe the next entry are held until it is known whether that entry
+    // is kept.
+    let mut extensions = Vec::new();
+    let mut pax_path = None;
+    let mut long_name = None;
+    for entry in source.entries()?.raw(true) {
+        check(controller)?;
+        let mut entry = entry?;
+        let header = entry.header().clone();
+        let entry_type = header.entry_type();
+        if entry_type.is_pax_global_extensions() {
+            builder.append(&header, &mut entry)?;
+            continue;
+        }
+        if entry_type.is_pax_local_extensions()
+            || entry_type.is_gnu_longname()
+            || entry_type.is_gnu_longlink()
+        {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            if entry_type.is_pax_local_extensions() {
+                pax_path = pax_value(&data, "path").map(bytes_path).or(pax_path);
+            } else if entry_type.is_gnu_longname() {
+                let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                long_name = Some(bytes_path(&data[..len]));
+            }
+            extensions.push((header, data));
+            continue;
+        }
+
+        let path = pax_path
+            .take()
+            .or(long_name.take())
+            .unwrap_or_else(|| bytes_path(&header.path_bytes()));
+        if archive::enclosed(&path).is_some_and(|name| is_replaced(&name)) {
+            extensions.clear();
+            continue;
+        }
+        for (header, data) in extensions.drain(..) {
+            builder.append(&header, data.as_slice())?;
+        }
+        builder.append(&header, &mut entry)?;
+    }
+
+    for entry in added.iter() {
+        check(controller)?;
+        controller.set_current_file(&entry.path);
+        if entry.path.is_file() {
+            // Read through OpReader to report progress within large files
+            let metadata = fs::metadata(&entry.path)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&metadata);
+            let reader = OpReader::new(&entry.path, controller.clone())?;
+            builder.append_data(&mut header, &entry.name, reader)?;
+        } else {
+            builder.append_path_with_name(&entry.path, &entry.name)?;
+        }
+        controller.file_done();
+    }
+    builder.into_inner()
+}
+
+// Value of `key` in the records of a PAX extended header, which are written as
+// "<length> <key>=<value>\n"
+fn pax_value<'a>(data: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest.iter().position(|&b| b == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        let record = rest.get(space + 1..len)?.strip_suffix(b"\n")?;
+        rest = &rest[len..];
+        let equals = record.iter().position(|&b| b == b'=')?;
+        if &record[..equals] == key.as_bytes() {
+            return Some(&record[equals + 1..]);
+        }
+    }
+    None
+}
+
+fn bytes_path(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+fn rewrite_zip<W: Write + io::Seek>(
+    writer: W,
+    archive: &Path,
+    is_replaced: &dyn Fn(&Path) -> bool,
+    added: &[NewEntry],
+    password: Option<&str>,
+    controller: &Controller,
+) -> io::Result<W> {
+    let mut source = zip::ZipArchive::new(io::BufReader::new(fs::File::open(archive)?))?;
+    // Names are matched as they were listed
+    let encoding = archive::NameEncoding::Auto.resolve(&mut source)?;
+    let mut zip = zip::ZipWriter::new(writer);
+    // Added items are compressed like the entries they replace, or else like the first file
+    let mut methods = BTreeMap::new();
+    let mut default_method = None;
+    for i in 0..source.len() {
+        check(controller)?;
+        // Entries are copied without decompressing them, so encrypted ones need no password
+        let file = source.by_index_raw(i)?;
+        let compressed_size = file.compressed_size();
+        let method = writable_method(file.compression());
+        if !file.is_dir() {
+            default_method.get_or_insert(method);
+        }
+        match encoding.enclosed_name(&file) {
+            Some(name) if is_replaced(&name) => {
+                methods.insert(name, method);
+            }
+            _ => zip.raw_copy_file(file)?,
+        }
+        controller.add_bytes(compressed_size);
+    }
+
+    let mut buffer = vec![0; 4 * 1024 * 1024];
+    for entry in added.iter() {
+        check(controller)?;
+        controller.set_current_file(&entry.path);
+        let Some(name) = entry.name.to_str() else {
+            log::warn!("skipping {:?}, which is not valid UTF-8", entry.path);
+            continue;
+        };
+        let mut options = zip::write::SimpleFileOptions::default();
+        if let Some(method) = methods.get(&entry.name).or(default_method.as_ref()) {
+            options = options.compression_method(*method);
+        }
+        if let Some(password) = password {
+            options = options.with_aes_encryption(Aes256, password);
+        }
+        if entry.path.is_file() {
+            let mut file = fs::File::open(&entry.path)?;
+            let metadata = file.metadata()?;
+            if metadata.len() >= 4 * 1024 * 1024 * 1024 {
+                // The large file option must be enabled for files above 4 GiB
+                options = options.large_file(true);
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                options = options.unix_permissions(metadata.mode());
+            }
+            zip.start_file(name, options)?;
+            loop {
+                check(controller)?;
+                let count = file.read(&mut buffer)?;
+                if count == 0 {
+                    break;
+                }
+                zip.write_all(&buffer[..count])?;
+                controller.add_bytes(count as u64);
+            }
+        } else {
+            zip.add_directory(name, options)?;
+        }
+        controller.file_done();
+    }
+    Ok(zip.finish()?)
+}
+
+// Methods that entries can be decompressed with but not written are replaced by deflate
+fn writable_method(method: zip::CompressionMethod) -> zip::CompressionMethod {
+    use zip::CompressionMethod;
+    match method {
+        CompressionMethod::Stored | CompressionMethod::Bzip2 | CompressionMethod::Zstd => method,
+        _ => CompressionMethod::Deflated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Write},
+        path::PathBuf,
+        sync,
+    };
+
+    use futures::StreamExt;
+
+    use super::add_to_archive;
+    use crate::{
+        app::{test_utils::empty_fs, DialogPage, Message},
+        archive,
+        operation::{Controller, ReplaceResult},
+    };
+
+    #[test]
+    fn add_to_tar_gz() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.tar.gz");
+        {
+            let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+                fs::File::create(&archive_path)?,
+                flate2::Compression::default(),
+            ));
+            let mut header = tar::Header::new_gnu();
+            header.set_size(3);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, "dir/old.txt", &b"old"[..])?;
+            builder.append_data(&mut header, "same.txt", &b"old"[..])?;
+            builder.into_inner()?.finish()?;
+        }
+
+        let dir = fs.path().join("dir");
+        fs::create_dir(&dir)?;
+        fs::write(dir.join("new.txt"), "new")?;
+        let same = fs.path().join("same.txt");
+        fs::write(&same, "new")?;
+
+        let (tx, mut rx) = futures::channel::mpsc::channel(1);
+        let handle_messages = std::thread::spawn(move || {
+            futures::executor::block_on(async {
+                while let Some(msg) = rx.next().await {
+                    if let Message::DialogPush(DialogPage::Replace { tx, .. }) = msg {
+                        tx.send(ReplaceResult::Replace(false))
+                            .await
+                            .expect("Sending a response to a replace request should succeed");
+                    }
+                }
+            })
+        });
+        add_to_archive(
+            &archive_path,
+            &[dir, same],
+            None,
+            &sync::Arc::new(tokio::sync::Mutex::new(tx)),
+            &Controller::default(),
+        )
+        .expect("Adding to the archive should have succeeded");
+        handle_messages
+            .join()
+            .expect("Message handler should finish");
+
+        let names: Vec<_> = archive::list(&archive_path)?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("dir/old.txt"),
+                PathBuf::from("dir/new.txt"),
+                PathBuf::from("same.txt"),
+            ]
+        );
+        assert_eq!(
+            archive::read(&archive_path, &PathBuf::from("same.txt"), 3)?,
+            b"new"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_to_zip_keeps_compression() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.zip");
+        {
+            let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path)?);
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+            zip.start_file("old.txt", options)?;
+            zip.write_all(b"old")?;
+            zip.finish()?;
+        }
+        let new = fs.path().join("new.txt");
+        fs::write(&new, "new")?;
+
+        let (tx, _rx) = futures::channel::mpsc::channel(1);
+        add_to_archive(
+            &archive_path,
+            &[new],
+            None,
+            &sync::Arc::new(tokio::sync::Mutex::new(tx)),
+            &Controller::default(),
+        )
+        .expect("Adding to the archive should have succeeded");
+
+        let mut zip = zip::ZipArchive::new(fs::File::open(&archive_path)?)?;
+        for name in ["old.txt", "new.txt"] {
+            assert_eq!(
+                zip.by_name(name)?.compression(),
+                zip::CompressionMethod::Stored
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_to_tar_keeps_pax_headers() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let archive_path = fs.path().join("archive.tar");
+        let long_name = format!("{}/file.txt", "d".repeat(120));
+        {
+            let mut builder = tar::Builder::new(fs::File::create(&archive_path)?);
+            let record = format!(" path={}\n", long_name);
+            // The length at the start of a record counts its own digits
+            let len = record.len() + (record.len() + 3).to_string().len();
+            let pax = format!("{}{}", len, record);
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::XHeader);
+            header.set_size(pax.len() as u64);
+            header.set_cksum();
+            builder.append(&header, pax.as_bytes())?;
+            let mut header = tar::Header::new_ustar();
+            header.set_path("file.txt")?;
+            header.set_size(3);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, &b"old"[..])?;
+            builder.finish()?;
+        }
+        let new = fs.path().join("new.txt");
+        fs::write(&new, "new")?;
+
+        let (tx, _rx) = futures::channel::mpsc::channel(1);
+        add_to_archive(
+            &archive_path,
+            &[new],
+            None,
+            &sync::Arc::new(tokio::sync::Mutex::new(tx)),
+            &Controller::default(),
+        )
+        .expect("Adding to the archive should have succeeded");
+
+        let names: Vec<_> = archive::list(&archive_path)?
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(names, [PathBuf::from(long_name), PathBuf::from("new.txt")]);
+
+        Ok(())
+    }
+}
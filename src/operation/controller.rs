@@ -1,8 +1,17 @@
 use crate::fl;
 
-use std::sync::{Arc, Mutex};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::sync::Notify;
 
+// Minimum time between samples of the transfer rate
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+// Weight of the newest sample in the averaged transfer rate
+const RATE_SMOOTHING: f64 = 0.3;
+
 #[derive(Clone, Copy, Debug)]
 pub enum ControllerState {
     Cancelled,
@@ -10,10 +19,39 @@ pub enum ControllerState {
     Running,
 }
 
+/// Detailed progress of an operation that transfers data
+#[derive(Clone, Debug, Default)]
+pub struct ControllerProgress {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub current_file: Option<PathBuf>,
+    /// Bytes per second, averaged over recent samples
+    pub rate: Option<f64>,
+}
+
+impl ControllerProgress {
+    /// Estimated time until all bytes are transferred
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate.filter(|rate| *rate > 0.0)?;
+        let remaining = self.bytes_total.saturating_sub(self.bytes_done);
+        Some(Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProgressDetails {
+    progress: ControllerProgress,
+    // Time and bytes done when the transfer rate was last sampled
+    sample_opt: Option<(Instant, u64)>,
+}
+
 #[derive(Debug)]
 struct ControllerInner {
     state: Mutex<ControllerState>,
     progress: Mutex<f32>,
+    details: Mutex<ProgressDetails>,
     notify: Notify,
 }
 
@@ -30,6 +68,7 @@ impl Default for Controller {
             inner: Arc::new(ControllerInner {
                 state: Mutex::new(ControllerState::Running),
                 progress: Mutex::new(0.0),
+                details: Mutex::new(ProgressDetails::default()),
                 notify: Notify::new(),
             }),
         }
@@ -57,11 +96,73 @@ impl Controller {
         *self.inner.progress.lock().unwrap() = progress;
     }
 
+    pub fn details(&self) -> ControllerProgress {
+        self.inner.details.lock().unwrap().progress.clone()
+    }
+
+    /// Set the number of files and bytes the operation will transfer
+    pub fn set_totals(&self, files_total: usize, bytes_total: u64) {
+        let mut details = self.inner.details.lock().unwrap();
+        details.progress.files_total = files_total;
+        details.progress.bytes_total = bytes_total;
+    }
+
+    pub fn set_current_file(&self, path: &Path) {
+        self.inner.details.lock().unwrap().progress.current_file = Some(path.to_path_buf());
+    }
+
+    pub fn file_done(&self) {
+        self.inner.details.lock().unwrap().progress.files_done += 1;
+    }
+
+    pub fn add_bytes(&self, count: u64) {
+        self.update_bytes_done(|bytes_done| bytes_done + count);
+    }
+
+    /// Update the bytes transferred, which also updates the progress and transfer rate
+    pub fn set_bytes_done(&self, bytes_done: u64) {
+        self.update_bytes_done(|_| bytes_done);
+    }
+
+    // Read and update the bytes transferred under one lock, as operations may report from
+    // several tasks at once
+    fn update_bytes_done(&self, f: impl FnOnce(u64) -> u64) {
+        let mut details = self.inner.details.lock().unwrap();
+        let bytes_done = f(details.progress.bytes_done);
+        details.progress.bytes_done = bytes_done;
+
+        let now = Instant::now();
+        match details.sample_opt {
+            Some((time, bytes)) => {
+                let elapsed = now.duration_since(time);
+                if elapsed >= RATE_INTERVAL {
+                    let rate = bytes_done.saturating_sub(bytes) as f64 / elapsed.as_secs_f64();
+                    details.progress.rate = Some(match details.progress.rate {
+                        Some(average) => average + (rate - average) * RATE_SMOOTHING,
+                        None => rate,
+                    });
+                    details.sample_opt = Some((now, bytes_done));
+                }
+            }
+            None => details.sample_opt = Some((now, bytes_done)),
+        }
+
+        let bytes_total = details.progress.bytes_total;
+        drop(details);
+        if bytes_total > 0 {
+            self.set_progress((bytes_done as f64 / bytes_total as f64).min(1.0) as f32);
+        }
+    }
+
     pub fn state(&self) -> ControllerState {
         *self.inner.state.lock().unwrap()
     }
 
     pub fn set_state(&self, state: ControllerState) {
+        if !matches!(state, ControllerState::Running) {
+            // Time spent paused is not counted in the transfer rate
+            self.inner.details.lock().unwrap().sample_opt = None;
+        }
         *self.inner.state.lock().unwrap() = state;
         self.inner.notify.notify_waiters();
     }
@@ -106,3 +207,34 @@ impl Drop for Controller {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Controller, ControllerProgress};
+
+    #[test]
+    fn progress_follows_bytes() {
+        let controller = Controller::default();
+        controller.set_totals(2, 200);
+        controller.add_bytes(50);
+        assert_eq!(controller.progress(), 0.25);
+        controller.set_bytes_done(200);
+        controller.file_done();
+        assert_eq!(controller.progress(), 1.0);
+        assert_eq!(controller.details().files_done, 1);
+    }
+
+    #[test]
+    fn eta_from_rate() {
+        let progress = ControllerProgress {
+            bytes_done: 100,
+            bytes_total: 300,
+            rate: Some(50.0),
+            ..Default::default()
+        };
+        assert_eq!(progress.eta(), Some(Duration::from_secs(4)));
+        assert_eq!(ControllerProgress::default().eta(), None);
+    }
+}
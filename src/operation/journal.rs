@@ -0,0 +1,481 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+
+use super::{Operation, OperationSelection};
+
+// Maximum number of completed operations that can be undone
+const JOURNAL_LIMIT: usize = 100;
+
+/// A completed operation along with the operations that reverse it
+#[derive(Clone, Debug)]
+pub struct JournalEntry {
+    pub operation: Operation,
+    pub inverse: Vec<Operation>,
+}
+
+// State captured before an operation is performed, needed to build its inverse
+#[derive(Debug)]
+enum Pending {
+    Record {
+        operation: Operation,
+        // Destinations that existed before the operation, which must never be removed by undo
+        existing: BTreeSet<PathBuf>,
+        // Mode before a permissions change
+        mode_opt: Option<u32>,
+        // Entry being redone, which goes back on the redo stack if the operation fails
+        redo_opt: Option<JournalEntry>,
+    },
+    // Step of an undo, with the steps that follow it
+    Undo {
+        remaining: Vec<Operation>,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct Journal {
+    undo: Vec<JournalEntry>,
+    redo: Vec<JournalEntry>,
+    pending: BTreeMap<u64, Pending>,
+}
+
+impl Journal {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Record an operation that will be performed, or an entry that is redone
+    pub fn prepare(&mut self, id: u64, operation: &Operation, redo_opt: Option<JournalEntry>) {
+        self.pending.insert(
+            id,
+            Pending::Record {
+                operation: operation.clone(),
+                existing: BTreeSet::new(),
+                mode_opt: None,
+                redo_opt,
+            },
+        );
+    }
+
+    /// Capture the state needed to reverse an operation once it starts. Operations may wait
+    /// behind others, which can change the same paths, so this is not done when they are queued.
+    pub fn start(&mut self, id: u64) {
+        let Some(Pending::Record {
+            operation,
+            existing,
+            mode_opt,
+            ..
+        }) = self.pending.get_mut(&id)
+        else {
+            return;
+        };
+        match operation {
+            Operation::Copy { paths, to } | Operation::Move { paths, to, .. } => {
+                for path in paths.iter() {
+                    if let Some(name) = path.file_name() {
+                        let dest = to.join(name);
+                        if dest.exists() {
+                            existing.insert(dest);
+                        }
+                    }
+                }
+            }
+            Operation::Compress { to, .. } => {
+                if to.exists() {
+                    existing.insert(to.clone());
+                }
+            }
+            Operation::SetPermissions { path, .. } => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    *mode_opt = fs::metadata(path)
+                        .ok()
+                        .map(|metadata| metadata.permissions().mode() & 0o7777);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Mark an operation as a step of an undo, so it is not recorded itself. The remaining steps
+    /// are returned by [`Journal::complete`] once it is done.
+    pub fn prepare_undo(&mut self, id: u64, remaining: Vec<Operation>) {
+        self.pending.insert(id, Pending::Undo { remaining });
+    }
+
+    /// Record a completed operation, if it can be reversed. If the operation was a step of an
+    /// undo, the steps that remain are returned, to be performed one after another.
+    pub fn complete(&mut self, id: u64, op_sel: &OperationSelection) -> Vec<Operation> {
+        let (operation, existing, mode_opt, redo_opt) = match self.pending.remove(&id) {
+            Some(Pending::Record {
+                operation,
+                existing,
+                mode_opt,
+                redo_opt,
+            }) => (operation, existing, mode_opt, redo_opt),
+            Some(Pending::Undo { remaining }) => return remaining,
+            None => return Vec::new(),
+        };
+
+        let inverse = inverse(&operation, op_sel, &existing, mode_opt);
+        if inverse.is_empty() {
+            return Vec::new();
+        }
+
+        if redo_opt.is_none() {
+            self.redo.clear();
+        }
+        self.undo.push(JournalEntry { operation, inverse });
+        if self.undo.len() > JOURNAL_LIMIT {
+            self.undo.remove(0);
+        }
+        Vec::new()
+    }
+
+    /// Forget a failed operation
+    pub fn fail(&mut self, id: u64) {
+        match self.pending.remove(&id) {
+            Some(Pending::Record {
+                redo_opt: Some(entry),
+                ..
+            }) => {
+                // Nothing was recorded, so the entry can still be redone
+                self.redo.push(entry);
+            }
+            Some(Pending::Undo { .. }) => {
+                // The redo stack no longer matches the state on disk
+                self.redo.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Take the most recent entry to undo, moving it to the redo stack
+    pub fn undo(&mut self) -> Option<JournalEntry> {
+        let entry = self.undo.pop()?;
+        self.redo.push(entry.clone());
+        Some(entry)
+    }
+
+    /// Take the most recently undone entry to perform again
+    pub fn redo(&mut self) -> Option<JournalEntry> {
+        self.redo.pop()
+    }
+}
+
+fn inverse(
+    operation: &Operation,
+    op_sel: &OperationSelection,
+    existing: &BTreeSet<PathBuf>,
+    mode_opt: Option<u32>,
+) -> Vec<Operation> {
+    // Paths created by the operation, excluding anything that was already there
+    let created = || -> Vec<PathBuf> {
+        op_sel
+            .selected
+            .iter()
+            .filter(|path| !existing.contains(*path))
+            .cloned()
+            .collect()
+    };
+
+    let mut inverse = Vec::new();
+    match operation {
+        Operation::BatchRename { renames } => {
+            inverse.push(Operation::BatchRename {
+                renames: renames
+                    .iter()
+                    .map(|(from, to)| (to.clone(), from.clone()))
+                    .collect(),
+            });
+        }
+        Operation::Compress { .. }
+        | Operation::Copy { .. }
+        | Operation::Duplicate { .. }
+        | Operation::Extract { .. }
+        | Operation::ExtractEntries { .. }
+        | Operation::Link { .. } => {
+            let paths = created();
+            if !paths.is_empty() {
+                inverse.push(Operation::Delete { paths });
+            }
+        }
+        Operation::ChangePermissions { .. } => {
+            // Previous modes, owners and groups are collected while changing them
+            inverse.extend(op_sel.inverse.iter().cloned());
+        }
+        Operation::Move {
+            cross_device_copy, ..
+        } => {
+            let pairs = op_sel
+                .pairs
+                .iter()
+                .filter(|(_, moved)| !existing.contains(moved));
+            if *cross_device_copy {
+                // The sources were kept, so only the copies are removed
+                let paths: Vec<_> = pairs.map(|(_, moved)| moved.clone()).collect();
+                if !paths.is_empty() {
+                    inverse.push(Operation::Delete { paths });
+                }
+                return inverse;
+            }
+
+            // Items are moved back to their original parents, which may differ
+            let mut by_parent = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+            let mut renames = Vec::new();
+            for (from, moved) in pairs {
+                if from.file_name() != moved.file_name() {
+                    // Items renamed to keep both get their original names back
+                    renames.push(Operation::Rename {
+                        from: moved.clone(),
+                        to: from.clone(),
+                    });
+                } else if let Some(parent) = from.parent() {
+                    by_parent
+                        .entry(parent.to_path_buf())
+                        .or_default()
+                        .push(moved.clone());
+                }
+            }
+            for (parent, paths) in by_parent {
+                inverse.push(Operation::Move {
+                    paths,
+                    to: parent,
+                    cross_device_copy: false,
+                });
+            }
+            inverse.extend(renames);
+        }
+        Operation::NewFile { path } | Operation::NewFolder { path } => {
+            inverse.push(Operation::Delete {
+                paths: vec![path.clone()],
+            });
+        }
+        Operation::Rename { from, to } => {
+            inverse.push(Operation::Rename {
+                from: to.clone(),
+                to: from.clone(),
+            });
+        }
+        Operation::Restore { .. } => {
+            if !op_sel.selected.is_empty() {
+                inverse.push(Operation::Delete {
+                    paths: op_sel.selected.clone(),
+                });
+            }
+        }
+        Operation::SetPermissions { path, .. } => {
+            if let Some(mode) = mode_opt {
+                inverse.push(Operation::SetPermissions {
+                    path: path.clone(),
+                    mode,
+                });
+            }
+        }
+        // Trashing is undone through the toast, other operations cannot be reversed
+        _ => {}
+    }
+    inverse
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Journal, Operation, OperationSelection};
+
+    fn rename(from: &str, to: &str) -> Operation {
+        Operation::Rename {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+        }
+    }
+
+    #[test]
+    fn undo_redo_rename() {
+        let mut journal = Journal::default();
+        journal.prepare(0, &rename("/a", "/b"), None);
+        journal.complete(0, &OperationSelection::default());
+        assert!(journal.can_undo());
+
+        let entry = journal.undo().expect("rename should be undoable");
+        assert_eq!(entry.inverse, vec![rename("/b", "/a")]);
+        assert!(!journal.can_undo());
+        assert!(journal.can_redo());
+
+        let entry = journal.redo().expect("rename should be redoable");
+        assert_eq!(entry.operation, rename("/a", "/b"));
+        let operation = entry.operation.clone();
+        journal.prepare(1, &operation, Some(entry));
+        journal.complete(1, &OperationSelection::default());
+        assert!(journal.can_undo());
+    }
+
+    #[test]
+    fn undo_batch_rename() {
+        let operation = Operation::BatchRename {
+            renames: vec![
+                (PathBuf::from("/a"), PathBuf::from("/b")),
+                (PathBuf::from("/b"), PathBuf::from("/a")),
+                (PathBuf::from("/c"), PathBuf::from("/d")),
+            ],
+        };
+        let mut journal = Journal::default();
+        journal.prepare(0, &operation, None);
+        journal.complete(0, &OperationSelection::default());
+
+        // All renames are undone together by a single operation
+        let entry = journal.undo().expect("batch rename should be undoable");
+        assert_eq!(
+            entry.inverse,
+            vec![Operation::BatchRename {
+                renames: vec![
+                    (PathBuf::from("/b"), PathBuf::from("/a")),
+                    (PathBuf::from("/a"), PathBuf::from("/b")),
+                    (PathBuf::from("/d"), PathBuf::from("/c")),
+                ],
+            }]
+        );
+        assert!(!journal.can_undo());
+    }
+
+    #[test]
+    fn new_operation_clears_redo() {
+        let mut journal = Journal::default();
+        journal.prepare(0, &rename("/a", "/b"), None);
+        journal.complete(0, &OperationSelection::default());
+        journal.undo();
+        assert!(journal.can_redo());
+
+        journal.prepare(1, &rename("/c", "/d"), None);
+        journal.complete(1, &OperationSelection::default());
+        assert!(!journal.can_redo());
+    }
+
+    #[test]
+    fn failed_undo_clears_redo() {
+        let mut journal = Journal::default();
+        journal.prepare(0, &rename("/a", "/b"), None);
+        journal.complete(0, &OperationSelection::default());
+        journal.undo();
+        journal.prepare_undo(1, Vec::new());
+        journal.fail(1);
+        assert!(!journal.can_redo());
+    }
+
+    #[test]
+    fn failed_redo_is_kept() {
+        let mut journal = Journal::default();
+        journal.prepare(0, &rename("/a", "/b"), None);
+        journal.complete(0, &OperationSelection::default());
+        journal.undo();
+
+        let entry = journal.redo().expect("rename should be redoable");
+        let operation = entry.operation.clone();
+        journal.prepare(1, &operation, Some(entry));
+        journal.fail(1);
+        assert!(journal.can_redo());
+        assert!(!journal.can_undo());
+    }
+
+    #[test]
+    fn undo_steps_are_sequential() {
+        let mut journal = Journal::default();
+        journal.prepare_undo(0, vec![rename("/d", "/c"), rename("/f", "/e")]);
+        assert_eq!(
+            journal.complete(0, &OperationSelection::default()),
+            vec![rename("/d", "/c"), rename("/f", "/e")]
+        );
+        // Undo steps are not recorded themselves
+        assert!(!journal.can_undo());
+    }
+
+    #[test]
+    fn undo_move() {
+        let operation = |cross_device_copy| Operation::Move {
+            paths: vec![PathBuf::from("/from/a"), PathBuf::from("/from/b")],
+            to: PathBuf::from("/to"),
+            cross_device_copy,
+        };
+        let op_sel = OperationSelection {
+            pairs: vec![
+                (PathBuf::from("/from/a"), PathBuf::from("/to/a")),
+                (PathBuf::from("/from/b"), PathBuf::from("/to/b (2)")),
+            ],
+            ..Default::default()
+        };
+
+        // Moved items go back to their parents, with the names they had
+        let mut journal = Journal::default();
+        journal.prepare(0, &operation(false), None);
+        journal.complete(0, &op_sel);
+        let entry = journal.undo().expect("move should be undoable");
+        assert_eq!(
+            entry.inverse,
+            vec![
+                Operation::Move {
+                    paths: vec![PathBuf::from("/to/a")],
+                    to: PathBuf::from("/from"),
+                    cross_device_copy: false,
+                },
+                Operation::Rename {
+                    from: PathBuf::from("/to/b (2)"),
+                    to: PathBuf::from("/from/b"),
+                },
+            ]
+        );
+
+        // Copies across devices keep their sources, so only the copies are removed
+        journal.prepare(1, &operation(true), None);
+        journal.complete(1, &op_sel);
+        let entry = journal.undo().expect("move should be undoable");
+        assert_eq!(
+            entry.inverse,
+            vec![Operation::Delete {
+                paths: vec![PathBuf::from("/to/a"), PathBuf::from("/to/b (2)")],
+            }]
+        );
+    }
+
+    #[test]
+    fn copy_does_not_remove_existing() {
+        let mut journal = Journal::default();
+        let existing = PathBuf::from("/to/existing");
+        let created = PathBuf::from("/to/created");
+        journal.prepare(
+            0,
+            &Operation::Copy {
+                paths: vec![PathBuf::from("/from/existing")],
+                to: PathBuf::from("/to"),
+            },
+            None,
+        );
+        journal.start(0);
+        if let Some(super::Pending::Record { existing: set, .. }) = journal.pending.get_mut(&0) {
+            // Simulate a destination that existed before the copy
+            set.insert(existing.clone());
+        }
+        journal.complete(
+            0,
+            &OperationSelection {
+                ignored: Vec::new(),
+                selected: vec![existing, created.clone()],
+                ..Default::default()
+            },
+        );
+        let entry = journal.undo().expect("copy should be undoable");
+        assert_eq!(
+            entry.inverse,
+            vec![Operation::Delete {
+                paths: vec![created]
+            }]
+        );
+    }
+}
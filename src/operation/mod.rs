@@ -1,29 +1,51 @@
 use crate::{
-    app::{ArchiveType, DialogPage, Message},
-    config::IconSizes,
+    app::{ArchiveType, CompressionLevel, DialogPage, Message},
+    archive::{EntryMetadata, NameEncoding},
+    batch_rename,
+    config::{IconSizes, OperationsConfig, ShredPattern},
     fl,
     mime_icon::mime_for_path,
     spawn_detached::spawn_detached,
     tab,
 };
 use cosmic::iced::futures::{channel::mpsc::Sender, SinkExt};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt::Formatter;
 use std::{
     borrow::Cow,
     fs,
     io::{self, Read, Write},
+    ops::ControlFlow,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 use tokio::sync::{mpsc, Mutex as TokioMutex};
 use walkdir::WalkDir;
 use zip::result::ZipError;
 use zip::AesMode::Aes256;
 
-pub use self::controller::{Controller, ControllerState};
+pub mod append;
+
+pub use self::controller::{Controller, ControllerProgress, ControllerState};
 pub mod controller;
 
+pub use self::journal::{Journal, JournalEntry};
+pub mod journal;
+
+pub use self::queue::UnfinishedOperation;
+use self::queue::{QueueLog, QueuedOperation, ResumeState};
+pub mod queue;
+
+pub use self::scheduler::Scheduler;
+pub mod scheduler;
+
+pub use self::shred::shred_warnings;
+pub mod shred;
+
+pub use self::permissions::PermissionChanges;
+pub mod permissions;
+
 use self::reader::OpReader;
 pub mod reader;
 
@@ -52,6 +74,15 @@ async fn handle_replace(
         }
     };
 
+    handle_replace_items(msg_tx, item_from, item_to, multiple).await
+}
+
+async fn handle_replace_items(
+    msg_tx: Arc<TokioMutex<Sender<Message>>>,
+    item_from: tab::Item,
+    item_to: tab::Item,
+    multiple: bool,
+) -> ReplaceResult {
     let (tx, mut rx) = mpsc::channel(1);
     let _ = msg_tx
         .lock()
@@ -70,10 +101,12 @@ async fn handle_replace(
 fn get_directory_name(file_name: &str) -> &str {
     // TODO: Chain with COMPOUND_EXTENSIONS once more formats are supported
     const SUPPORTED_EXTENSIONS: &[&str] = &[
+        ".7z",
         ".tar.bz2",
         ".tar.gz",
         ".tar.lzma",
         ".tar.xz",
+        ".tar.zst",
         ".tgz",
         ".tar",
         ".zip",
@@ -87,12 +120,223 @@ fn get_directory_name(file_name: &str) -> &str {
     file_name
 }
 
-// From https://docs.rs/zip/latest/zip/read/struct.ZipArchive.html#method.extract, with cancellation and progress added
+// The only item of a directory, if it is a directory itself
+fn single_dir(dir: &Path) -> Option<PathBuf> {
+    let mut entries = fs::read_dir(dir).ok()?;
+    let entry = entries.next()?.ok()?;
+    if entries.next().is_some() || !entry.file_type().ok()?.is_dir() {
+        return None;
+    }
+    Some(entry.path())
+}
+
+// Archives are extracted to a hidden folder `base`, which is then moved to where it belongs.
+// Archives holding a single folder are extracted as that folder, so it is not nested inside
+// another folder named after the archive. Other archives are wrapped in such a folder.
+async fn place_extracted(
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+    path: &Path,
+    base: &Path,
+    file_name: &str,
+    to: &Path,
+    multiple: bool,
+    replace_result_opt: &mut Option<ReplaceResult>,
+) -> Result<ControlFlow<bool, PathBuf>, OperationError> {
+    let (from, mut dest) = match single_dir(base) {
+        Some(root) => {
+            let dest = to.join(root.file_name().unwrap_or_default());
+            (root, dest)
+        }
+        None => (base.to_path_buf(), to.join(get_directory_name(file_name))),
+    };
+    let mut replace = false;
+    if dest.symlink_metadata().is_ok() {
+        if from == base {
+            // Wrapping folders are named after the archive, so they are kept beside others
+            dest = copy_unique_path(&dest, to);
+        } else {
+            let replace_result = match *replace_result_opt {
+                Some(result) => result,
+                None => {
+                    handle_replace(msg_tx.clone(), path.to_path_buf(), dest.clone(), multiple).await
+                }
+            };
+            match replace_result {
+                ReplaceResult::Replace(apply_to_all) => {
+                    if apply_to_all {
+                        *replace_result_opt = Some(replace_result);
+                    }
+                    replace = true;
+                }
+                ReplaceResult::KeepBoth => dest = copy_unique_path(&dest, to),
+                ReplaceResult::Skip(apply_to_all) => {
+                    if apply_to_all {
+                        *replace_result_opt = Some(replace_result);
+                    }
+                    return Ok(ControlFlow::Break(true));
+                }
+                ReplaceResult::Cancel => return Ok(ControlFlow::Break(false)),
+            }
+        }
+    }
+
+    let base = base.to_path_buf();
+    compio::runtime::spawn_blocking(move || -> Result<PathBuf, OperationError> {
+        if replace {
+            // The extracted folder replaces the existing item, rather than being merged into it
+            let removed = if dest.is_symlink() || !dest.is_dir() {
+                fs::remove_file(&dest)
+            } else {
+                fs::remove_dir_all(&dest)
+            };
+            removed.map_err(|err| {
+                OperationError::from_str(format!("failed to replace {:?}: {}", dest, err))
+            })?;
+        }
+        fs::rename(&from, &dest).map_err(OperationError::from_str)?;
+        if from != base {
+            if let Err(err) = fs::remove_dir(&base) {
+                log::warn!("failed to remove {:?}: {}", base, err);
+            }
+        }
+        Ok(dest)
+    })
+    .await
+    .map_err(wrap_compio_spawn_error)?
+    .map(ControlFlow::Continue)
+}
+
+// Extract the entries of an archive to `base`
+fn extract_archive(
+    path: &Path,
+    base: &Path,
+    guard: &mut crate::archive::Guard,
+    password: Option<String>,
+    name_encoding: NameEncoding,
+    restore_owner: bool,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let archive_path = crate::volumes::archive_path(path);
+    let mime = mime_for_path(&archive_path, None, false);
+    let result = match mime.essence_str() {
+        "application/gzip" | "application/x-compressed-tar" => {
+            OpReader::archive(path, controller.clone())
+                .map(io::BufReader::new)
+                .map(flate2::read::GzDecoder::new)
+                .map(tar::Archive::new)
+                .and_then(|archive| {
+                    crate::archive::unpack_tar(archive, base, guard, controller, restore_owner)
+                })
+                .map_err(OperationError::from_str)
+        }
+        "application/x-tar" => OpReader::archive(path, controller.clone())
+            .map(io::BufReader::new)
+            .map(tar::Archive::new)
+            .and_then(|archive| {
+                crate::archive::unpack_tar(archive, base, guard, controller, restore_owner)
+            })
+            .map_err(OperationError::from_str),
+        "application/x-7z-compressed" => OpReader::archive(path, controller.clone())
+            .map(io::BufReader::new)
+            .map_err(ZipError::from)
+            .and_then(|reader| {
+                crate::archive::unpack_7z(reader, password.as_deref(), base, guard, controller)
+            })
+            .map_err(password_error),
+        "application/zip" => crate::volumes::open(path)
+            .map(io::BufReader::new)
+            .map_err(ZipError::from)
+            .and_then(zip::ZipArchive::new)
+            .and_then(|mut archive| {
+                zip_extract(
+                    &mut archive,
+                    base,
+                    guard,
+                    controller.clone(),
+                    password,
+                    name_encoding,
+                    restore_owner,
+                )
+            })
+            .map_err(password_error),
+        #[cfg(feature = "bzip2")]
+        "application/x-bzip"
+        | "application/x-bzip-compressed-tar"
+        | "application/x-bzip2"
+        | "application/x-bzip2-compressed-tar" => OpReader::archive(path, controller.clone())
+            .map(io::BufReader::new)
+            .map(bzip2::read::BzDecoder::new)
+            .map(tar::Archive::new)
+            .and_then(|archive| {
+                crate::archive::unpack_tar(archive, base, guard, controller, restore_owner)
+            })
+            .map_err(OperationError::from_str),
+        #[cfg(feature = "xz2")]
+        "application/x-xz" | "application/x-xz-compressed-tar" => {
+            OpReader::archive(path, controller.clone())
+                .map(io::BufReader::new)
+                .map(xz2::read::XzDecoder::new)
+                .map(tar::Archive::new)
+                .and_then(|archive| {
+                    crate::archive::unpack_tar(archive, base, guard, controller, restore_owner)
+                })
+                .map_err(OperationError::from_str)
+        }
+        #[cfg(feature = "zstd")]
+        "application/zstd" | "application/x-zstd-compressed-tar" => {
+            OpReader::archive(path, controller.clone())
+                .map(io::BufReader::new)
+                .and_then(zstd::Decoder::with_buffer)
+                .map(tar::Archive::new)
+                .and_then(|archive| {
+                    crate::archive::unpack_tar(archive, base, guard, controller, restore_owner)
+                })
+                .map_err(OperationError::from_str)
+        }
+        _ => Err(OperationError::from_str(format!(
+            "unsupported mime type {:?}",
+            mime
+        ))),
+    };
+
+    // Archives cut at a fixed size end early without a sign of
+    // missing volumes, so point at the next one
+    result.map_err(|err| {
+        let next_opt = crate::volumes::VolumeSet::from_path(path)
+            .and_then(Result::ok)
+            .and_then(|set| set.missing_after());
+        match (err.kind, next_opt) {
+            (OperationErrorType::Generic(message), Some(next)) => OperationError::from_str(fl!(
+                "archive-volume-may-be-missing",
+                error = message,
+                name = next.file_name().unwrap_or_default().to_string_lossy()
+            )),
+            (kind, _) => OperationError { kind },
+        }
+    })
+}
+
+// Errors of archives that need a password, or a different one, ask for it
+fn password_error(err: ZipError) -> OperationError {
+    match err {
+        ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) | ZipError::InvalidPassword => {
+            OperationError {
+                kind: OperationErrorType::PasswordRequired,
+            }
+        }
+        _ => OperationError::from_str(err),
+    }
+}
+
+// From https://docs.rs/zip/latest/zip/read/struct.ZipArchive.html#method.extract, with cancellation, progress and the checks of the guard added
 fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
     archive: &mut zip::ZipArchive<R>,
     directory: P,
+    guard: &mut crate::archive::Guard,
     controller: Controller,
     password: Option<String>,
+    name_encoding: NameEncoding,
+    restore_owner: bool,
 ) -> zip::result::ZipResult<()> {
     use std::{ffi::OsString, fs};
     use zip::result::ZipError;
@@ -113,11 +357,11 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
         Ok(())
     }
 
-    #[cfg(unix)]
-    let mut files_by_unix_mode = Vec::new();
+    let mut metadata = Vec::new();
     let mut buffer = vec![0; 4 * 1024 * 1024];
     let total_files = archive.len();
     let mut pending_directory_creates = VecDeque::new();
+    let name_encoding = name_encoding.resolve(archive)?;
 
     for i in 0..total_files {
         futures::executor::block_on(async {
@@ -127,21 +371,32 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
                 .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
         })?;
 
-        controller.set_progress((i as f32) / total_files as f32);
-
         let mut file = match &password {
             None => archive.by_index(i),
             Some(pwd) => archive.by_index_decrypt(i, pwd.as_bytes()),
         }
         .map_err(|e| e)?;
-        let filepath = file
-            .enclosed_name()
-            .ok_or(ZipError::InvalidArchive("Invalid file path".into()))?;
+        let Some(filepath) = guard.entry_path(Path::new(&*name_encoding.decode(&file))) else {
+            continue;
+        };
 
         let outpath = directory.as_ref().join(filepath);
-
+        if !guard.check_path(&outpath) {
+            continue;
+        }
+        controller.set_current_file(&outpath);
+
+        // Metadata is applied once everything is extracted
+        let entry_metadata = EntryMetadata {
+            path: outpath.clone(),
+            mode: file.unix_mode(),
+            mtime: crate::archive::zip_entry_mtime(&file),
+            owner: crate::archive::zip_entry_owner(&file),
+        };
         if file.is_dir() {
+            controller.add_bytes(file.compressed_size());
             pending_directory_creates.push_back(outpath.clone());
+            metadata.push(entry_metadata);
             continue;
         }
         let symlink_target = if file.is_symlink() && (cfg!(unix) || cfg!(windows)) {
@@ -151,6 +406,9 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
         } else {
             None
         };
+        if symlink_target.is_some() {
+            controller.add_bytes(file.compressed_size());
+        }
         drop(file);
         if let Some(target) = symlink_target {
             // create all pending dirs
@@ -166,13 +424,19 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
             {
                 use std::os::unix::ffi::OsStringExt;
                 let target = OsString::from_vec(target);
-                std::os::unix::fs::symlink(&target, outpath.as_path())?;
+                if guard.check_link(&outpath, Path::new(&target)) {
+                    std::os::unix::fs::symlink(&target, outpath.as_path())?;
+                    metadata.push(entry_metadata);
+                }
             }
             #[cfg(windows)]
             {
                 let Ok(target) = String::from_utf8(target) else {
                     return Err(ZipError::InvalidArchive("Invalid UTF-8 as symlink target"));
                 };
+                if !guard.check_link(&outpath, Path::new(&target)) {
+                    continue;
+                }
                 let target = target.into_boxed_str();
                 let target_is_dir_from_archive =
                     archive.shared.files.contains_key(&target) && is_dir(&target);
@@ -189,6 +453,7 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
                 } else {
                     std::os::windows::fs::symlink_file(target_path, outpath.as_path())?;
                 }
+                metadata.push(entry_metadata);
             }
             continue;
         }
@@ -208,6 +473,9 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
         }
 
         let total = file.size();
+        // Progress is reported in compressed bytes, to match the size of the archive
+        let compressed = file.compressed_size();
+        let mut reported = 0;
         let mut outfile = fs::File::create(&outpath)?;
         let mut current = 0;
         loop {
@@ -222,38 +490,70 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
             if count == 0 {
                 break;
             }
+            // Sizes in the archive can be wrong, so the bytes are counted as they are written
+            guard.add_bytes(count as u64)?;
             outfile.write_all(&buffer[..count])?;
             current += count as u64;
 
             if current < total {
-                let file_progress = current as f32 / total as f32;
-                let total_progress = (i as f32 + file_progress) / total_files as f32;
-                controller.set_progress(total_progress);
+                let done = (current as u128 * compressed as u128 / total as u128) as u64;
+                controller.add_bytes(done - reported);
+                reported = done;
             }
         }
+        controller.add_bytes(compressed.saturating_sub(reported));
         outfile.sync_all()?;
-        #[cfg(unix)]
+        metadata.push(entry_metadata);
+    }
+    // Directories without contents are only created now
+    while let Some(pending_dir) = pending_directory_creates.pop_front() {
+        make_writable_dir_all(pending_dir)?;
+    }
+    crate::archive::restore_metadata(metadata, restore_owner);
+    Ok(())
+}
+
+/// Write paths to a tar archive, returning the writer once the archive is finished so
+/// compressors can be finished too
+fn tar_compress<W: Write>(
+    writer: W,
+    paths: &[PathBuf],
+    relative_root: &Path,
+    controller: &Controller,
+) -> Result<W, OperationError> {
+    let mut archive = tar::Builder::new(writer);
+    for path in paths.iter() {
+        futures::executor::block_on(async {
+            controller.check().await.map_err(OperationError::from_str)
+        })?;
+
+        controller.set_current_file(path);
+
+        if let Some(relative_path) = path
+            .strip_prefix(relative_root)
+            .map_err(OperationError::from_str)?
+            .to_str()
         {
-            // Check for real permissions, which we'll set in a second pass
-            if let Some(mode) = file.unix_mode() {
-                files_by_unix_mode.push((outpath.clone(), mode));
+            if path.is_file() {
+                // Read through OpReader to report progress within large files
+                let metadata = fs::metadata(path).map_err(OperationError::from_str)?;
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(&metadata);
+                let reader =
+                    OpReader::new(path, controller.clone()).map_err(OperationError::from_str)?;
+                archive
+                    .append_data(&mut header, relative_path, reader)
+                    .map_err(OperationError::from_str)?;
+            } else {
+                archive
+                    .append_path_with_name(path, relative_path)
+                    .map_err(OperationError::from_str)?;
             }
         }
-    }
-    #[cfg(unix)]
-    {
-        use std::cmp::Reverse;
-        use std::os::unix::fs::PermissionsExt;
 
-        if files_by_unix_mode.len() > 1 {
-            // Ensure we update children's permissions before making a parent unwritable
-            files_by_unix_mode.sort_by_key(|(path, _)| Reverse(path.clone()));
-        }
-        for (path, mode) in files_by_unix_mode.into_iter() {
-            fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
-        }
+        controller.file_done();
     }
-    Ok(())
+    archive.into_inner().map_err(OperationError::from_str)
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -264,10 +564,69 @@ pub enum ReplaceResult {
     Cancel,
 }
 
+/// Summary of a copy or move, built before anything is copied
+#[derive(Clone, Debug, Default)]
+pub struct OperationPlan {
+    pub files: usize,
+    pub bytes: u64,
+    /// Existing destinations that conflict with the files being copied
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// Response to the preview of an [`OperationPlan`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PlanResult {
+    /// Start the operation, resolving every conflict with the result if set, or asking otherwise
+    Start(Option<ReplaceResult>),
+    Cancel,
+}
+
+async fn handle_plan(msg_tx: Arc<TokioMutex<Sender<Message>>>, plan: OperationPlan) -> PlanResult {
+    let (tx, mut rx) = mpsc::channel(1);
+    let _ = msg_tx
+        .lock()
+        .await
+        .send(Message::DialogPush(DialogPage::ConfirmPlan {
+            plan,
+            replace_result: None,
+            tx,
+        }))
+        .await;
+    rx.recv().await.unwrap_or(PlanResult::Cancel)
+}
+
+// Top level sources and destinations of copying or moving items into a folder
+fn copy_or_move_pairs(paths: Vec<PathBuf>, to: &Path, method: Method) -> Vec<(PathBuf, PathBuf)> {
+    paths
+        .into_iter()
+        .filter_map(|from| {
+            if matches!(from.parent(), Some(parent) if parent == to)
+                && matches!(method, Method::Copy)
+            {
+                // `from`'s parent is equal to `to` which means we're copying to the same
+                // directory (duplicating files)
+                let to = copy_unique_path(&from, to);
+                Some((from, to))
+            } else if let Some(name) = from.file_name() {
+                let to = to.join(name);
+                Some((from, to))
+            } else {
+                //TODO: how to handle from missing file name?
+                None
+            }
+        })
+        .collect()
+}
+
+// Copy or move items to planned destinations, recording the operation so it can be resumed.
+// Resumed operations must use the same destinations as before, and have nothing left to do if
+// every item was already renamed.
 async fn copy_or_move(
-    paths: Vec<PathBuf>,
-    to: PathBuf,
+    queued: QueuedOperation,
+    mut from_to_pairs: Vec<(PathBuf, PathBuf)>,
     method: Method,
+    completed: BTreeSet<PathBuf>,
+    config: OperationsConfig,
     msg_tx: &Arc<TokioMutex<Sender<Message>>>,
     controller: Controller,
 ) -> Result<OperationSelection, OperationError> {
@@ -275,39 +634,27 @@ async fn copy_or_move(
 
     compio::runtime::spawn(async move {
         log::info!(
-            "{} {:?} to {:?}",
+            "{} {:?}",
             match method {
                 Method::Copy => "Copy",
                 Method::Move { .. } => "Move",
             },
-            paths,
-            to
+            from_to_pairs
         );
 
-        // Handle duplicate file names by renaming paths
-        let mut from_to_pairs: Vec<(PathBuf, PathBuf)> = paths
-            .into_iter()
-            .zip(std::iter::repeat(to.as_path()))
-            .filter_map(|(from, to)| {
-                if matches!(from.parent(), Some(parent) if parent == to)
-                    && matches!(method, Method::Copy)
-                {
-                    // `from`'s parent is equal to `to` which means we're copying to the same
-                    // directory (duplicating files)
-                    let to = copy_unique_path(&from, to);
-                    Some((from, to))
-                } else if let Some(name) = from.file_name() {
-                    let to = to.join(name);
-                    Some((from, to))
-                } else {
-                    //TODO: how to handle from missing file name?
-                    None
-                }
-            })
-            .collect();
+        let mut queue_log = QueueLog::create(&queued);
+        if let Some(queue_log) = &mut queue_log {
+            for (from, to) in from_to_pairs.iter() {
+                queue_log.pair(from, to);
+            }
+            for path in completed.iter() {
+                queue_log.finished(path);
+            }
+        }
 
         // Attempt quick and simple renames
         //TODO: allow rename to be used for directories in recursive context?
+        let mut renamed = OperationSelection::default();
         if matches!(method, Method::Move { .. }) {
             from_to_pairs.retain(|(from, to)| {
                 //TODO: show replace dialog here?
@@ -319,6 +666,12 @@ async fn copy_or_move(
                 match fs::rename(from, to) {
                     Ok(()) => {
                         log::info!("renamed {from:?} to {to:?}");
+                        if let Some(queue_log) = &mut queue_log {
+                            queue_log.renamed(from, to);
+                        }
+                        renamed.ignored.push(from.clone());
+                        renamed.selected.push(to.clone());
+                        renamed.pairs.push((from.clone(), to.clone()));
                         false
                     },
                     Err(err) => {
@@ -329,7 +682,14 @@ async fn copy_or_move(
             });
         }
 
-        let mut context = Context::new(controller.clone());
+        let mut context = Context::new(controller.clone())
+            .preserve(config.preserve_metadata)
+            .special_files(config.copy_special_files)
+            .verify(config.verify_copies)
+            .continue_on_error(config.continue_on_error);
+        context.op_sel = renamed;
+        context.queue_log = queue_log;
+        context.completed = completed;
 
         {
             context = context.on_progress(move |_op, progress| {
@@ -345,7 +705,10 @@ async fn copy_or_move(
                 };
                 let total_progress =
                     (item_progress + progress.current_ops as f32) / progress.total_ops as f32;
-                controller.set_progress(total_progress);
+                // Progress is measured in bytes instead when there are any
+                if controller.details().bytes_total == 0 {
+                    controller.set_progress(total_progress);
+                }
             });
         }
 
@@ -357,19 +720,91 @@ async fn copy_or_move(
             });
         }
 
+        // Large operations are previewed before they start
+        if let Some(preview_size) = config.preview_size {
+            let msg_tx = msg_tx.clone();
+            context = context.on_plan(move |plan| {
+                let msg_tx = msg_tx.clone();
+                Box::pin(async move {
+                    if plan.bytes < preview_size {
+                        return PlanResult::Start(None);
+                    }
+                    handle_plan(msg_tx, plan.clone()).await
+                })
+            });
+        }
+
         context
             .recursive_copy_or_move(from_to_pairs, method)
             .await
             .map_err(OperationError::from_str)?;
 
-        Result::<OperationSelection, OperationError>::Ok(context.op_sel)
+        if !context.verify_failed.is_empty() {
+            return Err(OperationError {
+                kind: OperationErrorType::VerifyFailed {
+                    mismatched: context
+                        .verify_failed
+                        .into_iter()
+                        .map(|(from, to)| FailedPath {
+                            from,
+                            to: Some(to),
+                            error: fl!("verify-mismatch"),
+                        })
+                        .collect(),
+                    failed: context.failed,
+                    op_sel: context.op_sel,
+                },
+            });
+        }
+
+        failed_result(context.failed, context.op_sel)
     })
     .await
     .map_err(wrap_compio_spawn_error)?
-    .map_err(OperationError::from_str)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        fl!(
+            "duration-hours",
+            hours = seconds / 3600,
+            minutes = (seconds % 3600) / 60
+        )
+    } else if seconds >= 60 {
+        fl!(
+            "duration-minutes",
+            minutes = seconds / 60,
+            seconds = seconds % 60
+        )
+    } else {
+        fl!("duration-seconds", seconds = seconds)
+    }
+}
+
+// Copies of items next to themselves, with unique names
+fn duplicate_pairs(paths: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let parent = path.parent()?;
+            Some((path.clone(), copy_unique_path(path, parent)))
+        })
+        .collect()
 }
 
 fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
+    unique_path(from, to, &fl!("copy_noun"))
+}
+
+// Path of an item named after `from` in the directory `to`, numbered with `noun` if the name is
+// already taken
+fn unique_path(from: &Path, to: &Path, noun: &str) -> PathBuf {
+    unique_path_with(from, to, noun, |path| matches!(path.try_exists(), Ok(true)))
+}
+
+// Like `unique_path`, where `taken` decides which paths are already taken
+fn unique_path_with(from: &Path, to: &Path, noun: &str, taken: impl Fn(&Path) -> bool) -> PathBuf {
     // List of compound extensions to check
     const COMPOUND_EXTENSIONS: &[&str] = &[
         ".tar.gz",
@@ -420,14 +855,14 @@ fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
                 file_name.to_string()
             } else {
                 match ext {
-                    Some(ref ext) => format!("{} ({} {}).{}", stem, fl!("copy_noun"), n, ext),
-                    None => format!("{} ({} {})", stem, fl!("copy_noun"), n),
+                    Some(ref ext) => format!("{} ({} {}).{}", stem, noun, n, ext),
+                    None => format!("{} ({} {})", stem, noun, n),
                 }
             };
 
             to = to.join(&new_name);
 
-            if !matches!(to.try_exists(), Ok(true)) {
+            if !taken(&to) {
                 break;
             }
             // Continue if a copy with index exists
@@ -437,6 +872,25 @@ fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
     to
 }
 
+// Path to `target` from inside the directory `base`, both of which must be absolute
+fn relative_path(target: &Path, base: &Path) -> PathBuf {
+    let target_components: Vec<_> = target.components().collect();
+    let base_components: Vec<_> = base.components().collect();
+    let common = target_components
+        .iter()
+        .zip(base_components.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in &target_components[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
 fn file_name(path: &Path) -> Cow<'_, str> {
     path.file_name()
         .map_or_else(|| fl!("unknown-folder").into(), |x| x.to_string_lossy())
@@ -469,21 +923,117 @@ fn paths_parent_name(paths: &[PathBuf]) -> Cow<'_, str> {
     file_name(parent)
 }
 
+// Rename items, which may take the names of each other
+fn batch_rename(renames: &[(PathBuf, PathBuf)], controller: &Controller) -> Result<(), String> {
+    let renames: Vec<_> = renames.iter().filter(|(from, to)| from != to).collect();
+    let sources: BTreeSet<&Path> = renames.iter().map(|(from, _)| from.as_path()).collect();
+
+    // Nothing is renamed unless every name is free or taken by another renamed item
+    let mut targets = BTreeSet::new();
+    for (from, to) in renames.iter() {
+        if !targets.insert(to.as_path()) {
+            return Err(format!("{:?} is the new name of more than one item", to));
+        }
+        if !sources.contains(to.as_path()) && batch_rename::name_taken(from, to) {
+            return Err(format!("{:?} already exists", to));
+        }
+    }
+
+    controller.set_totals(renames.len(), 0);
+    let mut done = Vec::new();
+    let result = batch_rename_all(&renames, &sources, controller, &mut done);
+    if result.is_err() {
+        // Items that were already renamed get their names back, so none are left under a
+        // temporary name
+        for (from, to) in done.iter().rev() {
+            if let Err(err) = fs::rename(to, from) {
+                log::warn!("failed to rename {:?} back to {:?}: {}", to, from, err);
+            }
+        }
+    }
+    result
+}
+
+// Rename every item, recording each rename in `done` so they can be reversed
+fn batch_rename_all(
+    renames: &[&(PathBuf, PathBuf)],
+    sources: &BTreeSet<&Path>,
+    controller: &Controller,
+    done: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), String> {
+    // Items taking the name of another item first move to a temporary name, which also breaks
+    // cycles such as swapped names
+    let mut temporary = Vec::new();
+    for (i, (from, to)) in renames.iter().enumerate() {
+        futures::executor::block_on(controller.check())?;
+        controller.set_current_file(from);
+        if sources.contains(to.as_path()) {
+            let Some(parent) = from.parent() else {
+                return Err(format!("path {:?} has no parent directory", from));
+            };
+            let temp = parent.join(format!(".cosmic-files-rename-{}-{}", std::process::id(), i));
+            fs::rename(from, &temp)
+                .map_err(|err| format!("failed to rename {:?}: {}", from, err))?;
+            done.push((from.clone(), temp.clone()));
+            temporary.push((temp, to));
+        } else {
+            fs::rename(from, to).map_err(|err| format!("failed to rename {:?}: {}", from, err))?;
+            done.push((from.clone(), to.clone()));
+            controller.file_done();
+        }
+    }
+    for (temp, to) in temporary {
+        fs::rename(&temp, to).map_err(|err| format!("failed to rename {:?}: {}", temp, err))?;
+        done.push((temp, to.clone()));
+        controller.file_done();
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct OperationSelection {
     // Paths to ignore if they are already selected
     pub ignored: Vec<PathBuf>,
     // Paths to select
     pub selected: Vec<PathBuf>,
+    // Problems that did not stop the operation from completing
+    pub warnings: Vec<String>,
+    // Top level items that were copied or moved, and where they ended up
+    pub pairs: Vec<(PathBuf, PathBuf)>,
+    // Operations that reverse changes which are only known once they are made
+    pub inverse: Vec<Operation>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LinkKind {
+    Hard,
+    Symbolic,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Operation {
+    /// Add items to an existing archive, rewriting it in its own format
+    AddToArchive {
+        archive: PathBuf,
+        paths: Vec<PathBuf>,
+        password: Option<String>,
+    },
+    /// Rename many items at once, in a way that can be undone as one
+    BatchRename {
+        renames: Vec<(PathBuf, PathBuf)>,
+    },
+    /// Change the mode, owner and group of items, optionally including their contents
+    ChangePermissions {
+        paths: Vec<PathBuf>,
+        changes: PermissionChanges,
+        recursive: bool,
+    },
     /// Compress files
     Compress {
         paths: Vec<PathBuf>,
         to: PathBuf,
         archive_type: ArchiveType,
+        level: CompressionLevel,
         password: Option<String>,
     },
     /// Copy items
@@ -491,6 +1041,10 @@ pub enum Operation {
         paths: Vec<PathBuf>,
         to: PathBuf,
     },
+    /// Copy items next to themselves
+    Duplicate {
+        paths: Vec<PathBuf>,
+    },
     /// Move items to the trash
     Delete {
         paths: Vec<PathBuf>,
@@ -506,6 +1060,21 @@ pub enum Operation {
         paths: Vec<PathBuf>,
         to: PathBuf,
         password: Option<String>,
+        name_encoding: NameEncoding,
+    },
+    /// Extract items from inside an archive, given as paths inside the archive
+    ExtractEntries {
+        archive: PathBuf,
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    /// Create links to items
+    Link {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        kind: LinkKind,
+        /// Point symbolic links at their targets relative to the link
+        relative: bool,
     },
     /// Move items
     Move {
@@ -534,6 +1103,11 @@ pub enum Operation {
     Restore {
         items: Vec<trash::TrashItem>,
     },
+    /// Resume an operation that was interrupted by the application exiting
+    Resume {
+        operation: Box<Operation>,
+        state: ResumeState,
+    },
     /// Set executable and launch
     SetExecutableAndLaunch {
         path: PathBuf,
@@ -543,12 +1117,45 @@ pub enum Operation {
         path: PathBuf,
         mode: u32,
     },
+    /// Overwrite the contents of items before permanently deleting them
+    Shred {
+        paths: Vec<PathBuf>,
+        passes: u8,
+        pattern: ShredPattern,
+    },
+    /// Read through archives to check them for corrupt entries, without extracting anything
+    TestArchive {
+        paths: Vec<PathBuf>,
+        password: Option<String>,
+    },
+}
+
+/// A path that was skipped because of an error, when continuing on errors
+#[derive(Clone, Debug)]
+pub struct FailedPath {
+    pub from: PathBuf,
+    /// Destination of the path, for operations that have one
+    pub to: Option<PathBuf>,
+    pub error: String,
 }
 
 #[derive(Clone, Debug)]
 pub enum OperationErrorType {
     Generic(String),
     PasswordRequired,
+    /// Copied files that do not match their sources, along with paths that failed and the items
+    /// that were copied, which stay selected
+    VerifyFailed {
+        mismatched: Vec<FailedPath>,
+        failed: Vec<FailedPath>,
+        op_sel: OperationSelection,
+    },
+    /// Paths that failed while the rest of the operation continued, along with the items that
+    /// were processed
+    Partial {
+        failed: Vec<FailedPath>,
+        op_sel: OperationSelection,
+    },
 }
 #[derive(Clone, Debug)]
 pub struct OperationError {
@@ -568,19 +1175,100 @@ impl std::fmt::Display for OperationError {
         match &self.kind {
             OperationErrorType::Generic(s) => s.fmt(f),
             OperationErrorType::PasswordRequired => f.write_str("Password required"),
+            OperationErrorType::VerifyFailed {
+                mismatched, failed, ..
+            } => {
+                f.write_str(&fl!("verify-failed", items = mismatched.len()))?;
+                for mismatched in mismatched.iter() {
+                    if let Some(to) = &mismatched.to {
+                        write!(f, "\n{}", to.display())?;
+                    }
+                }
+                if !failed.is_empty() {
+                    write!(f, "\n{}", fl!("operation-partial", items = failed.len()))?;
+                    for failed in failed.iter() {
+                        write!(f, "\n{}: {}", failed.from.display(), failed.error)?;
+                    }
+                }
+                Ok(())
+            }
+            OperationErrorType::Partial { failed, .. } => {
+                f.write_str(&fl!("operation-partial", items = failed.len()))?;
+                for failed in failed.iter() {
+                    write!(f, "\n{}: {}", failed.from.display(), failed.error)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+// Return the paths that failed as an error, if there are any, otherwise the selection
+fn failed_result(
+    failed: Vec<FailedPath>,
+    op_sel: OperationSelection,
+) -> Result<OperationSelection, OperationError> {
+    if failed.is_empty() {
+        Ok(op_sel)
+    } else {
+        Err(OperationError {
+            kind: OperationErrorType::Partial { failed, op_sel },
+        })
+    }
+}
+
 impl Operation {
-    pub fn pending_text(&self, ratio: f32, state: ControllerState) -> String {
-        let percent = (ratio * 100.0) as i32;
-        let progress = || match state {
-            ControllerState::Running => fl!("progress", percent = percent),
-            ControllerState::Paused => fl!("progress-paused", percent = percent),
-            ControllerState::Cancelled => fl!("progress-cancelled", percent = percent),
+    pub fn pending_text(&self, controller: &Controller) -> String {
+        let percent = (controller.progress() * 100.0) as i32;
+        let state = controller.state();
+        let progress = || {
+            let mut parts = vec![match state {
+                ControllerState::Running => fl!("progress", percent = percent),
+                ControllerState::Paused => fl!("progress-paused", percent = percent),
+                ControllerState::Cancelled => fl!("progress-cancelled", percent = percent),
+            }];
+            let details = controller.details();
+            if details.files_total > 1 {
+                parts.push(fl!(
+                    "progress-files",
+                    done = details.files_done,
+                    total = details.files_total
+                ));
+            }
+            if details.bytes_total > 0 {
+                parts.push(fl!(
+                    "progress-bytes",
+                    done = tab::format_size(details.bytes_done),
+                    total = tab::format_size(details.bytes_total)
+                ));
+                if matches!(state, ControllerState::Running) {
+                    if let Some(rate) = details.rate {
+                        parts.push(fl!("progress-rate", rate = tab::format_size(rate as u64)));
+                    }
+                    if let Some(eta) = details.eta() {
+                        parts.push(fl!("progress-eta", eta = format_duration(eta)));
+                    }
+                }
+            }
+            parts.join(", ")
         };
         match self {
+            Self::AddToArchive { archive, paths, .. } => fl!(
+                "adding-to-archive",
+                items = paths.len(),
+                name = file_name(archive),
+                progress = progress()
+            ),
+            Self::BatchRename { renames } => fl!(
+                "renaming-items",
+                items = renames.len(),
+                progress = progress()
+            ),
+            Self::ChangePermissions { paths, .. } => fl!(
+                "changing-permissions",
+                items = paths.len(),
+                progress = progress()
+            ),
             Self::Compress { paths, to, .. } => fl!(
                 "compressing",
                 items = paths.len(),
@@ -605,11 +1293,18 @@ impl Operation {
             Self::DeleteTrash { items } => {
                 fl!("deleting", items = items.len(), progress = progress())
             }
+            Self::Duplicate { paths } => fl!(
+                "duplicating",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                progress = progress()
+            ),
             Self::EmptyTrash => fl!("emptying-trash", progress = progress()),
             Self::Extract {
                 paths,
                 to,
                 password: _,
+                name_encoding: _,
             } => fl!(
                 "extracting",
                 items = paths.len(),
@@ -617,6 +1312,20 @@ impl Operation {
                 to = file_name(to),
                 progress = progress()
             ),
+            Self::ExtractEntries { archive, paths, to } => fl!(
+                "extracting",
+                items = paths.len(),
+                from = file_name(archive),
+                to = file_name(to),
+                progress = progress()
+            ),
+            Self::Link { paths, to, .. } => fl!(
+                "linking",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to),
+                progress = progress()
+            ),
             Self::Move { paths, to, .. } => fl!(
                 "moving",
                 items = paths.len(),
@@ -640,6 +1349,7 @@ impl Operation {
             }
             Self::RemoveFromRecents { paths } => fl!("removing-from-recents", items = paths.len()),
             Self::Restore { items } => fl!("restoring", items = items.len(), progress = progress()),
+            Self::Resume { operation, .. } => operation.pending_text(controller),
             Self::SetExecutableAndLaunch { path } => {
                 fl!("setting-executable-and-launching", name = file_name(path))
             }
@@ -650,11 +1360,26 @@ impl Operation {
                     mode = format!("{:#03o}", mode)
                 )
             }
+            Self::Shred { paths, .. } => {
+                fl!("shredding", items = paths.len(), progress = progress())
+            }
+            Self::TestArchive { paths, .. } => {
+                fl!("testing", items = paths.len(), progress = progress())
+            }
         }
     }
 
     pub fn completed_text(&self) -> String {
         match self {
+            Self::AddToArchive { archive, paths, .. } => fl!(
+                "added-to-archive",
+                items = paths.len(),
+                name = file_name(archive)
+            ),
+            Self::BatchRename { renames } => fl!("renamed-items", items = renames.len()),
+            Self::ChangePermissions { paths, .. } => {
+                fl!("changed-permissions", items = paths.len())
+            }
             Self::Compress { paths, to, .. } => fl!(
                 "compressed",
                 items = paths.len(),
@@ -674,17 +1399,35 @@ impl Operation {
                 to = fl!("trash")
             ),
             Self::DeleteTrash { items } => fl!("deleted", items = items.len()),
+            Self::Duplicate { paths } => fl!(
+                "duplicated",
+                items = paths.len(),
+                from = paths_parent_name(paths)
+            ),
             Self::EmptyTrash => fl!("emptied-trash"),
             Self::Extract {
                 paths,
                 to,
                 password: _,
+                name_encoding: _,
             } => fl!(
                 "extracted",
                 items = paths.len(),
                 from = paths_parent_name(paths),
                 to = file_name(to)
             ),
+            Self::ExtractEntries { archive, paths, to } => fl!(
+                "extracted",
+                items = paths.len(),
+                from = file_name(archive),
+                to = file_name(to)
+            ),
+            Self::Link { paths, to, .. } => fl!(
+                "linked",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to)
+            ),
             Self::Move { paths, to, .. } => fl!(
                 "moved",
                 items = paths.len(),
@@ -705,6 +1448,7 @@ impl Operation {
             Self::RemoveFromRecents { paths } => fl!("removed-from-recents", items = paths.len()),
             Self::Rename { from, to } => fl!("renamed", from = file_name(from), to = file_name(to)),
             Self::Restore { items } => fl!("restored", items = items.len()),
+            Self::Resume { operation, .. } => operation.completed_text(),
             Self::SetExecutableAndLaunch { path } => {
                 fl!("set-executable-and-launched", name = file_name(path))
             }
@@ -715,22 +1459,33 @@ impl Operation {
                     mode = format!("{:#03o}", mode)
                 )
             }
+            Self::Shred { paths, .. } => fl!("shredded", items = paths.len()),
+            Self::TestArchive { paths, .. } => fl!("tested", items = paths.len()),
         }
     }
 
     pub fn show_progress_notification(&self) -> bool {
         // Long running operations show a progress notification
         match self {
-            Self::Compress { .. }
+            Self::AddToArchive { .. }
+            | Self::ChangePermissions { .. }
+            | Self::Compress { .. }
             | Self::Copy { .. }
             | Self::Delete { .. }
             | Self::DeleteTrash { .. }
+            | Self::Duplicate { .. }
             | Self::EmptyTrash
             | Self::Extract { .. }
+            | Self::ExtractEntries { .. }
             | Self::Move { .. }
             | Self::PermanentlyDelete { .. }
-            | Self::Restore { .. } => true,
-            Self::NewFile { .. }
+            | Self::Restore { .. }
+            | Self::Resume { .. }
+            | Self::Shred { .. }
+            | Self::TestArchive { .. } => true,
+            Self::BatchRename { .. }
+            | Self::Link { .. }
+            | Self::NewFile { .. }
             | Self::NewFolder { .. }
             | Self::RemoveFromRecents { .. }
             | Self::Rename { .. }
@@ -739,11 +1494,89 @@ impl Operation {
         }
     }
 
-    pub fn toast(&self) -> Option<String> {
+    /// Operations that retry the paths that failed while this operation continued on errors
+    pub fn retry_failed(&self, failed: &[FailedPath]) -> Vec<Operation> {
         match self {
+            Self::Copy { .. } | Self::Duplicate { .. } | Self::Move { .. } => {
+                // Each path is copied or moved again into the parent of its destination
+                let mut groups: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+                for failed in failed.iter() {
+                    if let Some(to_parent) = failed.to.as_ref().and_then(|to| to.parent()) {
+                        groups
+                            .entry(to_parent.to_path_buf())
+                            .or_default()
+                            .push(failed.from.clone());
+                    }
+                }
+                groups
+                    .into_iter()
+                    .map(|(to, paths)| match self {
+                        Self::Move {
+                            cross_device_copy, ..
+                        } => Self::Move {
+                            paths,
+                            to,
+                            cross_device_copy: *cross_device_copy,
+                        },
+                        _ => Self::Copy { paths, to },
+                    })
+                    .collect()
+            }
+            Self::ChangePermissions {
+                changes, recursive, ..
+            } => vec![Self::ChangePermissions {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+                changes: *changes,
+                recursive: *recursive,
+            }],
+            Self::Delete { .. } => vec![Self::Delete {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+            }],
+            Self::Link {
+                to, kind, relative, ..
+            } => vec![Self::Link {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+                to: to.clone(),
+                kind: *kind,
+                relative: *relative,
+            }],
+            Self::Extract {
+                to,
+                password,
+                name_encoding,
+                ..
+            } => vec![Self::Extract {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+                to: to.clone(),
+                password: password.clone(),
+                name_encoding: *name_encoding,
+            }],
+            Self::PermanentlyDelete { .. } => vec![Self::PermanentlyDelete {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+            }],
+            Self::Resume { operation, .. } => operation.retry_failed(failed),
+            Self::Shred {
+                passes, pattern, ..
+            } => vec![Self::Shred {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+                passes: *passes,
+                pattern: *pattern,
+            }],
+            Self::TestArchive { password, .. } => vec![Self::TestArchive {
+                paths: failed.iter().map(|failed| failed.from.clone()).collect(),
+                password: password.clone(),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn toast(&self) -> Option<String> {
+        match self {
+            Self::AddToArchive { .. } => Some(self.completed_text()),
             Self::Compress { .. } => Some(self.completed_text()),
             Self::Delete { .. } => Some(self.completed_text()),
             Self::Extract { .. } => Some(self.completed_text()),
+            Self::ExtractEntries { .. } => Some(self.completed_text()),
             //TODO: more toasts
             _ => None,
         }
@@ -754,15 +1587,81 @@ impl Operation {
         self,
         msg_tx: &Arc<TokioMutex<Sender<Message>>>,
         controller: Controller,
+        config: OperationsConfig,
     ) -> Result<OperationSelection, OperationError> {
         let controller_clone = controller.clone();
 
         //TODO: IF ERROR, RETURN AN Operation THAT CAN UNDO THE CURRENT STATE
         let paths: Result<OperationSelection, OperationError> = match self {
+            Self::AddToArchive {
+                archive,
+                paths,
+                password,
+            } => {
+                let msg_tx = msg_tx.clone();
+                compio::runtime::spawn_blocking(
+                    move || -> Result<OperationSelection, OperationError> {
+                        append::add_to_archive(
+                            &archive,
+                            &paths,
+                            password.as_deref(),
+                            &msg_tx,
+                            &controller,
+                        )?;
+                        Ok(OperationSelection {
+                            ignored: paths,
+                            selected: vec![archive],
+                            ..Default::default()
+                        })
+                    },
+                )
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
+            Self::BatchRename { renames } => {
+                compio::runtime::spawn_blocking(move || -> Result<_, OperationError> {
+                    batch_rename(&renames, &controller).map_err(OperationError::from_str)?;
+                    Ok(OperationSelection {
+                        ignored: renames.iter().map(|(from, _)| from.clone()).collect(),
+                        selected: renames.into_iter().map(|(_, to)| to).collect(),
+                        ..Default::default()
+                    })
+                })
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
+            Self::ChangePermissions {
+                paths,
+                changes,
+                recursive,
+            } => compio::runtime::spawn_blocking(
+                move || -> Result<OperationSelection, OperationError> {
+                    let mut failed = Vec::new();
+                    let inverse = permissions::change_permissions(
+                        &paths,
+                        changes,
+                        recursive,
+                        &controller,
+                        config.continue_on_error,
+                        &mut failed,
+                    )
+                    .map_err(OperationError::from_str)?;
+                    failed_result(
+                        failed,
+                        OperationSelection {
+                            inverse,
+                            ..Default::default()
+                        },
+                    )
+                },
+            )
+            .await
+            .map_err(wrap_compio_spawn_error)?,
             Self::Compress {
                 paths,
                 to,
                 archive_type,
+                level,
                 password,
             } => {
                 compio::runtime::spawn_blocking(
@@ -777,7 +1676,22 @@ impl Operation {
                         let op_sel = OperationSelection {
                             ignored: paths.clone(),
                             selected: vec![to.clone()],
+                            ..Default::default()
+                        };
+
+                        let mut queue_log = if password.is_none() {
+                            QueueLog::create(&QueuedOperation::Compress {
+                                paths: paths.clone(),
+                                to: to.clone(),
+                                archive_type,
+                                level,
+                            })
+                        } else {
+                            None
                         };
+                        if let Some(queue_log) = &mut queue_log {
+                            queue_log.started(&to);
+                        }
 
                         let mut paths = paths;
                         for path in paths.clone().iter() {
@@ -789,57 +1703,152 @@ impl Operation {
                                 }
                             }
                         }
-
+                        controller.set_totals(
+                            paths.len(),
+                            paths
+                                .iter()
+                                .filter_map(|path| fs::metadata(path).ok())
+                                .filter(|metadata| metadata.is_file())
+                                .map(|metadata| metadata.len())
+                                .sum(),
+                        );
+
+                        let compression_level =
+                            archive_type.compression_level(level).unwrap_or_default();
                         match archive_type {
-                            ArchiveType::Tgz => {
+                            ArchiveType::SevenZ => {
                                 let mut archive = fs::File::create(&to)
                                     .map(io::BufWriter::new)
-                                    .map(|w| {
-                                        flate2::write::GzEncoder::new(
-                                            w,
-                                            flate2::Compression::default(),
-                                        )
-                                    })
-                                    .map(tar::Builder::new)
+                                    .map_err(sevenz_rust::Error::from)
+                                    .and_then(sevenz_rust::SevenZWriter::new)
                                     .map_err(OperationError::from_str)?;
+                                let lzma2 =
+                                    sevenz_rust::lzma::LZMA2Options::with_preset(compression_level)
+                                        .into();
+                                archive.set_content_methods(match &password {
+                                    // Names are encrypted too, as with the -mhe option of 7-Zip
+                                    Some(password) => vec![
+                                        sevenz_rust::AesEncoderOptions::new(
+                                            password.as_str().into(),
+                                        )
+                                        .into(),
+                                        lzma2,
+                                    ],
+                                    None => vec![lzma2],
+                                });
 
-                                let total_paths = paths.len();
-                                for (i, path) in paths.iter().enumerate() {
+                                for path in paths.iter() {
                                     futures::executor::block_on(async {
                                         controller.check().await.map_err(OperationError::from_str)
                                     })?;
 
-                                    controller.set_progress((i as f32) / total_paths as f32);
+                                    controller.set_current_file(path);
 
                                     if let Some(relative_path) = path
                                         .strip_prefix(relative_root)
                                         .map_err(OperationError::from_str)?
                                         .to_str()
                                     {
+                                        let mut entry = sevenz_rust::SevenZArchiveEntry::from_path(
+                                            path,
+                                            relative_path.to_string(),
+                                        );
+                                        if let Some(attributes) =
+                                            crate::archive::sevenz_attributes(path)
+                                        {
+                                            entry.has_windows_attributes = true;
+                                            entry.windows_attributes = attributes;
+                                        }
+                                        let reader_opt = if path.is_file() {
+                                            // Read through OpReader to report progress within
+                                            // large files
+                                            Some(
+                                                OpReader::new(path, controller.clone())
+                                                    .map_err(OperationError::from_str)?,
+                                            )
+                                        } else {
+                                            None
+                                        };
                                         archive
-                                            .append_path_with_name(path, relative_path)
+                                            .push_archive_entry(entry, reader_opt)
                                             .map_err(OperationError::from_str)?;
                                     }
+
+                                    controller.file_done();
                                 }
 
-                                archive.finish().map_err(OperationError::from_str)?;
+                                archive
+                                    .finish()
+                                    .and_then(|mut w| w.flush())
+                                    .map_err(OperationError::from_str)?;
                             }
+                            ArchiveType::Tar => fs::File::create(&to)
+                                .map(io::BufWriter::new)
+                                .map_err(OperationError::from_str)
+                                .and_then(|w| tar_compress(w, &paths, relative_root, &controller))?
+                                .flush()
+                                .map_err(OperationError::from_str)?,
+                            #[cfg(feature = "bzip2")]
+                            ArchiveType::TarBz2 => fs::File::create(&to)
+                                .map(io::BufWriter::new)
+                                .map(|w| {
+                                    bzip2::write::BzEncoder::new(
+                                        w,
+                                        bzip2::Compression::new(compression_level),
+                                    )
+                                })
+                                .map_err(OperationError::from_str)
+                                .and_then(|w| tar_compress(w, &paths, relative_root, &controller))?
+                                .finish()
+                                .and_then(|mut w| w.flush())
+                                .map_err(OperationError::from_str)?,
+                            #[cfg(feature = "xz2")]
+                            ArchiveType::TarXz => fs::File::create(&to)
+                                .map(io::BufWriter::new)
+                                .map(|w| xz2::write::XzEncoder::new(w, compression_level))
+                                .map_err(OperationError::from_str)
+                                .and_then(|w| tar_compress(w, &paths, relative_root, &controller))?
+                                .finish()
+                                .and_then(|mut w| w.flush())
+                                .map_err(OperationError::from_str)?,
+                            #[cfg(feature = "zstd")]
+                            ArchiveType::TarZst => fs::File::create(&to)
+                                .map(io::BufWriter::new)
+                                .and_then(|w| zstd::Encoder::new(w, compression_level as i32))
+                                .map_err(OperationError::from_str)
+                                .and_then(|w| tar_compress(w, &paths, relative_root, &controller))?
+                                .finish()
+                                .and_then(|mut w| w.flush())
+                                .map_err(OperationError::from_str)?,
+                            ArchiveType::Tgz => fs::File::create(&to)
+                                .map(io::BufWriter::new)
+                                .map(|w| {
+                                    flate2::write::GzEncoder::new(
+                                        w,
+                                        flate2::Compression::new(compression_level),
+                                    )
+                                })
+                                .map_err(OperationError::from_str)
+                                .and_then(|w| tar_compress(w, &paths, relative_root, &controller))?
+                                .finish()
+                                .and_then(|mut w| w.flush())
+                                .map_err(OperationError::from_str)?,
                             ArchiveType::Zip => {
                                 let mut archive = fs::File::create(&to)
                                     .map(io::BufWriter::new)
                                     .map(zip::ZipWriter::new)
                                     .map_err(OperationError::from_str)?;
 
-                                let total_paths = paths.len();
                                 let mut buffer = vec![0; 4 * 1024 * 1024];
-                                for (i, path) in paths.iter().enumerate() {
+                                for path in paths.iter() {
                                     futures::executor::block_on(async {
                                         controller.check().await.map_err(OperationError::from_str)
                                     })?;
 
-                                    controller.set_progress((i as f32) / total_paths as f32);
+                                    controller.set_current_file(path);
 
-                                    let mut zip_options = zip::write::SimpleFileOptions::default();
+                                    let mut zip_options = zip::write::SimpleFileOptions::default()
+                                        .compression_level(Some(compression_level.into()));
                                     if password.is_some() {
                                         zip_options = zip_options.with_aes_encryption(
                                             Aes256,
@@ -857,8 +1866,7 @@ impl Operation {
                                             let metadata = file
                                                 .metadata()
                                                 .map_err(OperationError::from_str)?;
-                                            let total = metadata.len();
-                                            if total >= 4 * 1024 * 1024 * 1024 {
+                                            if metadata.len() >= 4 * 1024 * 1024 * 1024 {
                                                 // The large file option must be enabled for files above 4 GiB
                                                 zip_options = zip_options.large_file(true);
                                             }
@@ -871,7 +1879,6 @@ impl Operation {
                                             archive
                                                 .start_file(relative_path, zip_options)
                                                 .map_err(OperationError::from_str)?;
-                                            let mut current = 0;
                                             loop {
                                                 futures::executor::block_on(async {
                                                     controller
@@ -889,12 +1896,7 @@ impl Operation {
                                                 archive
                                                     .write_all(&buffer[..count])
                                                     .map_err(OperationError::from_str)?;
-                                                current += count;
-
-                                                let file_progress = current as f32 / total as f32;
-                                                let total_progress =
-                                                    (i as f32 + file_progress) / total_paths as f32;
-                                                controller.set_progress(total_progress);
+                                                controller.add_bytes(count as u64);
                                             }
                                         } else {
                                             archive
@@ -902,6 +1904,8 @@ impl Operation {
                                                 .map_err(OperationError::from_str)?;
                                         }
                                     }
+
+                                    controller.file_done();
                                 }
 
                                 archive.finish().map_err(OperationError::from_str)?;
@@ -916,10 +1920,36 @@ impl Operation {
                 .map_err(OperationError::from_str)
             }
             Self::Copy { paths, to } => {
-                copy_or_move(paths, to, Method::Copy, msg_tx, controller).await
+                let pairs = copy_or_move_pairs(paths.clone(), &to, Method::Copy);
+                copy_or_move(
+                    QueuedOperation::Copy { paths, to },
+                    pairs,
+                    Method::Copy,
+                    BTreeSet::new(),
+                    config,
+                    msg_tx,
+                    controller,
+                )
+                .await
+            }
+            Self::Duplicate { paths } => {
+                // Items are copied into their own parents with unique names by a single copy, so
+                // progress and conflicts cover the whole selection
+                let pairs = duplicate_pairs(&paths);
+                copy_or_move(
+                    QueuedOperation::Duplicate { paths },
+                    pairs,
+                    Method::Copy,
+                    BTreeSet::new(),
+                    config,
+                    msg_tx,
+                    controller,
+                )
+                .await
             }
             Self::Delete { paths } => {
                 let total = paths.len();
+                let mut failed = Vec::new();
                 for (i, path) in paths.into_iter().enumerate() {
                     futures::executor::block_on(async {
                         controller.check().await.map_err(OperationError::from_str)
@@ -927,13 +1957,25 @@ impl Operation {
 
                     controller.set_progress((i as f32) / (total as f32));
 
-                    let _items_opt = compio::runtime::spawn_blocking(|| trash::delete(path))
+                    let path_clone = path.clone();
+                    match compio::runtime::spawn_blocking(|| trash::delete(path_clone))
                         .await
                         .map_err(wrap_compio_spawn_error)?
-                        .map_err(OperationError::from_str)?;
-                    //TODO: items_opt allows for easy restore
+                    {
+                        //TODO: items_opt allows for easy restore
+                        Ok(_items_opt) => {}
+                        Err(err) if config.continue_on_error => {
+                            log::warn!("failed to delete {:?}: {}", path, err);
+                            failed.push(FailedPath {
+                                from: path,
+                                to: None,
+                                error: err.to_string(),
+                            });
+                        }
+                        Err(err) => return Err(OperationError::from_str(err)),
+                    }
                 }
-                Ok(OperationSelection::default())
+                failed_result(failed, OperationSelection::default())
             }
             Self::DeleteTrash { items } => {
                 #[cfg(any(
@@ -999,110 +2041,239 @@ impl Operation {
                 Ok(OperationSelection::default())
             }
             Self::Extract {
-                paths,
+                mut paths,
                 to,
                 password,
-            } => compio::runtime::spawn_blocking(
-                move || -> Result<OperationSelection, OperationError> {
-                    let total_paths = paths.len();
-                    let mut op_sel = OperationSelection::default();
-                    for (i, path) in paths.iter().enumerate() {
-                        futures::executor::block_on(async {
-                            controller.check().await.map_err(OperationError::from_str)
-                        })?;
-
-                        controller.set_progress((i as f32) / total_paths as f32);
-
-                        if let Some(file_name) = path.file_name().and_then(|f| f.to_str()) {
-                            let dir_name = get_directory_name(file_name);
-                            let mut new_dir = to.join(dir_name);
-
-                            if new_dir.exists() {
-                                if let Some(new_dir_parent) = new_dir.parent() {
-                                    new_dir = copy_unique_path(&new_dir, new_dir_parent);
-                                }
-                            }
+                name_encoding,
+            } => {
+                // Split archives are extracted once, however many volumes are selected
+                crate::volumes::dedup_archives(&mut paths);
+                let sizes: Vec<u64> = paths
+                    .iter()
+                    .map(|path| crate::volumes::size(path))
+                    .collect();
+                controller.set_totals(paths.len(), sizes.iter().sum());
+                let mut bytes_done = 0;
+                let mut op_sel = OperationSelection::default();
+                let mut failed = Vec::new();
+                let mut replace_result_opt = None;
+                let mut queue_log = if password.is_none() {
+                    QueueLog::create(&QueuedOperation::Extract {
+                        paths: paths.clone(),
+                        to: to.clone(),
+                        name_encoding,
+                    })
+                } else {
+                    None
+                };
+                for (i, path) in paths.iter().enumerate() {
+                    controller.check().await.map_err(OperationError::from_str)?;
 
-                            op_sel.ignored.push(path.clone());
-                            op_sel.selected.push(new_dir.clone());
+                    controller.set_current_file(path);
 
-                            let controller = controller.clone();
-                            let mime = mime_for_path(path, None, false);
+                    let archive_path = crate::volumes::archive_path(path);
+                    if let Some(file_name) = archive_path.file_name().and_then(|f| f.to_str()) {
+                        // Whether an archive holds a single folder is only known once it is
+                        // read, so it is extracted to a hidden folder and moved afterwards
+                        let base = copy_unique_path(&to.join(format!(".{}", file_name)), &to);
+                        op_sel.ignored.push(path.clone());
+                        if let Some(queue_log) = &mut queue_log {
+                            queue_log.started(&base);
+                        }
+
+                        let (result, warnings) = {
+                            let path = path.clone();
+                            let base = base.clone();
+                            let archive_size = sizes[i];
                             let password = password.clone();
-                            match mime.essence_str() {
-                                "application/gzip" | "application/x-compressed-tar" => {
-                                    OpReader::new(path, controller)
-                                        .map(io::BufReader::new)
-                                        .map(flate2::read::GzDecoder::new)
-                                        .map(tar::Archive::new)
-                                        .and_then(|mut archive| archive.unpack(&new_dir))
-                                        .map_err(OperationError::from_str)?
-                                }
-                                "application/x-tar" => OpReader::new(path, controller)
-                                    .map(io::BufReader::new)
-                                    .map(tar::Archive::new)
-                                    .and_then(|mut archive| archive.unpack(&new_dir))
-                                    .map_err(OperationError::from_str)?,
-                                "application/zip" => fs::File::open(path)
-                                    .map(io::BufReader::new)
-                                    .map(zip::ZipArchive::new)
-                                    .map_err(OperationError::from_str)?
-                                    .and_then(move |mut archive| {
-                                        zip_extract(&mut archive, &new_dir, controller, password)
-                                    })
-                                    .map_err(|e| match e {
-                                        ZipError::UnsupportedArchive(
-                                            ZipError::PASSWORD_REQUIRED,
-                                        )
-                                        | ZipError::InvalidPassword => OperationError {
-                                            kind: OperationErrorType::PasswordRequired,
-                                        },
-                                        _ => OperationError::from_str(e),
-                                    })?,
-                                #[cfg(feature = "bzip2")]
-                                "application/x-bzip"
-                                | "application/x-bzip-compressed-tar"
-                                | "application/x-bzip2"
-                                | "application/x-bzip2-compressed-tar" => {
-                                    OpReader::new(path, controller)
-                                        .map(io::BufReader::new)
-                                        .map(bzip2::read::BzDecoder::new)
-                                        .map(tar::Archive::new)
-                                        .and_then(|mut archive| archive.unpack(&new_dir))
-                                        .map_err(OperationError::from_str)?
+                            let controller = controller.clone();
+                            compio::runtime::spawn_blocking(move || {
+                                let mut guard = crate::archive::Guard::new(
+                                    &base,
+                                    archive_size,
+                                    config.extract_size_limit,
+                                );
+                                let result = extract_archive(
+                                    &path,
+                                    &base,
+                                    &mut guard,
+                                    password,
+                                    name_encoding,
+                                    config.extract_ownership,
+                                    &controller,
+                                );
+                                (result, guard.finish())
+                            })
+                            .await
+                            .map_err(wrap_compio_spawn_error)?
+                        };
+                        op_sel.warnings.extend(warnings);
+                        let result = match result {
+                            Ok(()) => {
+                                place_extracted(
+                                    msg_tx,
+                                    path,
+                                    &base,
+                                    file_name,
+                                    &to,
+                                    paths.len() > 1,
+                                    &mut replace_result_opt,
+                                )
+                                .await
+                            }
+                            Err(err) => Err(err),
+                        };
+                        if !matches!(result, Ok(ControlFlow::Continue(_))) {
+                            // Hidden folders would be left behind unnoticed
+                            let base = base.clone();
+                            compio::runtime::spawn_blocking(move || {
+                                if let Err(err) = fs::remove_dir_all(&base) {
+                                    log::warn!("failed to remove {:?}: {}", base, err);
                                 }
-                                #[cfg(feature = "xz2")]
-                                "application/x-xz" | "application/x-xz-compressed-tar" => {
-                                    OpReader::new(path, controller)
-                                        .map(io::BufReader::new)
-                                        .map(xz2::read::XzDecoder::new)
-                                        .map(tar::Archive::new)
-                                        .and_then(|mut archive| archive.unpack(&new_dir))
-                                        .map_err(OperationError::from_str)?
+                            })
+                            .await
+                            .map_err(wrap_compio_spawn_error)?;
+                        }
+                        match result {
+                            Ok(ControlFlow::Continue(dest)) => {
+                                op_sel.selected.push(dest.clone());
+                                if let Some(queue_log) = &mut queue_log {
+                                    queue_log.finished(&base);
+                                    queue_log.finished(&dest);
+                                    queue_log.processed(path);
                                 }
-                                _ => Err(OperationError::from_str(format!(
-                                    "unsupported mime type {:?}",
-                                    mime
-                                )))?,
                             }
+                            Ok(ControlFlow::Break(true)) => {}
+                            Ok(ControlFlow::Break(false)) => break,
+                            Err(err)
+                                if config.continue_on_error
+                                    && !controller.is_cancelled()
+                                    && !matches!(
+                                        err.kind,
+                                        OperationErrorType::PasswordRequired
+                                    ) =>
+                            {
+                                log::warn!("failed to extract {:?}: {}", path, err);
+                                failed.push(FailedPath {
+                                    from: path.clone(),
+                                    to: Some(to.clone()),
+                                    error: err.to_string(),
+                                });
+                            }
+                            Err(err) => return Err(err),
                         }
                     }
 
-                    Ok(op_sel)
+                    // Archive headers are not counted while extracting, so catch up here
+                    bytes_done += sizes[i];
+                    controller.set_bytes_done(bytes_done);
+                    controller.file_done();
+                }
+
+                failed_result(failed, op_sel)
+            }
+            Self::ExtractEntries { archive, paths, to } => compio::runtime::spawn_blocking(
+                move || -> Result<OperationSelection, OperationError> {
+                    // Entries keep their names, unless that would replace an existing item
+                    let entries: Vec<(PathBuf, PathBuf)> = paths
+                        .iter()
+                        .filter_map(|path| {
+                            let mut dest = to.join(path.file_name()?);
+                            if dest.exists() {
+                                dest = copy_unique_path(&dest, &to);
+                            }
+                            Some((path.clone(), dest))
+                        })
+                        .collect();
+                    let mut guard = crate::archive::Guard::new(
+                        &to,
+                        fs::metadata(&archive).map_or(0, |metadata| metadata.len()),
+                        config.extract_size_limit,
+                    );
+                    crate::archive::extract(&archive, &entries, &mut guard, &controller)
+                        .map_err(OperationError::from_str)?;
+                    Ok(OperationSelection {
+                        ignored: Vec::new(),
+                        selected: entries.into_iter().map(|(_, dest)| dest).collect(),
+                        warnings: guard.finish(),
+                        ..Default::default()
+                    })
                 },
             )
             .await
-            .map_err(wrap_compio_spawn_error)?
-            .map_err(OperationError::from_str),
+            .map_err(wrap_compio_spawn_error)?,
+            Self::Link {
+                paths,
+                to,
+                kind,
+                relative,
+            } => compio::runtime::spawn_blocking(move || -> Result<_, OperationError> {
+                let mut op_sel = OperationSelection::default();
+                let mut failed = Vec::new();
+                controller.set_totals(paths.len(), 0);
+                for path in paths.iter() {
+                    futures::executor::block_on(controller.check())
+                        .map_err(OperationError::from_str)?;
+                    controller.set_current_file(path);
+
+                    let link = unique_path(path, &to, &fl!("link_noun"));
+                    let result = match kind {
+                        LinkKind::Hard => fs::hard_link(path, &link),
+                        LinkKind::Symbolic => {
+                            let target = if relative {
+                                relative_path(path, &to)
+                            } else {
+                                path.clone()
+                            };
+                            #[cfg(unix)]
+                            {
+                                std::os::unix::fs::symlink(&target, &link)
+                            }
+                            #[cfg(windows)]
+                            {
+                                if path.is_dir() {
+                                    std::os::windows::fs::symlink_dir(&target, &link)
+                                } else {
+                                    std::os::windows::fs::symlink_file(&target, &link)
+                                }
+                            }
+                        }
+                    };
+                    match result {
+                        Ok(()) => op_sel.selected.push(link),
+                        Err(err) => {
+                            // Links that were made are kept, so they can be selected and undone
+                            log::warn!("failed to link {:?} to {:?}: {}", link, path, err);
+                            failed.push(FailedPath {
+                                from: path.clone(),
+                                to: Some(link),
+                                error: err.to_string(),
+                            });
+                        }
+                    }
+                    controller.file_done();
+                }
+                failed_result(failed, op_sel)
+            })
+            .await
+            .map_err(wrap_compio_spawn_error)?,
             Self::Move {
                 paths,
                 to,
                 cross_device_copy,
             } => {
+                let method = Method::Move { cross_device_copy };
+                let pairs = copy_or_move_pairs(paths.clone(), &to, method);
                 copy_or_move(
-                    paths,
-                    to,
-                    Method::Move { cross_device_copy },
+                    QueuedOperation::Move {
+                        paths,
+                        to,
+                        cross_device_copy,
+                    },
+                    pairs,
+                    method,
+                    BTreeSet::new(),
+                    config,
                     msg_tx,
                     controller,
                 )
@@ -1116,6 +2287,7 @@ impl Operation {
                 Result::<_, OperationError>::Ok(OperationSelection {
                     ignored: Vec::new(),
                     selected: vec![path],
+                    ..Default::default()
                 })
             })
             .await
@@ -1129,6 +2301,7 @@ impl Operation {
                 Result::<_, OperationError>::Ok(OperationSelection {
                     ignored: Vec::new(),
                     selected: vec![path],
+                    ..Default::default()
                 })
             })
             .await
@@ -1136,12 +2309,15 @@ impl Operation {
             .map_err(OperationError::from_str),
             Self::PermanentlyDelete { paths } => {
                 let total = paths.len();
+                let mut failed = Vec::new();
                 for (idx, path) in paths.into_iter().enumerate() {
                     controller.check().await.map_err(OperationError::from_str)?;
 
                     controller.set_progress((idx as f32) / (total as f32));
 
-                    tokio::task::spawn_blocking(|| {
+                    let path_clone = path.clone();
+                    let result = tokio::task::spawn_blocking(move || {
+                        let path = path_clone;
                         if path.is_symlink() || path.is_file() {
                             fs::remove_file(path)
                         } else if path.is_dir() {
@@ -1154,11 +2330,22 @@ impl Operation {
                         }
                     })
                     .await
-                    .map_err(OperationError::from_str)?
                     .map_err(OperationError::from_str)?;
+                    match result {
+                        Ok(()) => {}
+                        Err(err) if config.continue_on_error => {
+                            log::warn!("failed to permanently delete {:?}: {}", path, err);
+                            failed.push(FailedPath {
+                                from: path,
+                                to: None,
+                                error: err.to_string(),
+                            });
+                        }
+                        Err(err) => return Err(OperationError::from_str(err)),
+                    }
                 }
 
-                Ok(OperationSelection::default())
+                failed_result(failed, OperationSelection::default())
             }
             Self::RemoveFromRecents { paths } => {
                 tokio::task::spawn_blocking(move || {
@@ -1179,6 +2366,7 @@ impl Operation {
                 Result::<_, OperationError>::Ok(OperationSelection {
                     ignored: vec![from],
                     selected: vec![to],
+                    ..Default::default()
                 })
             })
             .await
@@ -1208,8 +2396,89 @@ impl Operation {
                 Ok(OperationSelection {
                     ignored: Vec::new(),
                     selected: paths,
+                    ..Default::default()
                 })
             }
+            Self::Resume { operation, state } => match *operation {
+                Self::Copy { paths, to } => {
+                    copy_or_move(
+                        QueuedOperation::Copy { paths, to },
+                        state.pairs,
+                        Method::Copy,
+                        state.completed,
+                        config,
+                        msg_tx,
+                        controller,
+                    )
+                    .await
+                }
+                Self::Duplicate { paths } => {
+                    copy_or_move(
+                        QueuedOperation::Duplicate { paths },
+                        state.pairs,
+                        Method::Copy,
+                        state.completed,
+                        config,
+                        msg_tx,
+                        controller,
+                    )
+                    .await
+                }
+                Self::Move {
+                    paths,
+                    to,
+                    cross_device_copy,
+                } => {
+                    copy_or_move(
+                        QueuedOperation::Move {
+                            paths,
+                            to,
+                            cross_device_copy,
+                        },
+                        state.pairs,
+                        Method::Move { cross_device_copy },
+                        state.completed,
+                        config,
+                        msg_tx,
+                        controller,
+                    )
+                    .await
+                }
+                Self::Extract {
+                    paths,
+                    to,
+                    password,
+                    name_encoding,
+                } => {
+                    if !state.partial.is_empty() {
+                        Box::pin(
+                            Self::PermanentlyDelete {
+                                paths: state.partial,
+                            }
+                            .perform(
+                                msg_tx,
+                                controller.clone(),
+                                config,
+                            ),
+                        )
+                        .await?;
+                    }
+                    Box::pin(
+                        Self::Extract {
+                            paths,
+                            to,
+                            password,
+                            name_encoding,
+                        }
+                        .perform(msg_tx, controller, config),
+                    )
+                    .await
+                }
+                operation => Err(OperationError::from_str(format!(
+                    "{:?} cannot be resumed",
+                    operation
+                ))),
+            },
             Self::SetExecutableAndLaunch { path } => {
                 controller.check().await.map_err(OperationError::from_str)?;
 
@@ -1257,6 +2526,98 @@ impl Operation {
                 .map_err(OperationError::from_str)?;
                 Ok(OperationSelection::default())
             }
+            Self::Shred {
+                paths,
+                passes,
+                pattern,
+            } => compio::runtime::spawn_blocking(
+                move || -> Result<OperationSelection, OperationError> {
+                    let mut failed = Vec::new();
+                    shred::shred(
+                        &paths,
+                        passes,
+                        pattern,
+                        &controller,
+                        config.continue_on_error,
+                        &mut failed,
+                    )
+                    .map_err(OperationError::from_str)?;
+                    failed_result(failed, OperationSelection::default())
+                },
+            )
+            .await
+            .map_err(wrap_compio_spawn_error)?,
+            Self::TestArchive { paths, password } => {
+                let msg_tx = msg_tx.clone();
+                compio::runtime::spawn_blocking(
+                    move || -> Result<OperationSelection, OperationError> {
+                        let sizes: Vec<u64> = paths
+                            .iter()
+                            .map(|path| fs::metadata(path).map_or(0, |metadata| metadata.len()))
+                            .collect();
+                        controller.set_totals(paths.len(), sizes.iter().sum());
+                        let mut bytes_done = 0;
+                        let mut op_sel = OperationSelection::default();
+                        let mut failed = Vec::new();
+                        for (i, path) in paths.iter().enumerate() {
+                            futures::executor::block_on(controller.check())
+                                .map_err(OperationError::from_str)?;
+                            controller.set_current_file(path);
+
+                            match crate::archive::test(path, password.as_deref(), &controller) {
+                                Ok(report) => {
+                                    let problems = report.problems();
+                                    if problems > 0 {
+                                        op_sel.warnings.push(fl!(
+                                            "archive-test-problems",
+                                            name = file_name(path),
+                                            problems = problems
+                                        ));
+                                    }
+                                    futures::executor::block_on(async {
+                                        let _ = msg_tx
+                                            .lock()
+                                            .await
+                                            .send(Message::DialogPush(DialogPage::ArchiveTest {
+                                                path: path.clone(),
+                                                report,
+                                            }))
+                                            .await;
+                                    });
+                                }
+                                Err(
+                                    ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)
+                                    | ZipError::InvalidPassword,
+                                ) => {
+                                    return Err(OperationError {
+                                        kind: OperationErrorType::PasswordRequired,
+                                    })
+                                }
+                                Err(err)
+                                    if config.continue_on_error && !controller.is_cancelled() =>
+                                {
+                                    log::warn!("failed to test {:?}: {}", path, err);
+                                    failed.push(FailedPath {
+                                        from: path.clone(),
+                                        to: None,
+                                        error: err.to_string(),
+                                    });
+                                }
+                                Err(err) => return Err(OperationError::from_str(err)),
+                            }
+
+                            // Only the data of entries is counted while testing, so catch up here
+                            bytes_done += sizes[i];
+                            controller.set_bytes_done(bytes_done);
+                            controller.file_done();
+                        }
+
+                        failed_result(failed, op_sel)
+                    },
+                )
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
         };
 
         controller_clone.set_progress(1.0);
@@ -1288,15 +2649,20 @@ mod tests {
     use test_log::test;
     use tokio::sync;
 
-    use super::{Controller, Operation, OperationError, OperationSelection, ReplaceResult};
+    use super::{
+        batch_rename, Controller, LinkKind, Operation, OperationError, OperationErrorType,
+        OperationSelection, PlanResult, ReplaceResult,
+    };
     use crate::{
         app::{
             test_utils::{
                 empty_fs, filter_dirs, filter_files, simple_fs, NAME_LEN, NUM_DIRS, NUM_FILES,
                 NUM_HIDDEN, NUM_NESTED,
             },
-            DialogPage, Message,
+            ArchiveType, CompressionLevel, DialogPage, Message,
         },
+        archive::NameEncoding,
+        config::OperationsConfig,
         fl,
     };
 
@@ -1304,6 +2670,15 @@ mod tests {
     pub async fn operation_copy(
         paths: Vec<PathBuf>,
         to: PathBuf,
+    ) -> Result<OperationSelection, OperationError> {
+        operation_copy_with_config(paths, to, OperationsConfig::default()).await
+    }
+
+    /// Wrapper around `[Operation::Copy]` with non-default operation options
+    pub async fn operation_copy_with_config(
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        config: OperationsConfig,
     ) -> Result<OperationSelection, OperationError> {
         let id = fastrand::u64(0..u64::MAX);
         let (tx, mut rx) = mpsc::channel(1);
@@ -1316,7 +2691,7 @@ mod tests {
                 paths: paths_clone,
                 to: to_clone,
             }
-            .perform(&sync::Mutex::new(tx).into(), Controller::default())
+            .perform(&sync::Mutex::new(tx).into(), Controller::default(), config)
             .await
         };
 
@@ -1329,7 +2704,11 @@ mod tests {
                         tx.send(ReplaceResult::Cancel).await.expect("Sending a response to a replace request should succeed")
 
                     }
-                    _ => unreachable!("Only [ `Message::PendingProgress`, `Message::DialogPush(DialogPage::Replace)` ] are sent from operation"),
+                    Message::DialogPush(DialogPage::ConfirmPlan { tx, .. }) => {
+                        debug!("[{id}] Plan preview");
+                        tx.send(PlanResult::Start(Some(ReplaceResult::KeepBoth))).await.expect("Sending a response to a plan preview should succeed")
+                    }
+                    _ => unreachable!("Only [ `Message::PendingProgress`, `Message::DialogPush(DialogPage::Replace)`, `Message::DialogPush(DialogPage::ConfirmPlan)` ] are sent from operation"),
                 }
             }
         };
@@ -1337,6 +2716,738 @@ mod tests {
         futures::future::join(handle_messages, handle_copy).await.1
     }
 
+    #[test(compio::test)]
+    async fn copy_dir_with_verify() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, 0, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let path = fs.path();
+        let first_dir = filter_dirs(path)?
+            .next()
+            .expect("Should have at least one directory");
+        let to = path.join("verified");
+        fs::create_dir(&to)?;
+
+        let name = first_dir
+            .file_name()
+            .expect("First directory has a valid name");
+        debug!("Copying {} with verification", first_dir.display());
+        operation_copy_with_config(
+            vec![first_dir.clone()],
+            to.clone(),
+            OperationsConfig {
+                verify_copies: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Verified copy should have succeeded");
+
+        for file in filter_files(&first_dir)? {
+            let copied = to
+                .join(name)
+                .join(file.file_name().expect("File has a name"));
+            assert_eq!(
+                fs::read(&file)?,
+                fs::read(&copied)?,
+                "Copy should match source"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn copy_preserves_times() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let from = path.join("from");
+        let to = path.join("to");
+        fs::create_dir(&from)?;
+        fs::create_dir(&to)?;
+
+        let file = from.join("foo.txt");
+        File::create(&file)?;
+        let mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&file, mtime)?;
+
+        operation_copy(vec![from.clone()], to.clone())
+            .await
+            .expect("Copy operation should have succeeded");
+
+        for copied in [to.join("from"), to.join("from").join("foo.txt")] {
+            let metadata = fs::metadata(&copied)?;
+            if copied.is_file() {
+                assert_eq!(
+                    filetime::FileTime::from_last_modification_time(&metadata),
+                    mtime,
+                    "Modification time should be preserved"
+                );
+            } else {
+                assert_eq!(
+                    metadata.modified()?,
+                    fs::metadata(&from)?.modified()?,
+                    "Directory modification time should be preserved"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn compress_all_formats() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, 0, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let path = fs.path();
+        let paths: Vec<PathBuf> = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<_>>()?;
+        let entries = walkdir::WalkDir::new(path).into_iter().count() - 1;
+
+        for archive_type in ArchiveType::all() {
+            for level in CompressionLevel::all() {
+                let to = path.join(format!("archive-{:?}{}", level, archive_type.extension()));
+                let (tx, _rx) = mpsc::channel(1);
+                Operation::Compress {
+                    paths: paths.clone(),
+                    to: to.clone(),
+                    archive_type: *archive_type,
+                    level: *level,
+                    password: None,
+                }
+                .perform(
+                    &sync::Mutex::new(tx).into(),
+                    Controller::default(),
+                    OperationsConfig::default(),
+                )
+                .await
+                .expect("Compress operation should have succeeded");
+
+                // Every file and directory should be in the archive
+                let file = io::BufReader::new(File::open(&to)?);
+                let count = match archive_type {
+                    ArchiveType::SevenZ => {
+                        let len = file.get_ref().metadata()?.len();
+                        sevenz_rust::SevenZReader::new(file, len, sevenz_rust::Password::empty())
+                            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+                            .archive()
+                            .files
+                            .len()
+                    }
+                    ArchiveType::Tar => tar::Archive::new(file).entries()?.count(),
+                    #[cfg(feature = "bzip2")]
+                    ArchiveType::TarBz2 => tar::Archive::new(bzip2::read::BzDecoder::new(file))
+                        .entries()?
+                        .count(),
+                    #[cfg(feature = "xz2")]
+                    ArchiveType::TarXz => tar::Archive::new(xz2::read::XzDecoder::new(file))
+                        .entries()?
+                        .count(),
+                    #[cfg(feature = "zstd")]
+                    ArchiveType::TarZst => tar::Archive::new(zstd::Decoder::with_buffer(file)?)
+                        .entries()?
+                        .count(),
+                    ArchiveType::Tgz => tar::Archive::new(flate2::read::GzDecoder::new(file))
+                        .entries()?
+                        .count(),
+                    ArchiveType::Zip => zip::ZipArchive::new(file)?.len(),
+                };
+                assert_eq!(count, entries, "{:?} archive should have every item", to);
+                fs::remove_file(&to)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn extract_single_folder() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, 0, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let path = fs.path();
+        let dir = filter_dirs(path)?
+            .next()
+            .expect("Should have at least one directory");
+        let name = dir.file_name().expect("Directory has a name").to_owned();
+        let archive = path.join("single.zip");
+        let (tx, _rx) = mpsc::channel(1);
+        Operation::Compress {
+            paths: vec![dir.clone()],
+            to: archive.clone(),
+            archive_type: ArchiveType::Zip,
+            level: CompressionLevel::Default,
+            password: None,
+        }
+        .perform(
+            &sync::Mutex::new(tx).into(),
+            Controller::default(),
+            OperationsConfig::default(),
+        )
+        .await
+        .expect("Compress operation should have succeeded");
+
+        let to = path.join("to");
+        fs::create_dir(&to)?;
+        // The second extraction conflicts with the first, and both are kept. The third replaces
+        // the first, rather than merging with it.
+        let stray = to.join(&name).join("stray.txt");
+        for response in [
+            ReplaceResult::KeepBoth,
+            ReplaceResult::KeepBoth,
+            ReplaceResult::Replace(false),
+        ] {
+            if matches!(response, ReplaceResult::Replace(_)) {
+                fs::write(&stray, "stray")?;
+            }
+            let (tx, mut rx) = mpsc::channel(1);
+            let paths = vec![archive.clone()];
+            let to_clone = to.clone();
+            let handle_extract = async move {
+                Operation::Extract {
+                    paths,
+                    to: to_clone,
+                    password: None,
+                    name_encoding: NameEncoding::Auto,
+                }
+                .perform(
+                    &sync::Mutex::new(tx).into(),
+                    Controller::default(),
+                    OperationsConfig::default(),
+                )
+                .await
+            };
+            let handle_messages = async move {
+                while let Some(msg) = rx.next().await {
+                    if let Message::DialogPush(DialogPage::Replace { tx, .. }) = msg {
+                        tx.send(response)
+                            .await
+                            .expect("Sending a response to a replace request should succeed");
+                    }
+                }
+            };
+            futures::future::join(handle_messages, handle_extract)
+                .await
+                .1
+                .expect("Extract operation should have succeeded");
+        }
+
+        // The folder is not nested inside another folder named after the archive
+        assert!(!to.join(&name).join(&name).exists());
+        assert!(!stray.exists());
+        for file in filter_files(&dir)? {
+            let extracted = to
+                .join(&name)
+                .join(file.file_name().expect("File has a name"));
+            assert_eq!(fs::read(&file)?, fs::read(&extracted)?);
+        }
+        assert_eq!(
+            fs::read_dir(&to)?.count(),
+            2,
+            "Both extracted folders should be kept, without a hidden folder"
+        );
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn extract_legacy_names() -> io::Result<()> {
+        use std::io::Write;
+
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        // Names are written as ASCII placeholders, which are not marked as UTF-8, and then
+        // replaced by Shift-JIS names of the same length
+        let dir = "テスト";
+        let file = "これは文字実験です.txt";
+        let (dir_sjis, _, _) = encoding_rs::SHIFT_JIS.encode(dir);
+        let (file_sjis, _, _) = encoding_rs::SHIFT_JIS.encode(file);
+        let dir_placeholder = "d".repeat(dir_sjis.len());
+        let file_placeholder = "f".repeat(file_sjis.len());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        zip.add_directory(dir_placeholder.as_str(), options)?;
+        zip.start_file(format!("{}/{}", dir_placeholder, file_placeholder), options)?;
+        zip.write_all(b"contents")?;
+        let mut data = zip.finish()?.into_inner();
+        for (placeholder, name) in [
+            (&dir_placeholder, &dir_sjis),
+            (&file_placeholder, &file_sjis),
+        ] {
+            let mut i = 0;
+            while i + placeholder.len() <= data.len() {
+                if &data[i..i + placeholder.len()] == placeholder.as_bytes() {
+                    data[i..i + placeholder.len()].copy_from_slice(name);
+                    i += placeholder.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        let archive = path.join("legacy.zip");
+        fs::write(&archive, data)?;
+
+        // Browsing guesses the same names as extracting
+        let entry = PathBuf::from(dir).join(file);
+        assert!(crate::archive::list(&archive)?
+            .iter()
+            .any(|listed| listed.path == entry));
+        assert_eq!(crate::archive::read(&archive, &entry, 100)?, b"contents");
+
+        let to = path.join("to");
+        fs::create_dir(&to)?;
+        let (tx, _rx) = mpsc::channel(1);
+        Operation::Extract {
+            paths: vec![archive],
+            to: to.clone(),
+            password: None,
+            name_encoding: NameEncoding::Auto,
+        }
+        .perform(
+            &sync::Mutex::new(tx).into(),
+            Controller::default(),
+            OperationsConfig::default(),
+        )
+        .await
+        .expect("Extract operation should have succeeded");
+
+        assert_eq!(fs::read(to.join(dir).join(file))?, b"contents");
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn extract_split_archive() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        let mut contents = vec![0; 5000];
+        fastrand::Rng::with_seed(0).fill(&mut contents);
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "data/file.bin", contents.as_slice())?;
+        let data = tar.into_inner()?.finish()?;
+        let mut volumes = Vec::new();
+        for (i, chunk) in data.chunks(1000).enumerate() {
+            let volume = path.join(format!("data.tar.gz.a{}", char::from(b'a' + i as u8)));
+            fs::write(&volume, chunk)?;
+            volumes.push(volume);
+        }
+        assert!(volumes.len() > 3);
+
+        // Selecting several volumes extracts the archive once
+        let to = path.join("to");
+        fs::create_dir(&to)?;
+        let (tx, _rx) = mpsc::channel(1);
+        let msg_tx = sync::Mutex::new(tx).into();
+        Operation::Extract {
+            paths: volumes.clone(),
+            to: to.clone(),
+            password: None,
+            name_encoding: NameEncoding::Auto,
+        }
+        .perform(&msg_tx, Controller::default(), OperationsConfig::default())
+        .await
+        .expect("Extract operation should have succeeded");
+        assert_eq!(fs::read(to.join("data").join("file.bin"))?, contents);
+        assert_eq!(fs::read_dir(&to)?.count(), 1);
+
+        // Volumes cut at a fixed size that end early point at the next volume
+        for volume in volumes[3..].iter() {
+            fs::remove_file(volume)?;
+        }
+        let to = path.join("truncated");
+        fs::create_dir(&to)?;
+        let err = Operation::Extract {
+            paths: vec![volumes[0].clone()],
+            to,
+            password: None,
+            name_encoding: NameEncoding::Auto,
+        }
+        .perform(&msg_tx, Controller::default(), OperationsConfig::default())
+        .await
+        .expect_err("Extract operation should have failed");
+        assert!(err.to_string().contains("data.tar.gz.ad"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn extract_encrypted_7z() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let dir = path.join("data");
+        fs::create_dir(&dir)?;
+        fs::write(dir.join("file.txt"), "secret contents")?;
+
+        let archive = path.join("data.7z");
+        let (tx, _rx) = mpsc::channel(1);
+        let msg_tx = sync::Mutex::new(tx).into();
+        Operation::Compress {
+            paths: vec![dir],
+            to: archive.clone(),
+            archive_type: ArchiveType::SevenZ,
+            level: CompressionLevel::Default,
+            password: Some("password".to_string()),
+        }
+        .perform(&msg_tx, Controller::default(), OperationsConfig::default())
+        .await
+        .expect("Compress operation should have succeeded");
+
+        // Without the password, the password dialog is shown
+        let to = path.join("to");
+        fs::create_dir(&to)?;
+        let extract = |password: Option<&str>| Operation::Extract {
+            paths: vec![archive.clone()],
+            to: to.clone(),
+            password: password.map(str::to_string),
+            name_encoding: NameEncoding::Auto,
+        };
+        let err = extract(None)
+            .perform(&msg_tx, Controller::default(), OperationsConfig::default())
+            .await
+            .expect_err("Extract operation should need a password");
+        assert!(matches!(err.kind, OperationErrorType::PasswordRequired));
+
+        extract(Some("password"))
+            .perform(&msg_tx, Controller::default(), OperationsConfig::default())
+            .await
+            .expect("Extract operation should have succeeded");
+        assert_eq!(
+            fs::read_to_string(to.join("data").join("file.txt"))?,
+            "secret contents"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test(compio::test)]
+    async fn extract_restores_metadata() -> io::Result<()> {
+        use chrono::{Local, TimeZone};
+        use std::{
+            io::Write,
+            os::unix::fs::PermissionsExt,
+            time::{Duration, SystemTime},
+        };
+
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        let tar_mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let mut tar = tar::Builder::new(File::create(path.join("tools.tar"))?);
+        for (name, entry_type, data) in [
+            ("tools/", tar::EntryType::Directory, &b""[..]),
+            ("tools/run", tar::EntryType::Regular, &b"true"[..]),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_mode(0o755);
+            header.set_mtime(1_500_000_000);
+            header.set_size(data.len() as u64);
+            tar.append_data(&mut header, name, data)?;
+        }
+        tar.into_inner()?;
+
+        // Zip archives store the local time
+        let zip_mtime: SystemTime = Local
+            .with_ymd_and_hms(2017, 7, 14, 2, 40, 0)
+            .single()
+            .expect("Time should be valid")
+            .into();
+        let options = zip::write::SimpleFileOptions::default()
+            .last_modified_time(
+                zip::DateTime::from_date_and_time(2017, 7, 14, 2, 40, 0)
+                    .expect("Time should be valid"),
+            )
+            .unix_permissions(0o755);
+        let mut zip = zip::ZipWriter::new(File::create(path.join("tools.zip"))?);
+        zip.add_directory("tools/", options)?;
+        zip.start_file("tools/run", options)?;
+        zip.write_all(b"true")?;
+        zip.finish()?;
+
+        for (archive, mtime) in [("tools.tar", tar_mtime), ("tools.zip", zip_mtime)] {
+            let to = path.join(format!("{}-extracted", archive));
+            fs::create_dir(&to)?;
+            let (tx, _rx) = mpsc::channel(1);
+            Operation::Extract {
+                paths: vec![path.join(archive)],
+                to: to.clone(),
+                password: None,
+                name_encoding: NameEncoding::Auto,
+            }
+            .perform(
+                &sync::Mutex::new(tx).into(),
+                Controller::default(),
+                OperationsConfig::default(),
+            )
+            .await
+            .expect("Extract operation should have succeeded");
+
+            for extracted in [to.join("tools"), to.join("tools").join("run")] {
+                let metadata = fs::metadata(&extracted)?;
+                assert_eq!(
+                    metadata.permissions().mode() & 0o777,
+                    0o755,
+                    "{:?} should have its stored mode",
+                    extracted
+                );
+                assert_eq!(
+                    metadata.modified()?,
+                    mtime,
+                    "{:?} should have its stored time",
+                    extracted
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn duplicate_items() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, 0, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let path = fs.path();
+        let file = filter_files(path)?
+            .next()
+            .expect("Should have at least one file");
+        let dir = filter_dirs(path)?
+            .next()
+            .expect("Should have at least one directory");
+        let other_dir = filter_dirs(path)?
+            .nth(1)
+            .expect("Should have at least two directories");
+        let nested = filter_files(&other_dir)?
+            .next()
+            .expect("Should have at least one nested file");
+
+        // Items in different folders are duplicated by one copy
+        let (tx, _rx) = mpsc::channel(1);
+        let controller = Controller::default();
+        let op_sel = Operation::Duplicate {
+            paths: vec![file.clone(), dir.clone(), nested.clone()],
+        }
+        .perform(
+            &sync::Mutex::new(tx).into(),
+            controller.clone(),
+            OperationsConfig::default(),
+        )
+        .await
+        .expect("Duplicate operation should have succeeded");
+        let details = controller.details();
+        assert_eq!(details.files_done, details.files_total);
+
+        // The copies are next to the originals and selected afterwards
+        assert_eq!(op_sel.selected.len(), 3);
+        for (original, copy) in [file, dir, nested].iter().zip(op_sel.selected.iter()) {
+            assert_eq!(copy.parent(), original.parent());
+            assert_ne!(original, copy);
+            assert!(copy
+                .file_name()
+                .expect("Copy has a name")
+                .to_string_lossy()
+                .contains(&fl!("copy_noun")));
+        }
+        assert_eq!(
+            fs::read_dir(&op_sel.selected[1])?.count(),
+            fs::read_dir(&dir)?.count(),
+            "Directory contents should be copied"
+        );
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn link_items() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let from = path.join("from");
+        let to = path.join("to");
+        fs::create_dir(&from)?;
+        fs::create_dir(&to)?;
+        let file = from.join("foo.txt");
+        fs::write(&file, "foo")?;
+
+        for kind in [LinkKind::Symbolic, LinkKind::Hard] {
+            let (tx, _rx) = mpsc::channel(1);
+            Operation::Link {
+                paths: vec![file.clone()],
+                to: to.clone(),
+                kind,
+                relative: true,
+            }
+            .perform(
+                &sync::Mutex::new(tx).into(),
+                Controller::default(),
+                OperationsConfig::default(),
+            )
+            .await
+            .expect("Link operation should have succeeded");
+        }
+
+        // The second link is numbered because the first one took the name
+        let symlink = to.join("foo.txt");
+        assert_eq!(fs::read_link(&symlink)?, PathBuf::from("../from/foo.txt"));
+        assert_eq!(fs::read_to_string(&symlink)?, "foo");
+        let hard_link = to.join(format!("foo ({} 1).txt", fl!("link_noun")));
+        assert!(!hard_link.is_symlink());
+        fs::write(&file, "bar")?;
+        assert_eq!(fs::read_to_string(&hard_link)?, "bar");
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn copy_with_plan_preview() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let from = path.join("from");
+        let to = path.join("to");
+        fs::create_dir(&from)?;
+        fs::create_dir(&to)?;
+        fs::write(from.join("foo.txt"), "new")?;
+        fs::write(to.join("foo.txt"), "old")?;
+
+        // The preview resolves the conflict by keeping both files
+        operation_copy_with_config(
+            vec![from.join("foo.txt")],
+            to.clone(),
+            OperationsConfig {
+                preview_size: Some(0),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Copy operation should have succeeded");
+
+        assert_eq!(fs::read_to_string(to.join("foo.txt"))?, "old");
+        assert_eq!(fs::read_dir(&to)?.count(), 2, "Both files should be kept");
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn copy_continues_on_error() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, NUM_HIDDEN, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let path = fs.path();
+        let to = path.join("to");
+        fs::create_dir(&to)?;
+
+        let file = filter_files(path)
+            .next()
+            .expect("Should have at least one file");
+        let missing = path.join("missing");
+        let err = operation_copy_with_config(
+            vec![missing.clone(), file.clone()],
+            to.clone(),
+            OperationsConfig {
+                continue_on_error: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect_err("Copy operation should report the missing path");
+
+        match err.kind {
+            OperationErrorType::Partial { failed, op_sel } => {
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].from, missing);
+                assert_eq!(failed[0].to.as_deref(), Some(to.join("missing").as_path()));
+                // The copied path is still reported, so it can be selected and undone
+                assert_eq!(op_sel.selected, vec![to.join(file.file_name().unwrap())]);
+            }
+            kind => panic!("Expected a partial failure, got {:?}", kind),
+        }
+        assert!(
+            to.join(file.file_name().unwrap()).is_file(),
+            "Other paths should still be copied"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test(compio::test)]
+    async fn copy_recreates_fifos() -> io::Result<()> {
+        use std::{ffi::CString, os::unix::fs::FileTypeExt};
+
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let from = path.join("from");
+        let to = path.join("to");
+        fs::create_dir(&from)?;
+        fs::create_dir(&to)?;
+        File::create(from.join("foo.txt"))?;
+
+        let fifo = CString::new(from.join("fifo").into_os_string().into_encoded_bytes())?;
+        if unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        operation_copy(vec![from.clone()], to.clone())
+            .await
+            .expect("Copy operation should have succeeded");
+
+        let copied = to.join("from");
+        assert!(copied.join("foo.txt").is_file());
+        assert!(
+            fs::symlink_metadata(copied.join("fifo"))?
+                .file_type()
+                .is_fifo(),
+            "FIFO should be recreated"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test(compio::test)]
+    async fn copy_keeps_sparse_holes() -> io::Result<()> {
+        use std::{io::Write, os::unix::fs::MetadataExt};
+
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let from = path.join("sparse.img");
+        let to = path.join("copies");
+        fs::create_dir(&to)?;
+
+        // A little data followed by a large hole
+        let mut file = File::create(&from)?;
+        file.set_len(64 * 1024 * 1024)?;
+        file.write_all(b"data")?;
+        drop(file);
+
+        operation_copy(vec![from.clone()], to.clone())
+            .await
+            .expect("Copy operation should have succeeded");
+
+        let copied = to.join("sparse.img");
+        let (from_metadata, to_metadata) = (fs::metadata(&from)?, fs::metadata(&copied)?);
+        assert_eq!(
+            from_metadata.len(),
+            to_metadata.len(),
+            "Length should match"
+        );
+        assert_eq!(
+            fs::read(&from)?,
+            fs::read(&copied)?,
+            "Contents should match"
+        );
+        // Only check holes when the filesystem supports them
+        if from_metadata.blocks() * 512 < from_metadata.len() {
+            assert!(
+                to_metadata.blocks() * 512 < to_metadata.len(),
+                "Copy should keep holes"
+            );
+        }
+
+        Ok(())
+    }
+
     #[test(compio::test)]
     async fn copy_file_to_same_location() -> io::Result<()> {
         let fs = simple_fs(NUM_FILES, 0, 1, 0, NAME_LEN)?;
@@ -1507,4 +3618,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn batch_rename_rolls_back() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        for name in ["a", "b", "c"] {
+            fs::write(path.join(name), name)?;
+        }
+
+        // Swapped names go through temporary names, and the last rename fails
+        let err = batch_rename(
+            &[
+                (path.join("a"), path.join("b")),
+                (path.join("b"), path.join("a")),
+                (path.join("c"), path.join("missing").join("c")),
+            ],
+            &Controller::default(),
+        )
+        .expect_err("Renaming into a missing folder should fail");
+        debug!("{}", err);
+
+        // Every item has its original name again
+        for name in ["a", "b", "c"] {
+            assert_eq!(fs::read_to_string(path.join(name))?, name);
+        }
+        assert_eq!(fs::read_dir(path)?.count(), 3);
+
+        Ok(())
+    }
 }
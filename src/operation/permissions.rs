@@ -0,0 +1,364 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+use super::{Controller, FailedPath, Operation};
+use crate::fl;
+
+/// Changes applied to each item by [`super::Operation::ChangePermissions`]. Fields that are
+/// `None` are left unchanged.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PermissionChanges {
+    pub file_mode: Option<u32>,
+    pub dir_mode: Option<u32>,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+}
+
+impl PermissionChanges {
+    /// Parse octal modes and user and group names, where empty values are left unchanged
+    pub fn parse(
+        file_mode: &str,
+        dir_mode: &str,
+        owner: &str,
+        group: &str,
+    ) -> Result<Self, String> {
+        let parse_mode = |mode: &str| -> Result<Option<u32>, String> {
+            let mode = mode.trim();
+            if mode.is_empty() {
+                return Ok(None);
+            }
+            match u32::from_str_radix(mode, 8) {
+                Ok(bits) if bits <= 0o7777 => Ok(Some(bits)),
+                _ => Err(fl!("permissions-invalid-mode", mode = mode)),
+            }
+        };
+        let owner = owner.trim();
+        let group = group.trim();
+        Ok(Self {
+            file_mode: parse_mode(file_mode)?,
+            dir_mode: parse_mode(dir_mode)?,
+            owner: if owner.is_empty() {
+                None
+            } else {
+                Some(
+                    uzers::get_user_by_name(owner)
+                        .ok_or_else(|| fl!("permissions-unknown-owner", name = owner))?
+                        .uid(),
+                )
+            },
+            group: if group.is_empty() {
+                None
+            } else {
+                Some(
+                    uzers::get_group_by_name(group)
+                        .ok_or_else(|| fl!("permissions-unknown-group", name = group))?
+                        .gid(),
+                )
+            },
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Change the mode, owner and group of paths, including their contents if `recursive` is set.
+/// Errors are collected in `failed` instead of stopping if `continue_on_error` is set. Returns
+/// the operations that give changed items back their previous modes, owners and groups.
+pub fn change_permissions(
+    paths: &[PathBuf],
+    changes: PermissionChanges,
+    recursive: bool,
+    controller: &Controller,
+    continue_on_error: bool,
+    failed: &mut Vec<FailedPath>,
+) -> Result<Vec<Operation>, String> {
+    // Directories are changed after their contents, as a mode without access to a directory
+    // would stop its contents from being changed
+    let mut entries = Vec::new();
+    for path in paths.iter() {
+        let walker = WalkDir::new(path)
+            .max_depth(if recursive { usize::MAX } else { 0 })
+            .contents_first(true);
+        for entry in walker {
+            check(controller)?;
+            match entry {
+                Ok(entry) => entries.push(entry.into_path()),
+                Err(err) if continue_on_error => {
+                    failed.push(FailedPath {
+                        from: err.path().unwrap_or(path).to_path_buf(),
+                        to: None,
+                        error: err.to_string(),
+                    });
+                }
+                Err(err) => return Err(format!("failed to walk directory {:?}: {}", path, err)),
+            }
+        }
+    }
+
+    controller.set_totals(entries.len(), 0);
+    // Items that had the same state are restored together
+    let mut restore = BTreeMap::<PermissionChanges, Vec<PathBuf>>::new();
+    for path in entries.iter() {
+        check(controller)?;
+        controller.set_current_file(path);
+        match change(path, changes) {
+            Ok(previous) => {
+                if !previous.is_empty() {
+                    restore.entry(previous).or_default().push(path.clone());
+                }
+                controller.file_done();
+            }
+            Err(err) if continue_on_error && !controller.is_cancelled() => {
+                log::warn!("failed to change permissions of {:?}: {}", path, err);
+                failed.push(FailedPath {
+                    from: path.clone(),
+                    to: None,
+                    error: err.to_string(),
+                });
+            }
+            Err(err) => {
+                return Err(format!(
+                    "failed to change permissions of {:?}: {}",
+                    path, err
+                ))
+            }
+        }
+    }
+    Ok(restore
+        .into_iter()
+        .map(|(changes, paths)| Operation::ChangePermissions {
+            paths,
+            changes,
+            recursive: false,
+        })
+        .collect())
+}
+
+fn check(controller: &Controller) -> Result<(), String> {
+    futures::executor::block_on(controller.check())
+}
+
+// Returns the changes that restore the previous state of the item
+#[cfg(unix)]
+fn change(path: &Path, changes: PermissionChanges) -> io::Result<PermissionChanges> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata = path.symlink_metadata()?;
+    let mut previous = PermissionChanges::default();
+    if changes.owner.is_some() || changes.group.is_some() {
+        // Change the owner of links themselves instead of their targets
+        std::os::unix::fs::lchown(path, changes.owner, changes.group)?;
+        previous.owner = changes.owner.map(|_| metadata.uid());
+        previous.group = changes.group.map(|_| metadata.gid());
+    }
+    // Links have no mode of their own and setting it would change their targets
+    let mode = if metadata.is_dir() {
+        changes.dir_mode
+    } else if metadata.is_file() {
+        changes.file_mode
+    } else {
+        None
+    };
+    if let Some(mode) = mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        let mode = Some(metadata.mode() & 0o7777);
+        if metadata.is_dir() {
+            previous.dir_mode = mode;
+        } else {
+            previous.file_mode = mode;
+        }
+    }
+    Ok(previous)
+}
+
+#[cfg(not(unix))]
+fn change(_path: &Path, _changes: PermissionChanges) -> io::Result<PermissionChanges> {
+    //TODO: what to do on non-Unix systems?
+    Ok(PermissionChanges::default())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, io, os::unix::fs::PermissionsExt};
+
+    use super::{change_permissions, PermissionChanges};
+    use crate::{
+        app::test_utils::{
+            empty_fs, simple_fs, NAME_LEN, NUM_DIRS, NUM_FILES, NUM_HIDDEN, NUM_NESTED,
+        },
+        operation::{Controller, Operation},
+    };
+
+    #[test]
+    fn recursive_modes() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, NUM_HIDDEN, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let controller = Controller::default();
+        let mut failed = Vec::new();
+        change_permissions(
+            &[fs.path().to_path_buf()],
+            PermissionChanges {
+                file_mode: Some(0o600),
+                dir_mode: Some(0o750),
+                ..Default::default()
+            },
+            true,
+            &controller,
+            false,
+            &mut failed,
+        )
+        .expect("Changing permissions should have succeeded");
+
+        assert!(failed.is_empty());
+        for entry in walkdir::WalkDir::new(fs.path()) {
+            let entry = entry?;
+            let mode = entry.metadata()?.permissions().mode() & 0o7777;
+            if entry.file_type().is_dir() {
+                assert_eq!(
+                    mode,
+                    0o750,
+                    "{:?} should have the folder mode",
+                    entry.path()
+                );
+            } else {
+                assert_eq!(mode, 0o600, "{:?} should have the file mode", entry.path());
+            }
+        }
+        let details = controller.details();
+        assert_eq!(details.files_done, details.files_total);
+
+        // Without recursion only the selected item changes
+        let file = fs.path().join("single");
+        fs::write(&file, "")?;
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644))?;
+        change_permissions(
+            &[fs.path().to_path_buf()],
+            PermissionChanges {
+                file_mode: Some(0o600),
+                dir_mode: Some(0o700),
+                ..Default::default()
+            },
+            false,
+            &controller,
+            false,
+            &mut failed,
+        )
+        .expect("Changing permissions should have succeeded");
+        assert_eq!(
+            fs::metadata(fs.path())?.permissions().mode() & 0o7777,
+            0o700
+        );
+        assert_eq!(fs::metadata(&file)?.permissions().mode() & 0o7777, 0o644);
+
+        Ok(())
+    }
+
+    #[test]
+    fn folder_mode_without_access() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let dir = fs.path().join("dir");
+        let sub = dir.join("sub");
+        fs::create_dir_all(&sub)?;
+        fs::write(sub.join("file"), "")?;
+
+        // Folders lose access last, after everything inside has changed
+        let controller = Controller::default();
+        let mut failed = Vec::new();
+        change_permissions(
+            &[dir.clone()],
+            PermissionChanges {
+                file_mode: Some(0o600),
+                dir_mode: Some(0o600),
+                ..Default::default()
+            },
+            true,
+            &controller,
+            false,
+            &mut failed,
+        )
+        .expect("Changing permissions should have succeeded");
+        assert!(failed.is_empty());
+        assert_eq!(fs::metadata(&dir)?.permissions().mode() & 0o7777, 0o600);
+
+        // Give access back so the contents can be checked and removed
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+        assert_eq!(fs::metadata(&sub)?.permissions().mode() & 0o7777, 0o600);
+        fs::set_permissions(&sub, fs::Permissions::from_mode(0o700))?;
+        assert_eq!(
+            fs::metadata(sub.join("file"))?.permissions().mode() & 0o7777,
+            0o600
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn restore_previous_modes() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let dir = fs.path().join("dir");
+        fs::create_dir(&dir)?;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755))?;
+        for (name, mode) in [("a", 0o644), ("b", 0o600), ("c", 0o644)] {
+            let file = dir.join(name);
+            fs::write(&file, "")?;
+            fs::set_permissions(&file, fs::Permissions::from_mode(mode))?;
+        }
+
+        let controller = Controller::default();
+        let mut failed = Vec::new();
+        let inverse = change_permissions(
+            &[dir.clone()],
+            PermissionChanges {
+                file_mode: Some(0o640),
+                dir_mode: Some(0o700),
+                ..Default::default()
+            },
+            true,
+            &controller,
+            false,
+            &mut failed,
+        )
+        .expect("Changing permissions should have succeeded");
+
+        // Items that had the same mode are restored by one operation
+        assert_eq!(inverse.len(), 3);
+        for operation in inverse {
+            let Operation::ChangePermissions {
+                paths,
+                changes,
+                recursive,
+            } = operation
+            else {
+                panic!("Inverse should change permissions");
+            };
+            change_permissions(&paths, changes, recursive, &controller, false, &mut failed)
+                .expect("Restoring permissions should have succeeded");
+        }
+        let mode =
+            |path| -> io::Result<u32> { Ok(fs::metadata(path)?.permissions().mode() & 0o7777) };
+        assert_eq!(mode(dir.clone())?, 0o755);
+        assert_eq!(mode(dir.join("a"))?, 0o644);
+        assert_eq!(mode(dir.join("b"))?, 0o600);
+        assert_eq!(mode(dir.join("c"))?, 0o644);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_changes() {
+        let changes =
+            PermissionChanges::parse("644", " 0755 ", "", "").expect("Modes should be valid");
+        assert_eq!(changes.file_mode, Some(0o644));
+        assert_eq!(changes.dir_mode, Some(0o755));
+        assert!(PermissionChanges::parse("", "", "", "")
+            .expect("Empty values should be valid")
+            .is_empty());
+        assert!(PermissionChanges::parse("789", "", "", "").is_err());
+        assert!(PermissionChanges::parse("17777", "", "", "").is_err());
+    }
+}
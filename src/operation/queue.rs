@@ -0,0 +1,653 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{file_name, paths_parent_name, Operation};
+use crate::{
+    app::{ArchiveType, CompressionLevel},
+    archive::NameEncoding,
+    fl,
+};
+
+static NEXT_RECORD: AtomicU64 = AtomicU64::new(0);
+
+fn queue_dir() -> Option<PathBuf> {
+    Some(dirs::state_dir()?.join("cosmic-files").join("operations"))
+}
+
+// Operations record a process id in their file name so that records of running instances are
+// not treated as interrupted
+fn record_pid(path: &Path) -> Option<u32> {
+    path.file_stem()?.to_str()?.split('-').next()?.parse().ok()
+}
+
+fn process_running(pid: u32) -> bool {
+    if pid == process::id() {
+        return true;
+    }
+
+    if cfg!(target_os = "linux") {
+        // Process ids are reused, so also check that the process is another instance
+        match (
+            fs::read_link(format!("/proc/{}/exe", pid)),
+            std::env::current_exe(),
+        ) {
+            (Ok(exe), Ok(current_exe)) => exe == current_exe,
+            _ => false,
+        }
+    } else {
+        false
+    }
+}
+
+/// Operation that can be written to disk, resumed, and rolled back
+///
+/// Operations using a password are never written to disk.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum QueuedOperation {
+    Compress {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        archive_type: ArchiveType,
+        #[serde(default)]
+        level: CompressionLevel,
+    },
+    Copy {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    /// Copies next to the items themselves, whose destinations are recorded as pairs
+    Duplicate {
+        paths: Vec<PathBuf>,
+    },
+    Extract {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        #[serde(default)]
+        name_encoding: NameEncoding,
+    },
+    Move {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        cross_device_copy: bool,
+    },
+}
+
+impl QueuedOperation {
+    pub fn from_operation(operation: &Operation) -> Option<Self> {
+        match operation {
+            Operation::Compress {
+                paths,
+                to,
+                archive_type,
+                level,
+                password: None,
+            } => Some(Self::Compress {
+                paths: paths.clone(),
+                to: to.clone(),
+                archive_type: *archive_type,
+                level: *level,
+            }),
+            Operation::Copy { paths, to } => Some(Self::Copy {
+                paths: paths.clone(),
+                to: to.clone(),
+            }),
+            Operation::Duplicate { paths } => Some(Self::Duplicate {
+                paths: paths.clone(),
+            }),
+            Operation::Extract {
+                paths,
+                to,
+                password: None,
+                name_encoding,
+            } => Some(Self::Extract {
+                paths: paths.clone(),
+                to: to.clone(),
+                name_encoding: *name_encoding,
+            }),
+            Operation::Move {
+                paths,
+                to,
+                cross_device_copy,
+            } => Some(Self::Move {
+                paths: paths.clone(),
+                to: to.clone(),
+                cross_device_copy: *cross_device_copy,
+            }),
+            Operation::Resume { operation, .. } => Self::from_operation(operation),
+            _ => None,
+        }
+    }
+
+    pub fn operation(&self) -> Operation {
+        match self.clone() {
+            Self::Compress {
+                paths,
+                to,
+                archive_type,
+                level,
+            } => Operation::Compress {
+                paths,
+                to,
+                archive_type,
+                level,
+                password: None,
+            },
+            Self::Copy { paths, to } => Operation::Copy { paths, to },
+            Self::Duplicate { paths } => Operation::Duplicate { paths },
+            Self::Extract {
+                paths,
+                to,
+                name_encoding,
+            } => Operation::Extract {
+                paths,
+                to,
+                password: None,
+                name_encoding,
+            },
+            Self::Move {
+                paths,
+                to,
+                cross_device_copy,
+            } => Operation::Move {
+                paths,
+                to,
+                cross_device_copy,
+            },
+        }
+    }
+
+    pub fn interrupted_text(&self) -> String {
+        match self {
+            Self::Compress { paths, to, .. } => fl!(
+                "interrupted-compressing",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to)
+            ),
+            Self::Copy { paths, to } => fl!(
+                "interrupted-copying",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to)
+            ),
+            Self::Duplicate { paths } => fl!(
+                "interrupted-duplicating",
+                items = paths.len(),
+                from = paths_parent_name(paths)
+            ),
+            Self::Extract { paths, to, .. } => fl!(
+                "interrupted-extracting",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to)
+            ),
+            Self::Move { paths, to, .. } => fl!(
+                "interrupted-moving",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to)
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Header {
+    operation: QueuedOperation,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+enum Event {
+    /// Top level source and destination of a copy or move
+    Pair(PathBuf, PathBuf),
+    /// Destination that did not exist is being written
+    Started(PathBuf),
+    /// Destination has been completely written
+    Finished(PathBuf),
+    /// Source has been moved with a single rename
+    Renamed(PathBuf, PathBuf),
+    /// Source has been completely processed
+    Processed(PathBuf),
+    /// Sources of a move are being removed
+    Cleanup,
+}
+
+/// State needed to continue an interrupted operation where it stopped
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ResumeState {
+    pub pairs: Vec<(PathBuf, PathBuf)>,
+    pub completed: BTreeSet<PathBuf>,
+    /// Partially extracted items, removed before extracting again
+    pub partial: Vec<PathBuf>,
+}
+
+/// Append-only record of a running operation
+///
+/// The record is removed when dropped, so it only remains on disk if the process exits before
+/// the operation finishes.
+#[derive(Debug)]
+pub struct QueueLog {
+    path: PathBuf,
+    file: fs::File,
+    cleanup: bool,
+}
+
+impl QueueLog {
+    pub fn create(operation: &QueuedOperation) -> Option<Self> {
+        let dir = queue_dir()?;
+        let path = dir.join(format!(
+            "{}-{}.jsonl",
+            process::id(),
+            NEXT_RECORD.fetch_add(1, Ordering::Relaxed)
+        ));
+        let res = fs::create_dir_all(&dir)
+            .and_then(|()| fs::File::create(&path))
+            .and_then(|file| {
+                let mut log = Self {
+                    path: path.clone(),
+                    file,
+                    cleanup: false,
+                };
+                log.write(&Header {
+                    operation: operation.clone(),
+                })?;
+                Ok(log)
+            });
+        match res {
+            Ok(log) => Some(log),
+            Err(err) => {
+                log::warn!("failed to create operation record {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    fn write<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+
+    fn event(&mut self, event: Event) {
+        if let Err(err) = self.write(&event) {
+            log::warn!("failed to write operation record {:?}: {}", self.path, err);
+        }
+    }
+
+    pub fn pair(&mut self, from: &Path, to: &Path) {
+        self.event(Event::Pair(from.to_path_buf(), to.to_path_buf()));
+    }
+
+    pub fn started(&mut self, path: &Path) {
+        self.event(Event::Started(path.to_path_buf()));
+    }
+
+    pub fn finished(&mut self, path: &Path) {
+        self.event(Event::Finished(path.to_path_buf()));
+    }
+
+    pub fn renamed(&mut self, from: &Path, to: &Path) {
+        self.event(Event::Renamed(from.to_path_buf(), to.to_path_buf()));
+    }
+
+    pub fn processed(&mut self, path: &Path) {
+        self.event(Event::Processed(path.to_path_buf()));
+    }
+
+    pub fn cleanup(&mut self) {
+        if !self.cleanup {
+            self.cleanup = true;
+            self.event(Event::Cleanup);
+        }
+    }
+}
+
+impl Drop for QueueLog {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            log::warn!("failed to remove operation record {:?}: {}", self.path, err);
+        }
+    }
+}
+
+/// Operation left behind by a previous instance that exited before it finished
+#[derive(Clone, Debug)]
+pub struct UnfinishedOperation {
+    path: PathBuf,
+    pub operation: QueuedOperation,
+    pairs: Vec<(PathBuf, PathBuf)>,
+    started: BTreeSet<PathBuf>,
+    finished: BTreeSet<PathBuf>,
+    renamed: Vec<(PathBuf, PathBuf)>,
+    processed: BTreeSet<PathBuf>,
+    cleanup: bool,
+}
+
+impl PartialEq for UnfinishedOperation {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for UnfinishedOperation {}
+
+impl UnfinishedOperation {
+    /// Find and claim records of operations whose process is no longer running
+    pub fn load_all() -> Vec<Self> {
+        let Some(dir) = queue_dir() else {
+            return Vec::new();
+        };
+        let entries = match fs::read_dir(&dir) {
+            Ok(ok) => ok,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    log::warn!("failed to read operation records in {:?}: {}", dir, err);
+                }
+                return Vec::new();
+            }
+        };
+
+        let mut unfinished = Vec::new();
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Some(pid) = record_pid(&path) else {
+                continue;
+            };
+            if process_running(pid) {
+                continue;
+            }
+
+            // Claim the record, so that other instances starting at the same time skip it
+            let claimed = dir.join(format!(
+                "{}-{}.jsonl",
+                process::id(),
+                NEXT_RECORD.fetch_add(1, Ordering::Relaxed)
+            ));
+            if fs::rename(&path, &claimed).is_err() {
+                continue;
+            }
+
+            match Self::load(&claimed) {
+                Ok(ok) => unfinished.push(ok),
+                Err(err) => {
+                    log::warn!("failed to load operation record {:?}: {}", path, err);
+                    let _ = fs::remove_file(&claimed);
+                }
+            }
+        }
+        unfinished
+    }
+
+    fn load(path: &Path) -> io::Result<Self> {
+        let mut lines = io::BufReader::new(fs::File::open(path)?).lines();
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "missing operation header",
+                ))
+            }
+        };
+
+        let mut unfinished = Self {
+            path: path.to_path_buf(),
+            operation: header.operation,
+            pairs: Vec::new(),
+            started: BTreeSet::new(),
+            finished: BTreeSet::new(),
+            renamed: Vec::new(),
+            processed: BTreeSet::new(),
+            cleanup: false,
+        };
+        for line in lines {
+            // The last line may be truncated if the process was killed while writing it
+            let Ok(event) = serde_json::from_str::<Event>(&line?) else {
+                break;
+            };
+            match event {
+                Event::Pair(from, to) => unfinished.pairs.push((from, to)),
+                Event::Started(path) => {
+                    unfinished.started.insert(path);
+                }
+                Event::Finished(path) => {
+                    unfinished.started.remove(&path);
+                    unfinished.finished.insert(path);
+                }
+                Event::Renamed(from, to) => unfinished.renamed.push((from, to)),
+                Event::Processed(path) => {
+                    unfinished.processed.insert(path);
+                }
+                Event::Cleanup => unfinished.cleanup = true,
+            }
+        }
+        Ok(unfinished)
+    }
+
+    /// Rolling back a move is not possible once sources are being removed
+    pub fn can_roll_back(&self) -> bool {
+        !self.cleanup
+    }
+
+    /// Forget this record
+    pub fn discard(&self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            log::warn!("failed to remove operation record {:?}: {}", self.path, err);
+        }
+    }
+
+    /// Operations that continue where the interrupted operation stopped
+    pub fn resume(&self) -> Vec<Operation> {
+        self.discard();
+
+        let mut operations = Vec::new();
+        match &self.operation {
+            QueuedOperation::Copy { .. }
+            | QueuedOperation::Duplicate { .. }
+            | QueuedOperation::Move { .. } => {
+                // Partially written files are copied again
+                for path in self.started.iter() {
+                    if path.is_file() {
+                        if let Err(err) = fs::remove_file(path) {
+                            log::warn!("failed to remove partial file {:?}: {}", path, err);
+                        }
+                    }
+                }
+                if self.pairs.is_empty() {
+                    // Stopped before anything was planned, so it starts again
+                    operations.push(self.operation.operation());
+                    return operations;
+                }
+                let mut pairs = self.pairs.clone();
+                // Items that were renamed have already been moved
+                pairs.retain(|(from, _)| !self.renamed.iter().any(|(renamed, _)| renamed == from));
+                operations.push(Operation::Resume {
+                    operation: Box::new(self.operation.operation()),
+                    state: ResumeState {
+                        pairs,
+                        completed: self.finished.clone(),
+                        ..Default::default()
+                    },
+                });
+            }
+            QueuedOperation::Compress { .. } => {
+                // Archives are written from the start again
+                operations.push(self.operation.operation());
+            }
+            QueuedOperation::Extract {
+                paths,
+                to,
+                name_encoding,
+            } => {
+                let partial = existing_roots(self.started.iter());
+                let paths: Vec<PathBuf> = paths
+                    .iter()
+                    .filter(|path| !self.processed.contains(*path))
+                    .cloned()
+                    .collect();
+                if !paths.is_empty() {
+                    // Partial items are removed by the same operation, so that extracting again
+                    // only starts once they are gone
+                    operations.push(Operation::Resume {
+                        operation: Box::new(Operation::Extract {
+                            paths,
+                            to: to.clone(),
+                            password: None,
+                            name_encoding: *name_encoding,
+                        }),
+                        state: ResumeState {
+                            partial,
+                            ..Default::default()
+                        },
+                    });
+                } else if !partial.is_empty() {
+                    operations.push(Operation::PermanentlyDelete { paths: partial });
+                }
+            }
+        }
+        operations
+    }
+
+    /// Operations that remove everything the interrupted operation created
+    pub fn roll_back(&self) -> Vec<Operation> {
+        self.discard();
+
+        let mut operations = Vec::new();
+        let created = existing_roots(self.started.iter().chain(self.finished.iter()));
+        if !created.is_empty() {
+            operations.push(Operation::PermanentlyDelete { paths: created });
+        }
+        for (from, to) in self.renamed.iter() {
+            operations.push(Operation::Rename {
+                from: to.clone(),
+                to: from.clone(),
+            });
+        }
+        operations
+    }
+}
+
+// Existing paths, without any paths that are inside of another path in the list
+fn existing_roots<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> Vec<PathBuf> {
+    let paths: BTreeSet<&PathBuf> = paths.filter(|path| path.exists()).collect();
+    paths
+        .iter()
+        .filter(|path| {
+            !path
+                .ancestors()
+                .skip(1)
+                .any(|ancestor| paths.contains(&ancestor.to_path_buf()))
+        })
+        .map(|path| (*path).clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::PathBuf};
+
+    use super::{existing_roots, QueuedOperation, ResumeState, UnfinishedOperation};
+    use crate::{app::test_utils::empty_fs, archive::NameEncoding, operation::Operation};
+
+    #[test]
+    fn existing_roots_skips_children_and_missing() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let dir = path.join("dir");
+        let child = dir.join("child");
+        fs::create_dir_all(&child)?;
+        let missing = path.join("missing");
+
+        let paths: Vec<PathBuf> = vec![child, dir.clone(), missing];
+        assert_eq!(existing_roots(paths.iter()), vec![dir]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn resume_extract_removes_partial_items_first() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let partial = path.join("partial");
+        fs::create_dir(&partial)?;
+        let archives = vec![path.join("done.zip"), path.join("partial.zip")];
+
+        let unfinished = UnfinishedOperation {
+            path: path.join("record.jsonl"),
+            operation: QueuedOperation::Extract {
+                paths: archives.clone(),
+                to: path.to_path_buf(),
+                name_encoding: NameEncoding::Auto,
+            },
+            pairs: Vec::new(),
+            started: [partial.clone()].into(),
+            finished: Default::default(),
+            renamed: Vec::new(),
+            processed: [archives[0].clone()].into(),
+            cleanup: false,
+        };
+
+        // A single operation removes the partial items and then extracts again
+        assert_eq!(
+            unfinished.resume(),
+            vec![Operation::Resume {
+                operation: Box::new(Operation::Extract {
+                    paths: vec![archives[1].clone()],
+                    to: path.to_path_buf(),
+                    password: None,
+                    name_encoding: NameEncoding::Auto,
+                }),
+                state: ResumeState {
+                    partial: vec![partial],
+                    ..Default::default()
+                },
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn resume_duplicate_keeps_destinations() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let from = path.join("a");
+        let to = path.join("a (copy)");
+
+        let unfinished = UnfinishedOperation {
+            path: path.join("record.jsonl"),
+            operation: QueuedOperation::Duplicate {
+                paths: vec![from.clone()],
+            },
+            pairs: vec![(from.clone(), to.clone())],
+            started: Default::default(),
+            finished: Default::default(),
+            renamed: Vec::new(),
+            processed: Default::default(),
+            cleanup: false,
+        };
+
+        // Duplicates continue with the names they were given, rather than new unique names
+        assert_eq!(
+            unfinished.resume(),
+            vec![Operation::Resume {
+                operation: Box::new(Operation::Duplicate {
+                    paths: vec![from.clone()]
+                }),
+                state: ResumeState {
+                    pairs: vec![(from, to)],
+                    ..Default::default()
+                },
+            }]
+        );
+
+        Ok(())
+    }
+}
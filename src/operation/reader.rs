@@ -1,29 +1,30 @@
 use std::{fs, io, path::Path};
 
 use super::Controller;
+use crate::volumes::{self, VolumeReader};
 
 // Special reader just for operations, handling cancel and progress
-pub struct OpReader {
-    file: fs::File,
-    metadata: fs::Metadata,
-    current: u64,
+pub struct OpReader<R = fs::File> {
+    file: R,
     controller: Controller,
 }
 
 impl OpReader {
     pub fn new<P: AsRef<Path>>(path: P, controller: Controller) -> io::Result<Self> {
         let file = fs::File::open(&path)?;
-        let metadata = file.metadata()?;
-        Ok(Self {
-            file,
-            metadata,
-            current: 0,
-            controller,
-        })
+        Ok(Self { file, controller })
     }
 }
 
-impl io::Read for OpReader {
+impl OpReader<VolumeReader> {
+    /// Open an archive, reading all of its volumes if it is split
+    pub fn archive<P: AsRef<Path>>(path: P, controller: Controller) -> io::Result<Self> {
+        let file = volumes::open(path.as_ref())?;
+        Ok(Self { file, controller })
+    }
+}
+
+impl<R: io::Read> io::Read for OpReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         futures::executor::block_on(async {
             self.controller
@@ -33,11 +34,15 @@ impl io::Read for OpReader {
         })?;
 
         let count = self.file.read(buf)?;
-        self.current += count as u64;
-
-        let progress = self.current as f32 / self.metadata.len() as f32;
-        self.controller.set_progress(progress);
+        self.controller.add_bytes(count as u64);
 
         Ok(count)
     }
 }
+
+// Seeking skips data without reading it, so it is not counted as progress
+impl<R: io::Seek> io::Seek for OpReader<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
@@ -1,14 +1,29 @@
 use compio::buf::{IntoInner, IoBuf};
 use compio::io::{AsyncReadAt, AsyncWriteAt};
 use compio::BufResult;
+use filetime::FileTime;
+use sha2::{digest::Output, Digest, Sha256};
 use std::future::Future;
 use std::pin::Pin;
 use std::time::Instant;
-use std::{cell::Cell, error::Error, fs, ops::ControlFlow, path::PathBuf, rc::Rc};
+use std::{
+    cell::Cell,
+    collections::BTreeSet,
+    error::Error,
+    fs, io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 use walkdir::WalkDir;
 
-use super::{copy_unique_path, Controller, OperationSelection, ReplaceResult};
+use super::{
+    copy_unique_path, queue::QueueLog, Controller, FailedPath, OperationPlan, OperationSelection,
+    PlanResult, ReplaceResult,
+};
+use crate::fl;
 
+#[derive(Clone, Copy, Debug)]
 pub enum Method {
     Copy,
     Move { cross_device_copy: bool },
@@ -19,8 +34,28 @@ pub struct Context {
     controller: Controller,
     on_progress: Box<dyn OnProgress>,
     on_replace: Pin<Box<dyn OnReplace>>,
+    on_plan: Pin<Box<dyn OnPlan>>,
     pub(crate) op_sel: OperationSelection,
+    pub(crate) queue_log: Option<QueueLog>,
+    // Destinations completed before the operation was interrupted
+    pub(crate) completed: BTreeSet<PathBuf>,
     replace_result_opt: Option<ReplaceResult>,
+    preserve: bool,
+    // Directories created by the operation, which get their metadata once their contents are done
+    preserve_dirs: Vec<(PathBuf, PathBuf)>,
+    special_files: bool,
+    // Sources of special files that were not recreated, which are kept when moving
+    special_skipped: Vec<PathBuf>,
+    // Bytes of files that are already done, for reporting progress to the controller
+    bytes_done: u64,
+    verify: bool,
+    // Source and destination of copies that did not match after verification
+    pub(crate) verify_failed: Vec<(PathBuf, PathBuf)>,
+    continue_on_error: bool,
+    // Paths skipped after an error, when continuing on errors
+    pub(crate) failed: Vec<FailedPath>,
+    // Number of items whose owner could not be preserved, reported once for the operation
+    owner_failed: usize,
 }
 
 pub trait OnProgress: Fn(&Op, &Progress) + 'static {}
@@ -35,6 +70,15 @@ impl<F> OnReplace for F where
 {
 }
 
+pub trait OnPlan:
+    for<'a> Fn(&'a OperationPlan) -> Pin<Box<dyn Future<Output = PlanResult> + 'a>> + 'static
+{
+}
+impl<F> OnPlan for F where
+    F: for<'a> Fn(&'a OperationPlan) -> Pin<Box<dyn Future<Output = PlanResult> + 'a>> + 'static
+{
+}
+
 impl Context {
     pub fn new(controller: Controller) -> Self {
         Self {
@@ -43,8 +87,21 @@ impl Context {
             controller,
             on_progress: Box::new(|_op, _progress| {}),
             on_replace: Box::pin(|_op| Box::pin(async { ReplaceResult::Cancel })),
+            on_plan: Box::pin(|_plan| Box::pin(async { PlanResult::Start(None) })),
             op_sel: OperationSelection::default(),
+            queue_log: None,
+            completed: BTreeSet::new(),
             replace_result_opt: None,
+            preserve: false,
+            preserve_dirs: Vec::new(),
+            special_files: true,
+            special_skipped: Vec::new(),
+            bytes_done: 0,
+            verify: false,
+            verify_failed: Vec::new(),
+            continue_on_error: false,
+            failed: Vec::new(),
+            owner_failed: 0,
         }
     }
 
@@ -66,10 +123,38 @@ impl Context {
             for entry in WalkDir::new(&from_parent).into_iter() {
                 self.controller.check().await?;
 
-                let entry = entry.map_err(|err| {
-                    format!("failed to walk directory {:?}: {}", from_parent, err)
-                })?;
+                let entry = match entry {
+                    Ok(ok) => ok,
+                    Err(err) if self.continue_on_error => {
+                        log::warn!("failed to walk directory {:?}: {}", from_parent, err);
+                        let from = err.path().unwrap_or(&from_parent).to_path_buf();
+                        let to = if from == from_parent {
+                            Some(to_parent.clone())
+                        } else {
+                            from.strip_prefix(&from_parent)
+                                .ok()
+                                .map(|relative| to_parent.join(relative))
+                        };
+                        self.failed.push(FailedPath {
+                            from,
+                            to,
+                            error: err.to_string(),
+                        });
+                        continue;
+                    }
+                    Err(err) => {
+                        return Err(format!(
+                            "failed to walk directory {:?}: {}",
+                            from_parent, err
+                        ));
+                    }
+                };
                 let file_type = entry.file_type();
+                let size = if file_type.is_file() {
+                    entry.metadata().map_or(0, |metadata| metadata.len())
+                } else {
+                    0
+                };
                 let from = entry.into_path();
                 let kind = if file_type.is_dir() {
                     OpKind::Mkdir
@@ -83,7 +168,18 @@ impl Context {
                         .map_err(|err| format!("failed to read link {:?}: {}", from, err))?;
                     OpKind::Symlink { target }
                 } else {
-                    //TODO: present dialog and allow continue
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::MetadataExt;
+                        let metadata = fs::symlink_metadata(&from).map_err(|err| {
+                            format!("failed to read metadata of {:?}: {}", from, err)
+                        })?;
+                        OpKind::Special {
+                            mode: metadata.mode(),
+                            rdev: metadata.rdev(),
+                        }
+                    }
+                    #[cfg(not(unix))]
                     return Err(format!("{} is not a known file type", from.display()));
                 };
                 let to = if from == from_parent {
@@ -103,6 +199,7 @@ impl Context {
                     kind,
                     from,
                     to,
+                    size,
                     skipped: Rc::new(Skip {
                         normal: Cell::new(false),
                         cleanup: Cell::new(false),
@@ -125,27 +222,125 @@ impl Context {
             ops.push(cleanup_op);
         }
 
+        let plan = OperationPlan {
+            files: ops.iter().filter(|op| op.is_file()).count(),
+            bytes: ops.iter().map(|op| op.size).sum(),
+            conflicts: ops
+                .iter()
+                .filter(|op| op.is_file() && !self.completed.contains(&op.to) && op.to.is_file())
+                .map(|op| op.to.clone())
+                .collect(),
+        };
+        match (self.on_plan)(&plan).await {
+            PlanResult::Start(replace_result_opt) => {
+                if replace_result_opt.is_some() {
+                    self.replace_result_opt = replace_result_opt;
+                }
+            }
+            PlanResult::Cancel => {
+                self.controller.cancel();
+                self.controller.check().await?;
+            }
+        }
+
         let total_ops = ops.len();
+        self.controller.set_totals(plan.files, plan.bytes);
+        // Directories that could not be created, whose contents are skipped
+        let mut failed_dirs: Vec<PathBuf> = Vec::new();
         for (current_ops, mut op) in ops.into_iter().enumerate() {
             self.controller.check().await?;
 
+            if !op.is_cleanup && failed_dirs.iter().any(|dir| op.from.starts_with(dir)) {
+                op.skipped.normal.set(true);
+                if op.is_file() {
+                    self.file_done(&op);
+                }
+                continue;
+            }
+
+            if op.is_file() {
+                self.controller.set_current_file(&op.from);
+            }
             let progress = Progress {
                 current_ops,
                 total_ops,
                 current_bytes: 0,
                 total_bytes: None,
             };
-            (self.on_progress)(&op, &progress);
-            if op.run(self, progress).await.map_err(|err| {
-                format!(
-                    "failed to {:?} {:?} to {:?}: {}",
-                    op.kind, op.from, op.to, err
-                )
-            })? {
+            self.report_progress(&op, &progress);
+
+            let created = if op.is_cleanup {
+                if let Some(queue_log) = &mut self.queue_log {
+                    queue_log.cleanup();
+                }
+                false
+            } else if self.completed.contains(&op.to) {
+                // Already performed before the operation was interrupted
+                if op.is_file() {
+                    self.file_done(&op);
+                }
+                continue;
+            } else {
+                !op.to.exists()
+            };
+            if created {
+                if let Some(queue_log) = &mut self.queue_log {
+                    queue_log.started(&op.to);
+                }
+            }
+
+            let to = op.to.clone();
+            let finished = match op.run(self, progress).await {
+                Ok(finished) => finished,
+                Err(err) if self.continue_on_error && !self.controller.is_cancelled() => {
+                    log::warn!(
+                        "failed to {:?} {:?} to {:?}: {}",
+                        op.kind,
+                        op.from,
+                        op.to,
+                        err
+                    );
+                    // Sources that failed are kept when moving
+                    op.skipped.normal.set(true);
+                    if matches!(op.kind, OpKind::Mkdir) {
+                        failed_dirs.push(op.from.clone());
+                    }
+                    if op.is_file() {
+                        self.file_done(&op);
+                    }
+                    self.failed.push(FailedPath {
+                        from: op.from.clone(),
+                        to: (!op.is_cleanup).then(|| op.to.clone()),
+                        error: err.to_string(),
+                    });
+                    continue;
+                }
+                Err(err) => {
+                    return Err(format!(
+                        "failed to {:?} {:?} to {:?}: {}",
+                        op.kind, op.from, op.to, err
+                    ));
+                }
+            };
+            if finished {
+                if op.is_file() {
+                    self.file_done(&op);
+                }
+
+                // Record destinations that did not exist before, including renamed copies
+                if !op.is_cleanup && !op.skipped.normal.get() && (created || op.to != to) {
+                    if let Some(queue_log) = &mut self.queue_log {
+                        queue_log.finished(&op.to);
+                    }
+                }
+
                 // The from path is ignored in the operation selection if it is a top level item
-                if self.op_sel.ignored.contains(&op.from) {
+                if self.op_sel.ignored.contains(&op.from) && !op.skipped.normal.get() {
                     // So add the to path to the selection
                     self.op_sel.selected.push(op.to.clone());
+                    if !op.is_cleanup {
+                        self.op_sel.pairs.push((op.from.clone(), op.to.clone()));
+                    }
                 }
             } else {
                 // Cancelled
@@ -153,6 +348,20 @@ impl Context {
             }
         }
 
+        // Creating entries changes the times of their parents, so directories are done last
+        for (from, to) in std::mem::take(&mut self.preserve_dirs).into_iter().rev() {
+            self.controller.check().await?;
+            let warnings = preserve_metadata(&from, &to, &mut self.owner_failed);
+            self.op_sel.warnings.extend(warnings);
+        }
+
+        if self.owner_failed > 0 {
+            self.op_sel.warnings.push(fl!(
+                "preserve-owner-failed",
+                items = std::mem::take(&mut self.owner_failed)
+            ));
+        }
+
         Ok(true)
     }
 
@@ -166,6 +375,88 @@ impl Context {
         self
     }
 
+    /// Confirm or adjust the plan before any ops run
+    pub fn on_plan(mut self, f: impl OnPlan + 'static) -> Self {
+        self.on_plan = Box::pin(f);
+        self
+    }
+
+    fn report_progress(&self, op: &Op, progress: &Progress) {
+        (self.on_progress)(op, progress);
+        self.controller
+            .set_bytes_done(self.bytes_done + progress.current_bytes);
+    }
+
+    fn file_done(&mut self, op: &Op) {
+        self.bytes_done += op.size;
+        self.controller.set_bytes_done(self.bytes_done);
+        self.controller.file_done();
+    }
+
+    /// Copy timestamps, permissions, ownership and extended attributes to destinations
+    pub fn preserve(mut self, preserve: bool) -> Self {
+        self.preserve = preserve;
+        self
+    }
+
+    /// Recreate FIFOs, sockets and device nodes, or skip them if false
+    pub fn special_files(mut self, special_files: bool) -> Self {
+        self.special_files = special_files;
+        self
+    }
+
+    /// Skip paths that fail and collect their errors instead of stopping the operation
+    pub fn continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Read back copied files and compare their checksums with the sources
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    async fn hash_file(&mut self, path: &Path) -> Result<Output<Sha256>, Box<dyn Error>> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            // Drop cached pages so the data is read back from the device
+            let file = fs::File::open(path)?;
+            unsafe {
+                libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+            }
+        }
+
+        let file = compio::fs::File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut buf_in = std::mem::take(&mut self.buf);
+        let mut pos = 0;
+        loop {
+            let BufResult(result, buf_out) = file.read_at(buf_in, pos).await;
+            let count = match result {
+                Ok(0) => {
+                    self.buf = buf_out;
+                    break;
+                }
+                Ok(count) => count,
+                Err(why) => {
+                    self.buf = buf_out;
+                    return Err(why.into());
+                }
+            };
+            hasher.update(&buf_out[..count]);
+            pos += count as u64;
+
+            if let Err(why) = self.controller.check().await {
+                self.buf = buf_out;
+                return Err(why.into());
+            }
+            buf_in = buf_out;
+        }
+        Ok(hasher.finalize())
+    }
+
     async fn replace(&mut self, op: &Op) -> Result<ControlFlow<bool, PathBuf>, Box<dyn Error>> {
         let replace_result = match self.replace_result_opt {
             Some(result) => result,
@@ -207,11 +498,20 @@ pub struct Progress {
 #[derive(Debug)]
 pub enum OpKind {
     Copy,
-    Move { cross_device_copy: bool },
+    Move {
+        cross_device_copy: bool,
+    },
     Mkdir,
     Remove,
     Rmdir,
-    Symlink { target: PathBuf },
+    Symlink {
+        target: PathBuf,
+    },
+    #[cfg(unix)]
+    Special {
+        mode: u32,
+        rdev: u64,
+    },
 }
 
 #[derive(Debug)]
@@ -227,14 +527,23 @@ pub struct Op {
     pub kind: OpKind,
     pub from: PathBuf,
     pub to: PathBuf,
+    /// Size of the source file, or zero for other kinds of entries
+    pub size: u64,
     pub skipped: Rc<Skip>,
     pub is_cleanup: bool,
 }
 
 impl Op {
+    // Whether this op counts as one of the files transferred by the operation
+    fn is_file(&self) -> bool {
+        !self.is_cleanup && !matches!(self.kind, OpKind::Mkdir)
+    }
+
     fn move_cleanup_op(&self) -> Option<Self> {
         let kind = match self.kind {
             OpKind::Copy | OpKind::Move { .. } | OpKind::Symlink { .. } => OpKind::Remove,
+            #[cfg(unix)]
+            OpKind::Special { .. } => OpKind::Remove,
             OpKind::Mkdir => OpKind::Rmdir,
             OpKind::Remove | OpKind::Rmdir => return None,
         };
@@ -243,11 +552,101 @@ impl Op {
             from: self.from.clone(),
             //TODO: it is strange to have `to` here
             to: self.to.clone(),
+            size: 0,
             skipped: self.skipped.clone(),
             is_cleanup: true,
         })
     }
 
+    // Copy without passing data through userspace, first by sharing extents with a reflink and
+    // then with copy_file_range for each data segment, so holes in sparse files are kept.
+    // Returns false if neither is supported and a buffered copy is needed.
+    #[cfg(target_os = "linux")]
+    async fn copy_fast(
+        &self,
+        ctx: &mut Context,
+        from_file: &compio::fs::File,
+        to_file: &compio::fs::File,
+        len: u64,
+        progress: &mut Progress,
+    ) -> Result<bool, Box<dyn Error>> {
+        use std::os::fd::AsRawFd;
+
+        // Limit the size of each blocking call so progress and cancellation stay responsive
+        const CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+        let from_fd = from_file.as_raw_fd();
+        let to_fd = to_file.as_raw_fd();
+
+        if len == 0 {
+            return Ok(true);
+        }
+
+        if unsafe { libc::ioctl(to_fd, libc::FICLONE, from_fd) } == 0 {
+            progress.current_bytes = len;
+            ctx.report_progress(self, progress);
+            return Ok(true);
+        }
+
+        let mut pos = 0;
+        while pos < len {
+            ctx.controller.check().await?;
+
+            // Find the next segment of data, skipping over holes
+            let data = match unsafe { libc::lseek64(from_fd, pos as i64, libc::SEEK_DATA) } {
+                -1 => match io::Error::last_os_error().raw_os_error() {
+                    // There is no more data, only a hole until the end of the file
+                    Some(libc::ENXIO) => len,
+                    // Holes are not supported, so everything is data
+                    _ => pos,
+                },
+                data => data as u64,
+            };
+            let hole = match unsafe { libc::lseek64(from_fd, data as i64, libc::SEEK_HOLE) } {
+                -1 => len,
+                hole => (hole as u64).min(len),
+            };
+
+            let mut off_in = data as i64;
+            let mut off_out = data as i64;
+            while (off_in as u64) < hole {
+                let count = (hole - off_in as u64).min(CHUNK_SIZE) as usize;
+                let copied = unsafe {
+                    libc::copy_file_range(from_fd, &mut off_in, to_fd, &mut off_out, count, 0)
+                };
+                match copied {
+                    -1 => {
+                        let err = io::Error::last_os_error();
+                        return match err.raw_os_error() {
+                            // Not supported between these files, so fall back to a buffered copy
+                            Some(libc::EXDEV | libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL) => {
+                                progress.current_bytes = 0;
+                                Ok(false)
+                            }
+                            _ => Err(err.into()),
+                        };
+                    }
+                    // The file was truncated while copying
+                    0 => break,
+                    _ => {}
+                }
+
+                progress.current_bytes = off_in as u64;
+                ctx.report_progress(self, progress);
+                ctx.controller.check().await?;
+            }
+            pos = hole.max(off_in as u64);
+        }
+
+        // A hole at the end of the file is kept by extending the file to the full length
+        if unsafe { libc::ftruncate64(to_fd, len as i64) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        progress.current_bytes = len;
+        ctx.report_progress(self, progress);
+        Ok(true)
+    }
+
     async fn run(
         &mut self,
         ctx: &mut Context,
@@ -289,63 +688,114 @@ impl Op {
                 )?;
 
                 progress.total_bytes = Some(metadata.len());
-                (ctx.on_progress)(self, &progress);
+                ctx.report_progress(self, &progress);
                 if let Err(err) = to_file.set_permissions(metadata.permissions()).await {
                     // This error is not propagated upwards as some filesystems do not support setting permissions
                     log::warn!("failed to set permissions for {:?}: {}", self.to, err);
                 }
 
-                // Prevent spamming the progress callbacks.
-                let mut last_progress_update = Instant::now();
-                // io_uring/IOCP requires transferring ownership of the buffer to the kernel.
-                let mut buf_in = std::mem::take(&mut ctx.buf);
-                // Track where the current read/write position is at.
-                let mut pos = 0;
+                // Hash of the source as it was read, to compare with the destination afterwards
+                let mut hasher_opt = None;
+                // Reflinks and copy_file_range may share extents with the source, which would
+                // make verification compare the same data with itself, so verified copies are
+                // always buffered
+                #[cfg(target_os = "linux")]
+                let copied = !ctx.verify
+                    && self
+                        .copy_fast(ctx, &from_file, &to_file, metadata.len(), &mut progress)
+                        .await?;
+                #[cfg(not(target_os = "linux"))]
+                let copied = false;
 
-                loop {
-                    let BufResult(result, buf_out) = from_file.read_at(buf_in, pos).await;
+                if !copied {
+                    hasher_opt = ctx.verify.then(Sha256::new);
+                    // Runs of zeroes are left as holes when the source is sparse
+                    let sparse = is_sparse(&self.from);
 
-                    let count = match result {
-                        Ok(0) => {
-                            ctx.buf = buf_out;
-                            break;
-                        }
-                        Ok(count) => count,
-                        Err(why) => {
-                            ctx.buf = buf_out;
-                            return Err(why.into());
-                        }
-                    };
+                    // Prevent spamming the progress callbacks.
+                    let mut last_progress_update = Instant::now();
+                    // io_uring/IOCP requires transferring ownership of the buffer to the kernel.
+                    let mut buf_in = std::mem::take(&mut ctx.buf);
+                    // Track where the current read/write position is at.
+                    let mut pos = 0;
 
-                    let BufResult(result, buf_out_slice) =
-                        to_file.write_at(buf_out.slice(..count), pos).await;
-                    let buf_out = buf_out_slice.into_inner();
+                    loop {
+                        let BufResult(result, buf_out) = from_file.read_at(buf_in, pos).await;
 
-                    if let Err(why) = result {
-                        ctx.buf = buf_out;
-                        return Err(why.into());
-                    }
+                        let count = match result {
+                            Ok(0) => {
+                                ctx.buf = buf_out;
+                                break;
+                            }
+                            Ok(count) => count,
+                            Err(why) => {
+                                ctx.buf = buf_out;
+                                return Err(why.into());
+                            }
+                        };
+
+                        let buf_out = if sparse && buf_out[..count].iter().all(|&byte| byte == 0) {
+                            buf_out
+                        } else {
+                            let BufResult(result, buf_out_slice) =
+                                to_file.write_at(buf_out.slice(..count), pos).await;
+                            let buf_out = buf_out_slice.into_inner();
+
+                            if let Err(why) = result {
+                                ctx.buf = buf_out;
+                                return Err(why.into());
+                            }
+                            buf_out
+                        };
+
+                        if let Some(hasher) = &mut hasher_opt {
+                            hasher.update(&buf_out[..count]);
+                        }
 
-                    progress.current_bytes += count as u64;
-                    pos += count as u64;
+                        progress.current_bytes += count as u64;
+                        pos += count as u64;
 
-                    // Avoid spamming progress messages too early.
-                    let current = Instant::now();
-                    if current.duration_since(last_progress_update).as_millis() > 49 {
-                        last_progress_update = current;
-                        (ctx.on_progress)(self, &progress);
+                        // Avoid spamming progress messages too early.
+                        let current = Instant::now();
+                        if current.duration_since(last_progress_update).as_millis() > 49 {
+                            last_progress_update = current;
+                            ctx.report_progress(self, &progress);
 
-                        // Also check if the progress was cancelled.
-                        if let Err(why) = ctx.controller.check().await {
-                            ctx.buf = buf_out;
-                            return Err(why.into());
+                            // Also check if the progress was cancelled.
+                            if let Err(why) = ctx.controller.check().await {
+                                ctx.buf = buf_out;
+                                return Err(why.into());
+                            }
                         }
+
+                        buf_in = buf_out;
                     }
 
-                    buf_in = buf_out;
+                    if sparse {
+                        // Skipped zeroes at the end of the file still need to be allocated as a hole
+                        fs::OpenOptions::new()
+                            .write(true)
+                            .open(&self.to)?
+                            .set_len(pos)?;
+                    }
                 }
 
                 to_file.sync_all().await?;
+                drop(to_file);
+
+                if let Some(hasher) = hasher_opt {
+                    if ctx.hash_file(&self.to).await? != hasher.finalize() {
+                        log::warn!("{:?} does not match {:?} after copying", self.to, self.from);
+                        // Never remove the source of a move that failed verification
+                        self.skipped.cleanup.set(true);
+                        ctx.verify_failed.push((self.from.clone(), self.to.clone()));
+                    }
+                }
+
+                if ctx.preserve {
+                    let warnings = preserve_metadata(&self.from, &self.to, &mut ctx.owner_failed);
+                    ctx.op_sel.warnings.extend(warnings);
+                }
             }
             OpKind::Move { cross_device_copy } => {
                 // Remove `to` if overwriting and it is an existing file
@@ -379,6 +829,7 @@ impl Op {
                                 kind: OpKind::Copy,
                                 from: self.from.clone(),
                                 to: self.to.clone(),
+                                size: self.size,
                                 skipped: self.skipped.clone(),
                                 is_cleanup: self.is_cleanup,
                             };
@@ -390,12 +841,26 @@ impl Op {
                 }
             }
             OpKind::Mkdir => {
+                if ctx.preserve && !self.to.exists() {
+                    ctx.preserve_dirs.push((self.from.clone(), self.to.clone()));
+                }
                 compio::fs::create_dir_all(&self.to).await?;
             }
             OpKind::Remove => {
                 compio::fs::remove_file(&self.from).await?;
             }
             OpKind::Rmdir => {
+                // Directories still holding sources that failed or were skipped are kept
+                if ctx
+                    .verify_failed
+                    .iter()
+                    .map(|(from, _to)| from)
+                    .chain(ctx.special_skipped.iter())
+                    .chain(ctx.failed.iter().map(|failed| &failed.from))
+                    .any(|from| from.starts_with(&self.from))
+                {
+                    return Ok(true);
+                }
                 compio::fs::remove_dir(&self.from).await?;
             }
             OpKind::Symlink { ref target } => {
@@ -422,8 +887,192 @@ impl Op {
                         std::os::windows::fs::symlink_file(target, &self.to)?;
                     }
                 }
+                if ctx.preserve {
+                    let warnings = preserve_metadata(&self.from, &self.to, &mut ctx.owner_failed);
+                    ctx.op_sel.warnings.extend(warnings);
+                }
+            }
+            #[cfg(unix)]
+            OpKind::Special { mode, rdev } => {
+                if !ctx.special_files {
+                    log::info!("skipping special file {:?}", self.from);
+                    ctx.op_sel.warnings.push(fl!(
+                        "special-file-skipped",
+                        path = self.from.display().to_string()
+                    ));
+                    self.skip_special(ctx);
+                    return Ok(true);
+                }
+
+                // Remove `to` if overwriting and it is an existing entry that is not a directory
+                if fs::symlink_metadata(&self.to).is_ok_and(|metadata| !metadata.is_dir()) {
+                    match ctx.replace(self).await? {
+                        ControlFlow::Continue(to) => {
+                            self.to = to;
+                        }
+                        ControlFlow::Break(ret) => {
+                            return Ok(ret);
+                        }
+                    }
+                }
+
+                // Device nodes usually need privileges, so failures are reported and skipped
+                if let Err(err) = make_special(&self.to, mode, rdev) {
+                    log::warn!("failed to create special file {:?}: {}", self.to, err);
+                    ctx.op_sel.warnings.push(fl!(
+                        "special-file-failed",
+                        path = self.to.display().to_string(),
+                        error = err.to_string()
+                    ));
+                    self.skip_special(ctx);
+                    return Ok(true);
+                }
+
+                if ctx.preserve {
+                    let warnings = preserve_metadata(&self.from, &self.to, &mut ctx.owner_failed);
+                    ctx.op_sel.warnings.extend(warnings);
+                }
             }
         }
         Ok(true)
     }
+
+    // Mark a special file that was not recreated, so a move keeps its source
+    #[cfg(unix)]
+    fn skip_special(&self, ctx: &mut Context) {
+        self.skipped.normal.set(true);
+        ctx.special_skipped.push(self.from.clone());
+    }
+}
+
+// Create a FIFO, socket or device node with the type and permissions from `mode`
+#[cfg(unix)]
+fn make_special(path: &Path, mode: u32, rdev: u64) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let file_type = mode & libc::S_IFMT as u32;
+    let permissions = (mode & 0o7777) as libc::mode_t;
+    let ret = if file_type == libc::S_IFIFO as u32 {
+        unsafe { libc::mkfifo(c_path.as_ptr(), permissions) }
+    } else {
+        unsafe {
+            libc::mknod(
+                c_path.as_ptr(),
+                file_type as libc::mode_t | permissions,
+                rdev as libc::dev_t,
+            )
+        }
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+// Copy metadata from one path to another, returning descriptions of anything that could not be
+// preserved. Ownership failures are counted instead, as they usually apply to every item.
+// Symbolic links themselves are updated rather than their targets.
+fn preserve_metadata(from: &Path, to: &Path, owner_failed: &mut usize) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut warn = |attribute: String, err: io::Error| {
+        log::warn!("failed to preserve {} of {:?}: {}", attribute, to, err);
+        warnings.push(fl!(
+            "preserve-failed",
+            attribute = attribute,
+            path = to.display().to_string(),
+            error = err.to_string()
+        ));
+    };
+
+    let metadata = match fs::symlink_metadata(from) {
+        Ok(ok) => ok,
+        Err(err) => {
+            warn(fl!("metadata"), err);
+            return warnings;
+        }
+    };
+    let is_symlink = metadata.file_type().is_symlink();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{lchown, MetadataExt, PermissionsExt};
+
+        let mode = metadata.mode() & 0o7777;
+        if !is_symlink {
+            // Extended attributes, which include ACLs, can only be written while writable
+            if mode & 0o200 == 0 {
+                let _ = fs::set_permissions(to, fs::Permissions::from_mode(mode | 0o200));
+            }
+            match xattr::list(from) {
+                Ok(names) => {
+                    for name in names {
+                        let result =
+                            xattr::get(from, &name).and_then(|value_opt| match value_opt {
+                                Some(value) => xattr::set(to, &name, &value),
+                                None => Ok(()),
+                            });
+                        if let Err(err) = result {
+                            warn(
+                                fl!(
+                                    "extended-attribute",
+                                    name = name.to_string_lossy().into_owned()
+                                ),
+                                err,
+                            );
+                        }
+                    }
+                }
+                // The source filesystem does not have extended attributes
+                Err(err) if err.kind() == io::ErrorKind::Unsupported => {}
+                Err(err) => warn(fl!("extended-attributes"), err),
+            }
+        }
+
+        // Only root may give items away, other users may only change the group of their own items
+        let euid = uzers::get_effective_uid();
+        if euid == 0 || euid == metadata.uid() {
+            if let Err(err) = lchown(to, Some(metadata.uid()), Some(metadata.gid())) {
+                log::warn!("failed to preserve ownership of {:?}: {}", to, err);
+                *owner_failed += 1;
+            }
+        } else {
+            *owner_failed += 1;
+        }
+
+        // Changing the owner clears setuid and setgid, so the mode is set afterwards
+        if !is_symlink {
+            if let Err(err) = fs::set_permissions(to, fs::Permissions::from_mode(mode)) {
+                warn(fl!("permissions"), err);
+            }
+        }
+    }
+
+    let atime = FileTime::from_last_access_time(&metadata);
+    let mtime = FileTime::from_last_modification_time(&metadata);
+    let result = if is_symlink {
+        filetime::set_symlink_file_times(to, atime, mtime)
+    } else {
+        filetime::set_file_times(to, atime, mtime)
+    };
+    if let Err(err) = result {
+        warn(fl!("timestamps"), err);
+    }
+
+    warnings
+}
+
+// Check if a file takes up less space than its length, meaning it has holes
+fn is_sparse(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).is_ok_and(|metadata| metadata.blocks() * 512 < metadata.len())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
 }
@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    path::Path,
+};
+
+use super::Operation;
+
+// Identifier of the block device holding a path, with partitions mapped to their disk
+pub type Device = u64;
+
+// Device of the filesystem holding a path, without mapping partitions to their disk
+#[cfg(unix)]
+fn filesystem_device(path: &Path) -> Option<Device> {
+    use std::os::unix::fs::MetadataExt;
+
+    // Destinations may not exist yet, so use the nearest existing ancestor
+    Some(
+        path.ancestors()
+            .find_map(|ancestor| ancestor.symlink_metadata().ok())?
+            .dev(),
+    )
+}
+
+#[cfg(not(unix))]
+fn filesystem_device(_path: &Path) -> Option<Device> {
+    //TODO: support Windows?
+    None
+}
+
+pub(super) fn path_device(path: &Path) -> Option<Device> {
+    let dev = filesystem_device(path)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        // Partitions of the same disk compete for the same device
+        let sys_path = format!("/sys/dev/block/{}:{}", libc::major(dev), libc::minor(dev));
+        if let Ok(sys_path) = std::fs::canonicalize(sys_path) {
+            if sys_path.join("partition").exists() {
+                if let Some(disk_dev) = sys_path
+                    .parent()
+                    .and_then(|disk| std::fs::read_to_string(disk.join("dev")).ok())
+                    .and_then(|majmin| {
+                        let (major, minor) = majmin.trim().split_once(':')?;
+                        Some(libc::makedev(major.parse().ok()?, minor.parse().ok()?))
+                    })
+                {
+                    return Some(disk_dev);
+                }
+            }
+        }
+    }
+
+    Some(dev)
+}
+
+/// Devices read or written by an operation that transfers data. Operations that only change
+/// metadata return no devices and are never held back. This reads the filesystem, so it should
+/// not be called on the UI thread.
+pub fn operation_devices(operation: &Operation) -> BTreeSet<Device> {
+    let (paths, to) = match operation {
+        Operation::AddToArchive {
+            paths, archive: to, ..
+        }
+        | Operation::Compress { paths, to, .. }
+        | Operation::Copy { paths, to }
+        | Operation::Extract { paths, to, .. } => (paths, to),
+        Operation::Move { paths, to, .. } => {
+            // Moves on the same filesystem are renames, while moves between partitions of the
+            // same disk are copies
+            let to_device = filesystem_device(to);
+            if paths
+                .iter()
+                .all(|path| filesystem_device(path) == to_device)
+            {
+                return BTreeSet::new();
+            }
+            (paths, to)
+        }
+        Operation::ExtractEntries { archive, to, .. } => {
+            return [archive, to]
+                .into_iter()
+                .filter_map(|path| path_device(path))
+                .collect()
+        }
+        Operation::Resume { operation, .. } => return operation_devices(operation),
+        Operation::Duplicate { paths }
+        | Operation::Shred { paths, .. }
+        | Operation::TestArchive { paths, .. } => {
+            return paths.iter().filter_map(|path| path_device(path)).collect()
+        }
+        _ => return BTreeSet::new(),
+    };
+    paths
+        .iter()
+        .map(|path| path.as_path())
+        .chain(std::iter::once(to.as_path()))
+        .filter_map(path_device)
+        .collect()
+}
+
+/// Runs operations sharing a device one at a time, in order, while operations on independent
+/// devices run in parallel
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    running: BTreeMap<u64, BTreeSet<Device>>,
+    // Operations whose devices are still being found keep their place without devices
+    queued: VecDeque<(u64, Option<BTreeSet<Device>>)>,
+}
+
+impl Scheduler {
+    fn conflicts(&self, devices: &BTreeSet<Device>) -> bool {
+        self.running
+            .values()
+            .any(|running| !running.is_disjoint(devices))
+    }
+
+    /// Hold a place in the queue for an operation while its devices are found
+    pub fn reserve(&mut self, id: u64) {
+        self.queued.push_back((id, None));
+    }
+
+    /// Add an operation, or give a reserved operation its devices, returning true if it can
+    /// start now
+    pub fn submit(&mut self, id: u64, devices: BTreeSet<Device>) -> bool {
+        let i = match self
+            .queued
+            .iter()
+            .position(|(queued_id, _)| *queued_id == id)
+        {
+            Some(i) => i,
+            None => {
+                self.queued.push_back((id, None));
+                self.queued.len() - 1
+            }
+        };
+        // Operations also wait behind earlier queued operations on the same devices
+        let blocked = self.conflicts(&devices)
+            || self.queued.iter().take(i).any(|(_, queued)| {
+                queued
+                    .as_ref()
+                    .is_some_and(|queued| !queued.is_disjoint(&devices))
+            });
+        if blocked {
+            self.queued[i].1 = Some(devices);
+            false
+        } else {
+            self.queued.remove(i);
+            self.running.insert(id, devices);
+            true
+        }
+    }
+
+    /// Remove a running or queued operation, returning queued operations that can start now
+    pub fn finish(&mut self, id: u64) -> Vec<u64> {
+        self.running.remove(&id);
+        self.queued.retain(|(queued_id, _)| *queued_id != id);
+
+        let mut started = Vec::new();
+        let mut waiting = BTreeSet::new();
+        let mut i = 0;
+        while let Some((queued_id, devices_opt)) = self.queued.get(i) {
+            let Some(devices) = devices_opt else {
+                i += 1;
+                continue;
+            };
+            // Keep the order of queued operations that share devices
+            if self.conflicts(devices) || !waiting.is_disjoint(devices) {
+                waiting.extend(devices.iter().copied());
+                i += 1;
+                continue;
+            }
+            let queued_id = *queued_id;
+            if let Some((_, Some(devices))) = self.queued.remove(i) {
+                self.running.insert(queued_id, devices);
+                started.push(queued_id);
+            }
+        }
+        started
+    }
+
+    pub fn is_queued(&self, id: u64) -> bool {
+        self.queued.iter().any(|(queued_id, _)| *queued_id == id)
+    }
+
+    /// Position of an operation in the queue and the length of the queue
+    pub fn queue_position(&self, id: u64) -> Option<(usize, usize)> {
+        self.queued
+            .iter()
+            .position(|(queued_id, _)| *queued_id == id)
+            .map(|i| (i, self.queued.len()))
+    }
+
+    /// Move a queued operation earlier or later in the queue
+    pub fn reorder(&mut self, id: u64, earlier: bool) {
+        if let Some((i, len)) = self.queue_position(id) {
+            if earlier && i > 0 {
+                self.queued.swap(i, i - 1);
+            } else if !earlier && i + 1 < len {
+                self.queued.swap(i, i + 1);
+            }
+        }
+    }
+
+    /// Start a queued operation now, even if it shares devices with running operations.
+    /// Operations whose devices are still being found start on their own once they are known.
+    pub fn promote(&mut self, id: u64) -> bool {
+        let position = self
+            .queued
+            .iter()
+            .position(|(queued_id, devices)| *queued_id == id && devices.is_some());
+        match position {
+            Some(i) => {
+                if let Some((_, Some(devices))) = self.queued.remove(i) {
+                    self.running.insert(id, devices);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move a queued operation to the end of the queue
+    pub fn defer(&mut self, id: u64) {
+        if let Some((i, _)) = self.queue_position(id) {
+            if let Some(entry) = self.queued.remove(i) {
+                self.queued.push_back(entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::Scheduler;
+
+    fn devices(devices: &[u64]) -> BTreeSet<u64> {
+        devices.iter().copied().collect()
+    }
+
+    #[test]
+    fn shared_devices_run_in_order() {
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.submit(0, devices(&[1, 2])));
+        assert!(!scheduler.submit(1, devices(&[2])));
+        assert!(scheduler.submit(2, devices(&[3])));
+        assert!(!scheduler.submit(3, devices(&[2, 3])));
+        assert!(scheduler.submit(4, devices(&[])));
+
+        // Operation 3 must wait for 2 and for 1, which is ahead of it
+        assert_eq!(scheduler.finish(0), vec![1]);
+        assert_eq!(scheduler.finish(2), Vec::<u64>::new());
+        assert_eq!(scheduler.finish(1), vec![3]);
+    }
+
+    #[test]
+    fn reorder_promote_defer() {
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.submit(0, devices(&[1])));
+        assert!(!scheduler.submit(1, devices(&[1])));
+        assert!(!scheduler.submit(2, devices(&[1])));
+        assert!(!scheduler.submit(3, devices(&[1])));
+
+        scheduler.reorder(3, true);
+        assert_eq!(scheduler.queue_position(3), Some((1, 3)));
+        scheduler.defer(1);
+        assert_eq!(scheduler.queue_position(1), Some((2, 3)));
+        assert!(scheduler.promote(2));
+        assert!(!scheduler.is_queued(2));
+
+        assert_eq!(scheduler.finish(0), Vec::<u64>::new());
+        assert_eq!(scheduler.finish(2), vec![3]);
+        assert_eq!(scheduler.finish(3), vec![1]);
+    }
+
+    #[test]
+    fn reserved_operations_keep_their_place() {
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.submit(0, devices(&[1])));
+        scheduler.reserve(1);
+        scheduler.reserve(2);
+        scheduler.reserve(3);
+
+        // Operations on other devices do not wait for earlier devices to be found
+        assert!(scheduler.submit(3, devices(&[2])));
+        assert!(!scheduler.promote(1));
+
+        // Operations found first still run after operations reserved before them
+        assert!(!scheduler.submit(2, devices(&[1])));
+        assert!(!scheduler.submit(1, devices(&[1])));
+        assert_eq!(scheduler.finish(0), vec![1]);
+        assert_eq!(scheduler.finish(1), vec![2]);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    io::{self, Seek, Write},
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+#[cfg(target_os = "linux")]
+use super::scheduler::path_device;
+use super::{Controller, FailedPath};
+use crate::{config::ShredPattern, fl, tab};
+
+// Filesystems that write changes to new blocks, so old contents survive overwriting, by the
+// magic number that statfs reports for them
+#[cfg(target_os = "linux")]
+const COPY_ON_WRITE: &[(u32, &str)] = &[
+    (0xca45_1a4e, "bcachefs"),
+    (0x9123_683e, "btrfs"),
+    (0xf2f5_2010, "f2fs"),
+    (0x3434, "nilfs2"),
+    (0x2fc1_2fc1, "zfs"),
+];
+
+/// Reasons overwriting may not destroy the contents of paths, such as copy-on-write
+/// filesystems, network filesystems and solid state drives
+pub fn shred_warnings(paths: &[PathBuf]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut warn = |warning: String| {
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    };
+    for path in paths.iter() {
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+        if tab::fs_kind(&metadata) != tab::FsKind::Local {
+            warn(fl!("shred-warning-remote"));
+        }
+        if let Some(fs_type) = copy_on_write_type(path) {
+            warn(fl!("shred-warning-copy-on-write", fs_type = fs_type));
+        }
+        if is_solid_state(path) {
+            warn(fl!("shred-warning-solid-state"));
+        }
+    }
+    warnings
+}
+
+// Subvolumes have device numbers of their own that are not listed as mounts, so the type is
+// asked from the filesystem holding the path instead
+#[cfg(target_os = "linux")]
+fn copy_on_write_type(path: &Path) -> Option<&'static str> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    if unsafe { libc::statfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let f_type = unsafe { stat.assume_init() }.f_type;
+    COPY_ON_WRITE
+        .iter()
+        .find(|(magic, _)| f_type as u32 == *magic)
+        .map(|(_, fs_type)| *fs_type)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_on_write_type(_path: &Path) -> Option<&'static str> {
+    //TODO: support other platforms?
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn is_solid_state(path: &Path) -> bool {
+    let Some(dev) = path_device(path) else {
+        return false;
+    };
+    let rotational = format!(
+        "/sys/dev/block/{}:{}/queue/rotational",
+        libc::major(dev),
+        libc::minor(dev)
+    );
+    fs::read_to_string(rotational).is_ok_and(|rotational| rotational.trim() == "0")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_solid_state(_path: &Path) -> bool {
+    //TODO: support other platforms?
+    false
+}
+
+/// Overwrite the contents of files before removing them, recursing into directories. Errors
+/// are collected in `failed` instead of stopping if `continue_on_error` is set.
+pub fn shred(
+    paths: &[PathBuf],
+    passes: u8,
+    pattern: ShredPattern,
+    controller: &Controller,
+    continue_on_error: bool,
+    failed: &mut Vec<FailedPath>,
+) -> Result<(), String> {
+    // Directories are listed after their contents so they are empty when removed
+    let mut entries = Vec::new();
+    for path in paths.iter() {
+        for entry in WalkDir::new(path).contents_first(true) {
+            check(controller)?;
+            match entry {
+                Ok(entry) => {
+                    let is_file = entry.file_type().is_file();
+                    let size = if is_file {
+                        entry.metadata().map_or(0, |metadata| metadata.len())
+                    } else {
+                        0
+                    };
+                    entries.push((entry.into_path(), is_file, size));
+                }
+                Err(err) if continue_on_error => {
+                    failed.push(FailedPath {
+                        from: err.path().unwrap_or(path).to_path_buf(),
+                        to: None,
+                        error: err.to_string(),
+                    });
+                }
+                Err(err) => return Err(format!("failed to walk directory {:?}: {}", path, err)),
+            }
+        }
+    }
+
+    controller.set_totals(
+        entries.iter().filter(|(_, is_file, _)| *is_file).count(),
+        entries
+            .iter()
+            .map(|(_, _, size)| size * u64::from(passes))
+            .sum(),
+    );
+    let mut buf = vec![0u8; 128 * 1024];
+    for (path, is_file, size) in entries.iter() {
+        check(controller)?;
+        controller.set_current_file(path);
+
+        // Skip the contents of directories that could not be read
+        if failed.iter().any(|failed| path.starts_with(&failed.from)) {
+            continue;
+        }
+
+        let result = match path.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir(path),
+            // Symbolic links and special files have no contents of their own
+            Ok(metadata) if !metadata.is_file() => fs::remove_file(path),
+            Ok(_) => overwrite(path, *size, passes, pattern, controller, &mut buf)
+                .and_then(|()| fs::remove_file(path)),
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(()) => {
+                if *is_file {
+                    controller.file_done();
+                }
+            }
+            Err(err) if continue_on_error && !controller.is_cancelled() => {
+                log::warn!("failed to shred {:?}: {}", path, err);
+                failed.push(FailedPath {
+                    from: path.clone(),
+                    to: None,
+                    error: err.to_string(),
+                });
+            }
+            Err(err) => return Err(format!("failed to shred {:?}: {}", path, err)),
+        }
+    }
+    Ok(())
+}
+
+fn check(controller: &Controller) -> Result<(), String> {
+    futures::executor::block_on(controller.check())
+}
+
+fn overwrite(
+    path: &Path,
+    size: u64,
+    passes: u8,
+    pattern: ShredPattern,
+    controller: &Controller,
+    buf: &mut [u8],
+) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    if pattern == ShredPattern::Zero {
+        buf.fill(0);
+    }
+    for _pass in 0..passes {
+        file.rewind()?;
+        let mut written = 0;
+        while written < size {
+            check(controller).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            let count = (size - written).min(buf.len() as u64) as usize;
+            if pattern == ShredPattern::Random {
+                fastrand::fill(&mut buf[..count]);
+            }
+            file.write_all(&buf[..count])?;
+            written += count as u64;
+            controller.add_bytes(count as u64);
+        }
+        // Each pass must reach the device before the next one replaces it in the cache
+        file.sync_all()?;
+    }
+    // Also hide the size of the file before it is removed
+    file.set_len(0)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io};
+
+    use super::shred;
+    use crate::{
+        app::test_utils::{simple_fs, NAME_LEN, NUM_DIRS, NUM_FILES, NUM_HIDDEN, NUM_NESTED},
+        config::ShredPattern,
+        operation::Controller,
+    };
+
+    #[test]
+    fn shred_removes_directories() -> io::Result<()> {
+        let fs = simple_fs(NUM_FILES, NUM_HIDDEN, NUM_DIRS, NUM_NESTED, NAME_LEN)?;
+        let paths: Vec<_> = fs::read_dir(fs.path())?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<_>>()?;
+
+        let controller = Controller::default();
+        let mut failed = Vec::new();
+        shred(
+            &paths,
+            2,
+            ShredPattern::Random,
+            &controller,
+            false,
+            &mut failed,
+        )
+        .expect("Shred should have succeeded");
+
+        assert!(failed.is_empty());
+        assert_eq!(
+            fs::read_dir(fs.path())?.count(),
+            0,
+            "Everything should be removed"
+        );
+        let details = controller.details();
+        assert_eq!(details.files_done, details.files_total);
+        assert_eq!(details.bytes_done, details.bytes_total);
+
+        Ok(())
+    }
+}
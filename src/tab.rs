@@ -51,7 +51,7 @@ use std::{
     borrow::Cow,
     cell::Cell,
     cmp::Ordering,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     error::Error,
     fmt::{self, Display},
     fs::{self, File, Metadata},
@@ -68,7 +68,8 @@ use trash::TrashItemSize;
 use walkdir::WalkDir;
 
 use crate::{
-    app::{Action, PreviewItem, PreviewKind},
+    app::{Action, ArchiveType, PreviewItem, PreviewKind},
+    archive,
     clipboard::{ClipboardCopy, ClipboardKind, ClipboardPaste},
     config::{DesktopConfig, IconSizes, TabConfig, ICON_SCALE_MAX, ICON_SIZE_GRID},
     dialog::DialogKind,
@@ -364,7 +365,7 @@ pub fn trash_icon_symbolic(icon_size: u16) -> widget::icon::Handle {
 }
 
 //TODO: translate, add more levels?
-fn format_size(size: u64) -> String {
+pub(crate) fn format_size(size: u64) -> String {
     const KB: u64 = 1000;
     const MB: u64 = 1000 * KB;
     const GB: u64 = 1000 * MB;
@@ -1136,6 +1137,136 @@ pub fn scan_trash(sizes: IconSizes) -> Vec<Item> {
     items
 }
 
+/// Item for an entry inside an archive, which has `children` items if it is a directory
+pub fn item_from_archive_entry(
+    archive_path: &Path,
+    entry: &archive::ArchiveEntry,
+    children: usize,
+    sizes: IconSizes,
+) -> Item {
+    let name = entry
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let display_name = Item::display_name(&name);
+    let hidden = name.starts_with('.');
+
+    // Items are looked up by name, as they do not exist on disk
+    let (mime, icon_handle_grid, icon_handle_list, icon_handle_list_condensed) = if entry.is_dir {
+        let path = archive_path.join(&entry.path);
+        (
+            //TODO: make this a static
+            "inode/directory".parse().unwrap(),
+            folder_icon(&path, sizes.grid()),
+            folder_icon(&path, sizes.list()),
+            folder_icon(&path, sizes.list_condensed()),
+        )
+    } else {
+        let mime = mime_for_path(&entry.path, None, true);
+        (
+            mime.clone(),
+            mime_icon(mime.clone(), sizes.grid()),
+            mime_icon(mime.clone(), sizes.list()),
+            mime_icon(mime, sizes.list_condensed()),
+        )
+    };
+
+    Item {
+        name,
+        display_name,
+        is_mount_point: false,
+        metadata: ItemMetadata::Archive {
+            mtime: entry.mtime,
+            size: entry.size,
+            children_opt: entry.is_dir.then_some(children),
+        },
+        hidden,
+        location_opt: Some(Location::Archive(
+            archive_path.to_path_buf(),
+            entry.path.clone(),
+        )),
+        mime,
+        icon_handle_grid,
+        icon_handle_list,
+        icon_handle_list_condensed,
+        thumbnail_opt: None,
+        button_id: widget::Id::unique(),
+        pos_opt: Cell::new(None),
+        rect_opt: Cell::new(None),
+        selected: false,
+        highlighted: false,
+        overlaps_drag_rect: false,
+        dir_size: DirSize::NotDirectory,
+        cut: false,
+    }
+}
+
+pub fn scan_archive(archive_path: &Path, dir: &Path, sizes: IconSizes) -> Vec<Item> {
+    let entries = match archive::list_cached(archive_path) {
+        Ok(ok) => ok,
+        Err(err) => {
+            log::warn!("failed to read archive {:?}: {}", archive_path, err);
+            return Vec::new();
+        }
+    };
+
+    let mut items: Vec<Item> = archive::read_dir(&entries, dir)
+        .into_iter()
+        .map(|(entry, children)| item_from_archive_entry(archive_path, &entry, children, sizes))
+        .collect();
+    items.sort_by(|a, b| match (a.metadata.is_dir(), b.metadata.is_dir()) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => LANGUAGE_SORTER.compare(&a.display_name, &b.display_name),
+    });
+    items
+}
+
+// Send thumbnails of files inside an archive as they are read, in a single pass over the
+// archive. Files that cannot have thumbnails or could not be read are not tried again.
+fn archive_thumbnails_blocking(
+    archive_path: &Path,
+    files: Vec<(PathBuf, Option<u64>, Mime)>,
+    mut output: futures::channel::mpsc::Sender<Message>,
+) {
+    let mut send = |path: &Path, thumbnail| {
+        let location = Location::Archive(archive_path.to_path_buf(), path.to_path_buf());
+        futures::executor::block_on(output.send(Message::Thumbnail(location, thumbnail))).is_ok()
+    };
+
+    let mut reads = Vec::new();
+    let mut mimes = BTreeMap::new();
+    for (path, limit_opt, mime) in files {
+        match limit_opt {
+            Some(limit) => {
+                reads.push((path.clone(), limit));
+                mimes.insert(path, mime);
+            }
+            None => {
+                if !send(&path, ItemThumbnail::NotImage) {
+                    return;
+                }
+            }
+        }
+    }
+
+    let result = archive::read_each(archive_path, &reads, |path, data| {
+        match mimes.remove(path) {
+            Some(mime) => send(path, ItemThumbnail::from_archive(data, &mime)),
+            None => true,
+        }
+    });
+    if let Err(err) = result {
+        log::warn!("failed to read thumbnails in {:?}: {}", archive_path, err);
+    }
+    for path in mimes.into_keys() {
+        if !send(&path, ItemThumbnail::NotImage) {
+            return;
+        }
+    }
+}
+
 fn uri_to_path(uri: String) -> Option<PathBuf> {
     //TODO support for external drive or cloud?
     uri.strip_prefix("file://").map(PathBuf::from)
@@ -1360,6 +1491,8 @@ impl From<Location> for EditLocation {
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Location {
+    /// Directory inside an archive, as the path of the archive and the path inside it
+    Archive(PathBuf, PathBuf),
     Desktop(PathBuf, String, DesktopConfig),
     Network(String, String, Option<PathBuf>),
     Path(PathBuf),
@@ -1371,6 +1504,9 @@ pub enum Location {
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Self::Archive(archive, path) => {
+                write!(f, "{} in {}", path.display(), archive.display())
+            }
             Self::Desktop(path, display, ..) => {
                 write!(f, "{} on display {display}", path.display())
             }
@@ -1418,6 +1554,15 @@ impl Location {
         }
     }
 
+    /// Path used to copy this location, which for items inside archives is a path below the
+    /// archive that is extracted when pasted
+    pub fn copy_path_opt(&self) -> Option<PathBuf> {
+        match self {
+            Self::Archive(archive, path) => Some(archive.join(path)),
+            _ => self.path_opt().cloned(),
+        }
+    }
+
     pub fn with_path(&self, path: PathBuf) -> Self {
         match self {
             Self::Desktop(_, display, desktop_config) => {
@@ -1435,6 +1580,7 @@ impl Location {
 
     pub fn scan(&self, sizes: IconSizes) -> (Option<Item>, Vec<Item>) {
         let items = match self {
+            Self::Archive(archive, path) => scan_archive(archive, path, sizes),
             Self::Desktop(path, display, desktop_config) => {
                 scan_desktop(path, display, *desktop_config, sizes)
             }
@@ -1463,6 +1609,10 @@ impl Location {
 
     pub fn title(&self) -> String {
         match self {
+            Self::Archive(archive, path) => match path.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => folder_name(archive).0,
+            },
             Self::Desktop(path, _, _) => {
                 let (name, _) = folder_name(path);
                 name
@@ -1505,11 +1655,13 @@ impl fmt::Debug for TaskWrapper {
 pub enum Command {
     Action(Action),
     AddNetworkDrive,
+    AddToArchive(PathBuf, Vec<PathBuf>),
     AddToSidebar(PathBuf),
     AutoScroll(Option<f32>),
     ChangeLocation(String, Location, Option<Vec<PathBuf>>),
     Delete(Vec<PathBuf>),
     DropFiles(PathBuf, ClipboardPaste),
+    DropLinks(PathBuf, ClipboardPaste),
     EmptyTrash,
     #[cfg(feature = "desktop")]
     ExecEntryAction(cosmic::desktop::DesktopEntryData, usize),
@@ -1578,7 +1730,7 @@ pub enum Message {
     SetPermissions(PathBuf, u32),
     SetSort(HeadingOptions, bool),
     TabComplete(PathBuf, Vec<(String, PathBuf)>),
-    Thumbnail(PathBuf, ItemThumbnail),
+    Thumbnail(Location, ItemThumbnail),
     View(View),
     ToggleSort(HeadingOptions),
     Drop(Option<(Location, ClipboardPaste)>),
@@ -1640,6 +1792,11 @@ pub enum ItemMetadata {
         size_opt: Option<u64>,
         children_opt: Option<usize>,
     },
+    Archive {
+        mtime: Option<SystemTime>,
+        size: u64,
+        children_opt: Option<usize>,
+    },
 }
 
 impl ItemMetadata {
@@ -1654,6 +1811,7 @@ impl ItemMetadata {
             Self::SimpleFile { .. } => false,
             #[cfg(feature = "gvfs")]
             Self::GvfsPath { children_opt, .. } => children_opt.is_some(),
+            Self::Archive { children_opt, .. } => children_opt.is_some(),
         }
     }
 
@@ -1664,6 +1822,7 @@ impl ItemMetadata {
             Self::GvfsPath { mtime, .. } => {
                 Some(SystemTime::UNIX_EPOCH + Duration::from_secs(*mtime))
             }
+            Self::Archive { mtime, .. } => *mtime,
             _ => None,
         }
     }
@@ -1680,6 +1839,12 @@ impl ItemMetadata {
             },
             #[cfg(feature = "gvfs")]
             Self::GvfsPath { size_opt, .. } => *size_opt,
+            Self::Archive {
+                size, children_opt, ..
+            } => match children_opt {
+                Some(_) => None,
+                None => Some(*size),
+            },
             _ => None,
         }
     }
@@ -1864,6 +2029,33 @@ impl ItemThumbnail {
         ItemThumbnail::NotImage
     }
 
+    /// Number of bytes of a file inside an archive needed for its thumbnail, if it can have one.
+    /// Images are read whole and text files only from the start.
+    pub fn archive_limit(metadata: &ItemMetadata, mime: &mime::Mime) -> Option<u64> {
+        let size = metadata.file_size().unwrap_or_default();
+        if mime.type_() == mime::IMAGE && size <= 64 * 1000 * 1000 {
+            Some(size)
+        } else if mime.type_() == mime::TEXT {
+            Some(64 * 1000)
+        } else {
+            None
+        }
+    }
+
+    /// Thumbnail of an image or the start of a text file inside an archive, which is read
+    /// without extracting it
+    pub fn from_archive(data: Vec<u8>, mime: &mime::Mime) -> Self {
+        if mime.type_() == mime::TEXT {
+            ItemThumbnail::Text(widget::text_editor::Content::with_text(
+                &String::from_utf8_lossy(&data),
+            ))
+        } else if mime.subtype() == mime::SVG {
+            ItemThumbnail::Svg(widget::svg::Handle::from_memory(data))
+        } else {
+            ItemThumbnail::Image(widget::image::Handle::from_bytes(data), None)
+        }
+    }
+
     fn generate_thumbnail_external(
         path: &Path,
         mime: &mime::Mime,
@@ -1981,6 +2173,14 @@ impl Item {
         self.location_opt.as_ref()?.path_opt()
     }
 
+    // Folders accept dropped items, and so do tar and zip archives on disk, which offer to add
+    // them
+    fn is_dnd_dest(&self) -> bool {
+        self.metadata.is_dir()
+            || (matches!(self.metadata, ItemMetadata::Path { .. })
+                && ArchiveType::from_mime(self.mime.essence_str()).is_some())
+    }
+
     pub fn can_gallery(&self) -> bool {
         self.mime.type_() == mime::IMAGE || self.mime.type_() == mime::TEXT
     }
@@ -2110,6 +2310,25 @@ impl Item {
 
                 dir_children_count = *children_opt;
             }
+            ItemMetadata::Archive {
+                mtime,
+                size,
+                children_opt,
+            } => {
+                details = details.push(widget::text::body(match children_opt {
+                    Some(children) => fl!("items", items = children),
+                    None => fl!("item-size", size = format_size(*size)),
+                }));
+                if let Some(time) = mtime {
+                    let date_time_formatter = date_time_formatter(military_time);
+                    let time_formatter = time_formatter(military_time);
+                    details = details.push(widget::text::body(fl!(
+                        "item-modified",
+                        modified =
+                            format_time(*time, &date_time_formatter, &time_formatter).to_string()
+                    )));
+                }
+            }
             _ => {
                 //TODO: other metadata types
             }
@@ -2291,6 +2510,30 @@ impl Item {
                     )));
                 }
             }
+            ItemMetadata::Archive {
+                mtime,
+                size,
+                children_opt,
+            } => {
+                match children_opt {
+                    Some(children) => {
+                        column = column.push(widget::text::body(format!("Items: {}", children)));
+                    }
+                    None => {
+                        column = column
+                            .push(widget::text::body(format!("Size: {}", format_size(*size))));
+                    }
+                }
+                if let Some(time) = mtime {
+                    let date_time_formatter = date_time_formatter(military_time);
+                    let time_formatter = time_formatter(military_time);
+
+                    column = column.push(widget::text::body(format!(
+                        "Last modified: {}",
+                        format_time(*time, &date_time_formatter, &time_formatter)
+                    )));
+                }
+            }
             _ => {
                 //TODO: other metadata
             }
@@ -2398,6 +2641,8 @@ pub struct Tab {
     pub(crate) parent_item_opt: Option<Item>,
     pub(crate) items_opt: Option<Vec<Item>>,
     pub dnd_hovered: Option<(Location, Instant)>,
+    /// Copied or cut items include folders, which cannot be pasted as hard links
+    pub clipboard_dirs: bool,
     scrollable_id: widget::Id,
     select_focus: Option<usize>,
     select_range: Option<(usize, usize)>,
@@ -2513,6 +2758,7 @@ impl Tab {
             select_range: None,
             clicked: None,
             dnd_hovered: None,
+            clipboard_dirs: false,
             selected_clicked: false,
             modifiers: Modifiers::default(),
             last_right_click: None,
@@ -2587,6 +2833,21 @@ impl Tab {
         locations
     }
 
+    /// Paths of the selected items, in the order they are displayed
+    pub fn selected_paths_sorted(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Some(items) = self.column_sort() {
+            for (_, item) in items {
+                if item.selected {
+                    if let Some(path) = item.path_opt() {
+                        paths.push(path.to_path_buf());
+                    }
+                }
+            }
+        }
+        paths
+    }
+
     pub fn select_all(&mut self) {
         if let Some(ref mut items) = self.items_opt {
             for item in items.iter_mut() {
@@ -2902,6 +3163,9 @@ impl Tab {
                             cd = Some(location.clone());
                         } else if let Some(path) = location.path_opt() {
                             commands.push(Command::OpenFile(vec![path.to_path_buf()]));
+                        } else if let Location::Archive(..) = location {
+                            // Files inside archives cannot be opened, but can be previewed
+                            commands.push(Command::Preview(PreviewKind::Selected));
                         } else {
                             log::warn!("no path for item {:?}", clicked_item);
                         }
@@ -3464,10 +3728,24 @@ impl Tab {
             Message::LocationUp => {
                 // Sets location to the path's parent
                 // Does nothing if path is root or location is Trash
-                if let Location::Path(ref path) = self.location {
-                    if let Some(parent) = path.parent() {
-                        cd = Some(Location::Path(parent.to_owned()));
+                match &self.location {
+                    Location::Path(path) => {
+                        if let Some(parent) = path.parent() {
+                            cd = Some(Location::Path(parent.to_owned()));
+                        }
+                    }
+                    // Leaving the root of an archive goes to the folder containing it
+                    Location::Archive(archive, path) => {
+                        cd = match path.parent() {
+                            Some(parent) => {
+                                Some(Location::Archive(archive.clone(), parent.to_owned()))
+                            }
+                            None => archive
+                                .parent()
+                                .map(|parent| Location::Path(parent.to_owned())),
+                        };
                     }
+                    _ => {}
                 }
             }
             Message::ModifiersChanged(modifiers) => {
@@ -3724,9 +4002,8 @@ impl Tab {
                     }
                 }
             }
-            Message::Thumbnail(path, thumbnail) => {
+            Message::Thumbnail(location, thumbnail) => {
                 if let Some(ref mut items) = self.items_opt {
-                    let location = Location::Path(path);
                     for item in items.iter_mut() {
                         if item.location_opt.as_ref() == Some(&location) {
                             let handle_opt = match &thumbnail {
@@ -3780,6 +4057,21 @@ impl Tab {
             Message::Drop(Some((to, mut from))) => {
                 self.dnd_hovered = None;
                 match to {
+                    // Dropping onto an archive offers to add to it
+                    Location::Desktop(to, ..) | Location::Path(to)
+                        if to.is_file() && ArchiveType::from_path(&to).is_some() =>
+                    {
+                        from.paths.retain(|path| path != &to);
+                        if !from.paths.is_empty() {
+                            commands.push(Command::AddToArchive(to, from.paths))
+                        }
+                    }
+                    // Holding Ctrl and Shift while dropping creates links, even in the same folder
+                    Location::Desktop(to, ..) | Location::Path(to)
+                        if modifiers.control() && modifiers.shift() =>
+                    {
+                        commands.push(Command::DropLinks(to, from))
+                    }
                     Location::Desktop(to, ..) | Location::Path(to) => {
                         if let Ok(entries) = fs::read_dir(&to) {
                             for i in entries.into_iter().filter_map(|e| e.ok()) {
@@ -3815,7 +4107,9 @@ impl Tab {
             }
             Message::DndEnter(loc) => {
                 self.dnd_hovered = Some((loc.clone(), Instant::now()));
-                if loc != self.location {
+                // Archives accept drops but are not opened by hovering
+                let is_file = matches!(&loc, Location::Path(path) if path.is_file());
+                if loc != self.location && !is_file {
                     commands.push(Command::Iced(
                         cosmic::Task::perform(
                             async move {
@@ -3970,6 +4264,12 @@ impl Tab {
                             Some(child_count) => (true, *child_count as u64),
                             None => (false, size_opt.unwrap_or_default()),
                         },
+                        ItemMetadata::Archive {
+                            size, children_opt, ..
+                        } => match children_opt {
+                            Some(child_count) => (true, *child_count as u64),
+                            None => (false, *size),
+                        },
                     };
                     let (a_is_entry, a_size) = get_size(a.1);
                     let (b_is_entry, b_size) = get_size(b.1);
@@ -4531,6 +4831,48 @@ impl Tab {
                         .into(),
                 );
             }
+            Location::Archive(archive, path) => {
+                // The folder containing the archive, then the archive and the folders inside it
+                if let Some(parent) = archive.parent() {
+                    let (name, _) = folder_name(parent);
+                    children.push(
+                        widget::button::custom(widget::text::body(name))
+                            .padding(space_xxxs)
+                            .on_press(Message::Location(Location::Path(parent.to_path_buf())))
+                            .class(theme::Button::Link)
+                            .into(),
+                    );
+                }
+                let mut ancestors: Vec<&Path> = path.ancestors().collect();
+                ancestors.reverse();
+                for ancestor in ancestors {
+                    let name = match ancestor.file_name() {
+                        Some(name) => name.to_string_lossy().to_string(),
+                        None => folder_name(archive).0,
+                    };
+                    children.push(
+                        widget::icon::from_name("go-next-symbolic")
+                            .size(16)
+                            .icon()
+                            .into(),
+                    );
+                    let text = if ancestor == path {
+                        widget::text::heading(name)
+                    } else {
+                        widget::text::body(name)
+                    };
+                    children.push(
+                        widget::button::custom(text.wrapping(text::Wrapping::None))
+                            .padding(space_xxxs)
+                            .on_press(Message::Location(Location::Archive(
+                                archive.clone(),
+                                ancestor.to_path_buf(),
+                            )))
+                            .class(theme::Button::Link)
+                            .into(),
+                    );
+                }
+            }
         }
 
         for child in children {
@@ -4747,7 +5089,7 @@ impl Tab {
                     }
 
                     let column: Element<Message> =
-                        if item.metadata.is_dir() && item.location_opt.is_some() {
+                        if item.is_dnd_dest() && item.location_opt.is_some() {
                             self.dnd_dest(&item.location_opt.clone().unwrap(), column)
                         } else {
                             column.into()
@@ -5009,6 +5351,10 @@ impl Tab {
                             Some(mtime) => self.format_time(mtime).to_string(),
                             None => String::new(),
                         },
+                        ItemMetadata::Archive { mtime, .. } => match mtime {
+                            Some(mtime) => self.format_time(*mtime).to_string(),
+                            None => String::new(),
+                        },
                         _ => String::new(),
                     };
 
@@ -5067,6 +5413,18 @@ impl Tab {
                             }
                             None => format_size(size_opt.unwrap_or_default()),
                         },
+                        ItemMetadata::Archive {
+                            size, children_opt, ..
+                        } => match children_opt {
+                            Some(child_count) => {
+                                if *child_count == 1 {
+                                    format!("{} item", child_count)
+                                } else {
+                                    format!("{} items", child_count)
+                                }
+                            }
+                            None => format_size(*size),
+                        },
                     };
 
                     let row = if condensed {
@@ -5166,7 +5524,7 @@ impl Tab {
 
                     let button_row = button(row.into());
                     let button_row: Element<_> =
-                        if item.metadata.is_dir() && item.location_opt.is_some() {
+                        if item.is_dnd_dest() && item.location_opt.is_some() {
                             self.dnd_dest(item.location_opt.as_ref().unwrap(), button_row)
                         } else {
                             button_row.into()
@@ -5337,7 +5695,7 @@ impl Tab {
                 items
                     .iter()
                     .filter(|item| item.selected)
-                    .filter_map(|item| item.path_opt().cloned())
+                    .filter_map(|item| item.location_opt.as_ref()?.copy_path_opt())
                     .collect::<Vec<PathBuf>>()
             })
             .unwrap_or_default();
@@ -5524,6 +5882,11 @@ impl Tab {
                     }
                 }
 
+                if let Some(Location::Archive(..)) = &item.location_opt {
+                    // Read together below
+                    continue;
+                }
+
                 let Some(path) = item.path_opt().map(|path| path.to_path_buf()) else {
                     continue;
                 };
@@ -5548,7 +5911,7 @@ impl Tab {
                                     let thumbnail =
                                         ItemThumbnail::new(&path, metadata, mime, THUMBNAIL_SIZE);
                                     log::debug!("thumbnailed {:?} in {:?}", path, start.elapsed());
-                                    Message::Thumbnail(path.clone(), thumbnail)
+                                    Message::Thumbnail(Location::Path(path.clone()), thumbnail)
                                 })
                                 .await
                                 .unwrap()
@@ -5571,6 +5934,51 @@ impl Tab {
                 }
             }
 
+            // Files inside archives are read together, as compressed streams are read from the
+            // start to reach each file. The subscription is identified by all visible files, so
+            // it keeps running while thumbnails arrive.
+            let mut archive_thumbnails =
+                BTreeMap::<PathBuf, (Vec<PathBuf>, Vec<(PathBuf, Option<u64>, Mime)>)>::new();
+            for item in items.iter() {
+                let Some(Location::Archive(archive_path, path)) = &item.location_opt else {
+                    continue;
+                };
+                if item.metadata.is_dir()
+                    || !item
+                        .rect_opt
+                        .get()
+                        .is_some_and(|rect| rect.intersects(&visible_rect))
+                {
+                    continue;
+                }
+                let (paths, files) = archive_thumbnails.entry(archive_path.clone()).or_default();
+                paths.push(path.clone());
+                if item.thumbnail_opt.is_none() {
+                    files.push((
+                        path.clone(),
+                        ItemThumbnail::archive_limit(&item.metadata, &item.mime),
+                        item.mime.clone(),
+                    ));
+                }
+            }
+            for (archive_path, (paths, files)) in archive_thumbnails {
+                if files.is_empty() {
+                    continue;
+                }
+                subscriptions.push(Subscription::run_with_id(
+                    ("archive_thumbnails", archive_path.clone(), paths),
+                    stream::channel(1, |output| async move {
+                        tokio::task::spawn_blocking(move || {
+                            archive_thumbnails_blocking(&archive_path, files, output)
+                        })
+                        .await
+                        .unwrap();
+
+                        std::future::pending().await
+                    }),
+                ));
+            }
+
             if preview {
                 // Load directory size for selected items
                 if let Some(item) = items
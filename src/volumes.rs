@@ -0,0 +1,698 @@
+//! Archives split into several files, read back as one archive
+
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{fl, mime_icon::mime_for_path};
+
+const EOCD_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// How the volumes of a split archive are named
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Naming {
+    /// `archive.zip.001`, `archive.zip.002`, ... with the given number of digits
+    Numbered(usize),
+    /// `archive.tar.gz.aa`, `archive.tar.gz.ab`, ... as written by `split`
+    Lettered,
+    /// `archive.z01`, `archive.z02`, ... and finally `archive.zip`, as written by zip tools.
+    /// Offsets in these archives are relative to the volume they point into.
+    Zip,
+}
+
+impl Naming {
+    fn volume_name(self, archive_name: &str, index: usize) -> String {
+        match self {
+            Self::Numbered(digits) => format!("{}.{:0digits$}", archive_name, index),
+            Self::Lettered => {
+                let letter = |i: usize| char::from(b'a' + (i % 26) as u8);
+                format!(
+                    "{}.{}{}",
+                    archive_name,
+                    letter((index - 1) / 26),
+                    letter(index - 1)
+                )
+            }
+            Self::Zip => format!(
+                "{}.z{:02}",
+                archive_name.strip_suffix(".zip").unwrap_or(archive_name),
+                index
+            ),
+        }
+    }
+}
+
+fn is_archive_name(name: &str) -> bool {
+    crate::archive::MIME_TYPES.contains(&mime_for_path(Path::new(name), None, true).essence_str())
+}
+
+/// Parse the name of a volume into the name of its archive, how its volumes are named and its
+/// index, starting at one. The last volume of split zip archives is not recognized, as it has
+/// the name of the archive.
+fn parse(name: &str) -> Option<(String, Naming, usize)> {
+    let (stem, suffix) = name.rsplit_once('.')?;
+    // Names like `archive.tar.gz` are archives themselves
+    if stem.is_empty() || is_archive_name(name) {
+        return None;
+    }
+    let is_number = |digits: &str| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+    if suffix.len() >= 3 && is_number(suffix) {
+        let index = suffix.parse().ok().filter(|index| *index > 0)?;
+        if is_archive_name(stem) {
+            return Some((stem.to_string(), Naming::Numbered(suffix.len()), index));
+        }
+    } else if let Some(digits) = suffix.strip_prefix('z').filter(|digits| digits.len() >= 2) {
+        if is_number(digits) {
+            let index = digits.parse().ok().filter(|index| *index > 0)?;
+            return Some((format!("{}.zip", stem), Naming::Zip, index));
+        }
+    } else if suffix.len() == 2 && suffix.bytes().all(|b| b.is_ascii_lowercase()) {
+        if is_archive_name(stem) {
+            let bytes = suffix.as_bytes();
+            let index = usize::from(bytes[0] - b'a') * 26 + usize::from(bytes[1] - b'a') + 1;
+            return Some((stem.to_string(), Naming::Lettered, index));
+        }
+    }
+    None
+}
+
+/// Path of the archive that `path` is a volume of, or `path` itself if it is not a volume. This
+/// only looks at the name, so it is cheap enough for choosing mime types and menu items.
+pub fn archive_path(path: &Path) -> PathBuf {
+    match path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(parse)
+    {
+        Some((archive_name, _, _)) => path.with_file_name(archive_name),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Paths that are different volumes of the same archive are extracted once, keeping the first
+pub fn dedup_archives(paths: &mut Vec<PathBuf>) {
+    let mut seen = Vec::new();
+    paths.retain(|path| {
+        let archive = archive_path(path);
+        if seen.contains(&archive) {
+            false
+        } else {
+            seen.push(archive);
+            true
+        }
+    });
+}
+
+fn missing_error(path: &Path) -> io::Error {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        fl!("archive-volume-missing", name = name),
+    )
+}
+
+/// The volumes of a split archive, in order
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VolumeSet {
+    archive_name: String,
+    naming: Naming,
+    pub volumes: Vec<PathBuf>,
+}
+
+impl VolumeSet {
+    /// Find the volumes of the archive that `path` belongs to, or `None` if `path` is not part of
+    /// a split archive. Missing volumes before the last one found are reported as errors.
+    pub fn from_path(path: &Path) -> Option<io::Result<Self>> {
+        let name = path.file_name()?.to_str()?;
+        let dir = path.parent()?;
+        let (archive_name, naming) = match parse(name) {
+            Some((archive_name, naming, _)) => (archive_name, naming),
+            None if name.ends_with(".zip")
+                && dir.join(Naming::Zip.volume_name(name, 1)).exists() =>
+            {
+                (name.to_string(), Naming::Zip)
+            }
+            None => return None,
+        };
+
+        // Volumes may be missing anywhere, so find the last one before looking for gaps
+        let prefix = match naming {
+            Naming::Zip => archive_name.strip_suffix(".zip").unwrap_or(&archive_name),
+            _ => &archive_name,
+        };
+        let mut last = 0;
+        match fs::read_dir(dir) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    if let Some((entry_archive, entry_naming, index)) = entry
+                        .file_name()
+                        .to_str()
+                        .filter(|name| name.starts_with(prefix))
+                        .and_then(parse)
+                    {
+                        if entry_archive == archive_name && entry_naming == naming {
+                            last = last.max(index);
+                        }
+                    }
+                }
+            }
+            Err(err) => return Some(Err(err)),
+        }
+
+        let mut volumes = Vec::with_capacity(last + 1);
+        for index in 1..=last.max(1) {
+            let volume = dir.join(naming.volume_name(&archive_name, index));
+            if !volume.is_file() {
+                return Some(Err(missing_error(&volume)));
+            }
+            volumes.push(volume);
+        }
+        if naming == Naming::Zip {
+            let volume = dir.join(&archive_name);
+            if !volume.is_file() {
+                return Some(Err(missing_error(&volume)));
+            }
+            volumes.push(volume);
+        }
+
+        Some(Ok(Self {
+            archive_name,
+            naming,
+            volumes,
+        }))
+    }
+
+    /// The volume after the last one found, if the last one is as large as the first. Archives
+    /// cut into volumes of a fixed size give no other sign that their last volumes are missing.
+    pub fn missing_after(&self) -> Option<PathBuf> {
+        if self.naming == Naming::Zip {
+            // The last volume of split zip archives records how many there are
+            return None;
+        }
+        let first = fs::metadata(self.volumes.first()?).ok()?.len();
+        let last = fs::metadata(self.volumes.last()?).ok()?.len();
+        if first != last {
+            return None;
+        }
+        let dir = self.volumes.first()?.parent()?;
+        Some(
+            dir.join(
+                self.naming
+                    .volume_name(&self.archive_name, self.volumes.len() + 1),
+            ),
+        )
+    }
+
+    /// Total size of all volumes
+    pub fn size(&self) -> u64 {
+        self.volumes
+            .iter()
+            .map(|volume| fs::metadata(volume).map_or(0, |metadata| metadata.len()))
+            .sum()
+    }
+
+    /// Read the volumes as one archive
+    pub fn open(&self) -> io::Result<VolumeReader> {
+        let mut parts = Vec::with_capacity(self.volumes.len());
+        for volume in self.volumes.iter() {
+            parts.push(Source::File(fs::File::open(volume)?));
+        }
+        let reader = VolumeReader::new(parts)?;
+        if self.naming == Naming::Zip {
+            join_zip(reader, self)
+        } else {
+            Ok(reader)
+        }
+    }
+}
+
+/// Size of an archive, adding up its volumes if it is split
+pub fn size(path: &Path) -> u64 {
+    match VolumeSet::from_path(path) {
+        Some(Ok(set)) => set.size(),
+        _ => fs::metadata(path).map_or(0, |metadata| metadata.len()),
+    }
+}
+
+/// Open an archive, joining its volumes if it is split
+pub fn open(path: &Path) -> io::Result<VolumeReader> {
+    match VolumeSet::from_path(path) {
+        Some(set) => set?.open(),
+        None => VolumeReader::new(vec![Source::File(fs::File::open(path)?)]),
+    }
+}
+
+enum Source {
+    File(fs::File),
+    Memory(Vec<u8>),
+}
+
+struct Part {
+    source: Source,
+    start: u64,
+    len: u64,
+}
+
+/// Reader over several files as if they were one
+pub struct VolumeReader {
+    parts: Vec<Part>,
+    pos: u64,
+    len: u64,
+}
+
+impl VolumeReader {
+    fn new(sources: Vec<Source>) -> io::Result<Self> {
+        let mut parts = Vec::with_capacity(sources.len());
+        let mut start = 0;
+        for source in sources {
+            let len = match &source {
+                Source::File(file) => file.metadata()?.len(),
+                Source::Memory(data) => data.len() as u64,
+            };
+            parts.push(Part { source, start, len });
+            start += len;
+        }
+        Ok(Self {
+            parts,
+            pos: 0,
+            len: start,
+        })
+    }
+
+    /// Offsets where each part starts
+    fn starts(&self) -> Vec<u64> {
+        self.parts.iter().map(|part| part.start).collect()
+    }
+
+    fn read_at(&mut self, pos: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; len];
+        self.seek(SeekFrom::Start(pos))?;
+        self.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Replace everything from `pos` on with `data`
+    fn truncate_and_append(&mut self, pos: u64, data: Vec<u8>) {
+        self.parts.retain(|part| part.start < pos);
+        if let Some(part) = self.parts.last_mut() {
+            part.len = part.len.min(pos - part.start);
+        }
+        self.len = pos + data.len() as u64;
+        self.parts.push(Part {
+            source: Source::Memory(data),
+            start: pos,
+            len: self.len - pos,
+        });
+        self.pos = 0;
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let pos = self.pos;
+        let Some(part) = self
+            .parts
+            .iter_mut()
+            .find(|part| pos >= part.start && pos < part.start + part.len)
+        else {
+            return Ok(0);
+        };
+        let offset = pos - part.start;
+        let max = usize::try_from(part.len - offset).unwrap_or(usize::MAX);
+        let buf_len = buf.len().min(max);
+        let count = match &mut part.source {
+            Source::File(file) => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.read(&mut buf[..buf_len])?
+            }
+            Source::Memory(data) => {
+                let offset = offset as usize;
+                buf[..buf_len].copy_from_slice(&data[offset..offset + buf_len]);
+                buf_len
+            }
+        };
+        self.pos += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of archive")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Offsets in split zip archives are relative to the volume they point into, which no zip
+/// reader supports. Rewrite the central directory with offsets into the joined volumes, so that
+/// they read like a single archive.
+fn join_zip(mut reader: VolumeReader, set: &VolumeSet) -> io::Result<VolumeReader> {
+    let starts = reader.starts();
+
+    // The end of central directory record is at the end of the last volume, before a comment
+    let tail_len = reader.len.min(22 + 0xFFFF);
+    let tail_start = reader.len - tail_len;
+    let tail = reader.read_at(tail_start, tail_len as usize)?;
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|i| u32_at(&tail, *i) == EOCD_SIGNATURE)
+        .ok_or_else(|| invalid("could not find end of central directory"))?;
+    let comment_len = usize::from(u16_at(&tail, eocd + 20));
+    let comment = tail
+        .get(eocd + 22..eocd + 22 + comment_len)
+        .unwrap_or_default()
+        .to_vec();
+
+    let mut disks = u64::from(u16_at(&tail, eocd + 4)) + 1;
+    let mut cd_disk = u64::from(u16_at(&tail, eocd + 6));
+    let mut entries = u64::from(u16_at(&tail, eocd + 10));
+    let mut cd_size = u64::from(u32_at(&tail, eocd + 12));
+    let mut cd_offset = u64::from(u32_at(&tail, eocd + 16));
+    if eocd >= 20 && u32_at(&tail, eocd - 20) == ZIP64_LOCATOR_SIGNATURE {
+        let locator = eocd - 20;
+        let zip64_disk = u32_at(&tail, locator + 4) as usize;
+        let zip64_offset = starts
+            .get(zip64_disk)
+            .ok_or_else(|| invalid("invalid zip64 end of central directory locator"))?
+            + u64_at(&tail, locator + 8);
+        let record = reader.read_at(zip64_offset, 56)?;
+        if u32_at(&record, 0) != ZIP64_EOCD_SIGNATURE {
+            return Err(invalid("invalid zip64 end of central directory"));
+        }
+        disks = u64::from(u32_at(&record, 16)) + 1;
+        cd_disk = u64::from(u32_at(&record, 20));
+        entries = u64_at(&record, 32);
+        cd_size = u64_at(&record, 40);
+        cd_offset = u64_at(&record, 48);
+    }
+
+    if disks != set.volumes.len() as u64 {
+        // Only volumes before the last one can be missing, as it holds the record
+        let index = set.volumes.len().min(disks as usize);
+        let dir = set.volumes[0].parent().unwrap_or(Path::new(""));
+        return Err(missing_error(
+            &dir.join(set.naming.volume_name(&set.archive_name, index)),
+        ));
+    }
+
+    let cd_start = starts
+        .get(cd_disk as usize)
+        .ok_or_else(|| invalid("invalid end of central directory"))?
+        .saturating_add(cd_offset);
+    // Sizes are checked before allocating, as they come from the archive
+    if cd_start > reader.len || cd_size > reader.len - cd_start {
+        return Err(invalid("invalid end of central directory"));
+    }
+    let old_cd = reader.read_at(cd_start, cd_size as usize)?;
+    let mut cd = Vec::with_capacity(old_cd.len());
+    let mut pos = 0;
+    for _ in 0..entries {
+        if old_cd.len() < pos + 46 || u32_at(&old_cd, pos) != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid("invalid central directory entry"));
+        }
+        let name_len = usize::from(u16_at(&old_cd, pos + 28));
+        let extra_len = usize::from(u16_at(&old_cd, pos + 30));
+        let comment_len = usize::from(u16_at(&old_cd, pos + 32));
+        let end = pos + 46 + name_len + extra_len + comment_len;
+        if old_cd.len() < end {
+            return Err(invalid("invalid central directory entry"));
+        }
+        let mut header = old_cd[pos..pos + 46].to_vec();
+        let name = &old_cd[pos + 46..pos + 46 + name_len];
+        let extra = &old_cd[pos + 46 + name_len..pos + 46 + name_len + extra_len];
+        let entry_comment = &old_cd[pos + 46 + name_len + extra_len..end];
+
+        // Values too large for the header are in the zip64 extra field, in this order
+        let compressed_size = u32_at(&header, 20);
+        let size = u32_at(&header, 24);
+        let mut disk = u64::from(u16_at(&header, 34));
+        let mut offset = u64::from(u32_at(&header, 42));
+        let mut other_extra = Vec::with_capacity(extra.len());
+        let mut zip64_sizes = Vec::new();
+        let mut i = 0;
+        while i + 4 <= extra.len() {
+            let id = u16_at(extra, i);
+            let len = usize::from(u16_at(extra, i + 2));
+            let data = extra.get(i + 4..i + 4 + len).unwrap_or_default();
+            if id == ZIP64_EXTRA_ID {
+                let mut field = 0;
+                let mut next = |bytes: usize| {
+                    let value = data.get(field..field + bytes);
+                    field += bytes;
+                    value
+                };
+                if size == u32::MAX {
+                    zip64_sizes.extend_from_slice(next(8).unwrap_or(&[0; 8]));
+                }
+                if compressed_size == u32::MAX {
+                    zip64_sizes.extend_from_slice(next(8).unwrap_or(&[0; 8]));
+                }
+                if offset == u64::from(u32::MAX) {
+                    offset = next(8).map_or(offset, |value| u64_at(value, 0));
+                }
+                if disk == 0xFFFF {
+                    disk = next(4).map_or(disk, |value| u64::from(u32_at(value, 0)));
+                }
+            } else {
+                other_extra.extend_from_slice(&extra[i..(i + 4 + len).min(extra.len())]);
+            }
+            i += 4 + len;
+        }
+
+        let offset = starts
+            .get(disk as usize)
+            .ok_or_else(|| invalid("invalid central directory entry"))?
+            + offset;
+        let mut zip64 = zip64_sizes;
+        if offset >= u64::from(u32::MAX) {
+            zip64.extend_from_slice(&offset.to_le_bytes());
+            header[42..46].copy_from_slice(&u32::MAX.to_le_bytes());
+        } else {
+            header[42..46].copy_from_slice(&(offset as u32).to_le_bytes());
+        }
+        header[34..36].copy_from_slice(&0u16.to_le_bytes());
+        let mut extra = Vec::with_capacity(other_extra.len() + 4 + zip64.len());
+        if !zip64.is_empty() {
+            extra.extend_from_slice(&ZIP64_EXTRA_ID.to_le_bytes());
+            extra.extend_from_slice(&(zip64.len() as u16).to_le_bytes());
+            extra.extend_from_slice(&zip64);
+        }
+        extra.extend_from_slice(&other_extra);
+        header[30..32].copy_from_slice(&(extra.len() as u16).to_le_bytes());
+
+        cd.extend_from_slice(&header);
+        cd.extend_from_slice(name);
+        cd.extend_from_slice(&extra);
+        cd.extend_from_slice(entry_comment);
+        pos = end;
+    }
+
+    let cd_len = cd.len() as u64;
+    let needs_zip64 =
+        entries >= 0xFFFF || cd_len >= u64::from(u32::MAX) || cd_start >= u64::from(u32::MAX);
+    if needs_zip64 {
+        let record_start = cd_start + cd_len;
+        cd.extend_from_slice(&ZIP64_EOCD_SIGNATURE.to_le_bytes());
+        cd.extend_from_slice(&44u64.to_le_bytes());
+        cd.extend_from_slice(&45u16.to_le_bytes());
+        cd.extend_from_slice(&45u16.to_le_bytes());
+        cd.extend_from_slice(&0u32.to_le_bytes());
+        cd.extend_from_slice(&0u32.to_le_bytes());
+        cd.extend_from_slice(&entries.to_le_bytes());
+        cd.extend_from_slice(&entries.to_le_bytes());
+        cd.extend_from_slice(&cd_len.to_le_bytes());
+        cd.extend_from_slice(&cd_start.to_le_bytes());
+        cd.extend_from_slice(&ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+        cd.extend_from_slice(&0u32.to_le_bytes());
+        cd.extend_from_slice(&record_start.to_le_bytes());
+        cd.extend_from_slice(&1u32.to_le_bytes());
+    }
+    let entries_u16 = entries.min(0xFFFF) as u16;
+    cd.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+    cd.extend_from_slice(&0u16.to_le_bytes());
+    cd.extend_from_slice(&0u16.to_le_bytes());
+    cd.extend_from_slice(&entries_u16.to_le_bytes());
+    cd.extend_from_slice(&entries_u16.to_le_bytes());
+    cd.extend_from_slice(&(cd_len.min(u64::from(u32::MAX)) as u32).to_le_bytes());
+    cd.extend_from_slice(&(cd_start.min(u64::from(u32::MAX)) as u32).to_le_bytes());
+    cd.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+    cd.extend_from_slice(&comment);
+
+    reader.truncate_and_append(cd_start, cd);
+    Ok(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Read, Write},
+        path::Path,
+    };
+
+    use super::{archive_path, open, VolumeSet};
+    use crate::app::test_utils::empty_fs;
+
+    fn zip_bytes(files: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, data) in files.iter() {
+            writer.start_file(
+                *name,
+                zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored),
+            )?;
+            writer.write_all(data)?;
+        }
+        Ok(writer.finish()?.into_inner())
+    }
+
+    fn read_zip(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+        let mut archive = zip::ZipArchive::new(open(path)?)?;
+        let mut files = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            files.push((file.name().to_string(), data));
+        }
+        Ok(files)
+    }
+
+    #[test]
+    fn volume_names() {
+        assert_eq!(
+            archive_path(Path::new("/a/data.tar.gz.003")),
+            Path::new("/a/data.tar.gz")
+        );
+        assert_eq!(
+            archive_path(Path::new("/a/data.tar.gz.ab")),
+            Path::new("/a/data.tar.gz")
+        );
+        assert_eq!(
+            archive_path(Path::new("/a/data.z02")),
+            Path::new("/a/data.zip")
+        );
+        // Other files with numbered or two letter extensions are left alone
+        assert_eq!(
+            archive_path(Path::new("/a/notes.001")),
+            Path::new("/a/notes.001")
+        );
+        assert_eq!(
+            archive_path(Path::new("/a/notes.md")),
+            Path::new("/a/notes.md")
+        );
+    }
+
+    #[test]
+    fn raw_volumes() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let data = zip_bytes(&[("a.txt", b"first"), ("b.txt", &[7; 3000])])?;
+        for (i, chunk) in data.chunks(1000).enumerate() {
+            fs::write(fs.path().join(format!("data.zip.{:03}", i + 1)), chunk)?;
+        }
+
+        let files = read_zip(&fs.path().join("data.zip.002"))?;
+        assert_eq!(files[0], ("a.txt".to_string(), b"first".to_vec()));
+        assert_eq!(files[1], ("b.txt".to_string(), vec![7; 3000]));
+
+        // Gaps are reported by name
+        fs::remove_file(fs.path().join("data.zip.002"))?;
+        let err = open(&fs.path().join("data.zip.001")).err().unwrap();
+        assert!(err.to_string().contains("data.zip.002"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_zip() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let data = zip_bytes(&[("a.txt", &[7; 3000]), ("b.txt", b"second")])?;
+
+        // Split like zip tools, with a signature first and offsets relative to each volume
+        let archive = zip::ZipArchive::new(io::Cursor::new(data.clone()))?;
+        let cd_start = archive.central_directory_start() as usize;
+        let split = 1000;
+        let mut first = b"PK\x07\x08".to_vec();
+        first.extend_from_slice(&data[..split]);
+        fs::write(fs.path().join("data.z01"), first)?;
+        let mut last = data[split..].to_vec();
+        let mut pos = cd_start - split;
+        while u32::from_le_bytes(last[pos..pos + 4].try_into().unwrap()) == 0x02014b50 {
+            let offset = u32::from_le_bytes(last[pos + 42..pos + 46].try_into().unwrap()) as usize;
+            let (disk, offset) = if offset < split {
+                (0u16, offset + 4)
+            } else {
+                (1, offset - split)
+            };
+            last[pos + 34..pos + 36].copy_from_slice(&disk.to_le_bytes());
+            last[pos + 42..pos + 46].copy_from_slice(&(offset as u32).to_le_bytes());
+            let lens: usize = [28, 30, 32]
+                .iter()
+                .map(|i| u16::from_le_bytes([last[pos + i], last[pos + i + 1]]) as usize)
+                .sum();
+            pos += 46 + lens;
+        }
+        last[pos + 4..pos + 6].copy_from_slice(&1u16.to_le_bytes());
+        last[pos + 6..pos + 8].copy_from_slice(&1u16.to_le_bytes());
+        last[pos + 16..pos + 20].copy_from_slice(&((cd_start - split) as u32).to_le_bytes());
+        fs::write(fs.path().join("data.zip"), &last)?;
+
+        for name in ["data.z01", "data.zip"] {
+            let files = read_zip(&fs.path().join(name))?;
+            assert_eq!(files[0], ("a.txt".to_string(), vec![7; 3000]));
+            assert_eq!(files[1], ("b.txt".to_string(), b"second".to_vec()));
+        }
+
+        // Central directories on missing volumes or past the end are rejected
+        for (field, value) in [
+            (6, &5u16.to_le_bytes()[..]),
+            (12, &u32::MAX.to_le_bytes()[..]),
+        ] {
+            let mut corrupt = last.clone();
+            corrupt[pos + field..pos + field + value.len()].copy_from_slice(value);
+            fs::write(fs.path().join("data.zip"), corrupt)?;
+            assert!(open(&fs.path().join("data.zip")).is_err());
+        }
+        fs::write(fs.path().join("data.zip"), &last)?;
+
+        // The last volume records how many volumes there are
+        fs::rename(fs.path().join("data.z01"), fs.path().join("data.z02"))?;
+        assert!(VolumeSet::from_path(&fs.path().join("data.zip")).is_none());
+        let err = open(&fs.path().join("data.z02")).err().unwrap();
+        assert!(err.to_string().contains("data.z01"), "{}", err);
+
+        Ok(())
+    }
+}